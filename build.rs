@@ -24,10 +24,72 @@ mod doc_gen {
     use std::io::Write;
 
     // Rhai modules in the `rhai-fs` package.
+    // Each is included into its own module, mirroring `lib.rs`, so their imports don't clash.
+    #[allow(dead_code)]
     mod pkg {
-        include!("src/path.rs");
-        include!("src/file.rs");
-        include!("src/dir.rs");
+        pub mod config {
+            include!("src/config.rs");
+        }
+        pub mod encoding {
+            include!("src/encoding.rs");
+        }
+        pub mod path {
+            include!("src/path.rs");
+        }
+        pub mod file {
+            include!("src/file.rs");
+        }
+        pub mod dir {
+            include!("src/dir.rs");
+        }
+        pub mod diff {
+            include!("src/diff.rs");
+        }
+        pub mod grep {
+            include!("src/grep.rs");
+        }
+        pub mod ini {
+            include!("src/ini.rs");
+        }
+        pub mod binary {
+            include!("src/binary.rs");
+        }
+        pub mod env {
+            include!("src/env.rs");
+        }
+        #[cfg(any(
+            feature = "sha256",
+            feature = "sha1",
+            feature = "md5",
+            feature = "blake3",
+            feature = "crc32"
+        ))]
+        pub mod hash {
+            include!("src/hash.rs");
+        }
+        pub mod lockfile {
+            include!("src/lockfile.rs");
+        }
+        #[cfg(any(feature = "gzip", feature = "zstd", feature = "bzip2"))]
+        pub mod compress {
+            include!("src/compress.rs");
+        }
+        #[cfg(any(feature = "tar", feature = "zip"))]
+        pub mod archive {
+            include!("src/archive.rs");
+        }
+        #[cfg(any(feature = "json", feature = "toml", feature = "yaml", feature = "csv"))]
+        pub mod data {
+            include!("src/data.rs");
+        }
+        #[cfg(feature = "mmap")]
+        pub mod mmap {
+            include!("src/mmap.rs");
+        }
+        #[cfg(feature = "watch")]
+        pub mod watch {
+            include!("src/watch.rs");
+        }
     }
 
     #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -59,6 +121,12 @@ mod doc_gen {
                 .replace("&mut ", "")
                 .replace(" -> ()", "")
                 .replace("ImmutableString", "String")
+                .replace("PathBuf", "Path")
+                .replace("SharedFile", "File")
+                .replace("SharedLockFile", "LockFile")
+                .replace("SharedCompressedFile", "CompressedFile")
+                .replace("SharedMmap", "MappedFile")
+                .replace("SharedWatcher", "Watcher")
         }
 
         pub fn fmt_doc_comments(&self) -> Option<String> {
@@ -126,9 +194,62 @@ mod doc_gen {
     pub fn generate_doc(writer: &mut impl Write) {
         let mut engine = Engine::new();
         let mut fs_module = Module::new();
-        combine_with_exported_module!(&mut fs_module, "rhai_fs_path", pkg::path_functions);
-        combine_with_exported_module!(&mut fs_module, "rhai_file_path", pkg::file_functions);
-        combine_with_exported_module!(&mut fs_module, "rhai_dir_path", pkg::dir_functions);
+        combine_with_exported_module!(&mut fs_module, "rhai_fs_path", pkg::path::path_functions);
+        combine_with_exported_module!(&mut fs_module, "rhai_file_path", pkg::file::file_functions);
+        combine_with_exported_module!(&mut fs_module, "rhai_dir_path", pkg::dir::dir_functions);
+        combine_with_exported_module!(&mut fs_module, "rhai_diff_path", pkg::diff::diff_functions);
+        combine_with_exported_module!(&mut fs_module, "rhai_grep_path", pkg::grep::grep_functions);
+        combine_with_exported_module!(&mut fs_module, "rhai_ini_path", pkg::ini::ini_functions);
+        combine_with_exported_module!(
+            &mut fs_module,
+            "rhai_binary_path",
+            pkg::binary::binary_functions
+        );
+        combine_with_exported_module!(&mut fs_module, "rhai_env_path", pkg::env::env_functions);
+        combine_with_exported_module!(
+            &mut fs_module,
+            "rhai_lockfile_path",
+            pkg::lockfile::lockfile_functions
+        );
+        #[cfg(any(
+            feature = "sha256",
+            feature = "sha1",
+            feature = "md5",
+            feature = "blake3",
+            feature = "crc32"
+        ))]
+        combine_with_exported_module!(&mut fs_module, "rhai_hash_path", pkg::hash::hash_functions);
+        #[cfg(any(feature = "gzip", feature = "zstd", feature = "bzip2"))]
+        combine_with_exported_module!(
+            &mut fs_module,
+            "rhai_compress_path",
+            pkg::compress::compress_functions
+        );
+        #[cfg(any(feature = "tar", feature = "zip"))]
+        combine_with_exported_module!(
+            &mut fs_module,
+            "rhai_archive_path",
+            pkg::archive::archive_functions
+        );
+        #[cfg(any(feature = "json", feature = "toml", feature = "yaml", feature = "csv"))]
+        combine_with_exported_module!(&mut fs_module, "rhai_data_path", pkg::data::data_functions);
+        #[cfg(feature = "mmap")]
+        combine_with_exported_module!(&mut fs_module, "rhai_mmap_path", pkg::mmap::mmap_functions);
+        #[cfg(feature = "watch")]
+        combine_with_exported_module!(
+            &mut fs_module,
+            "rhai_watch_path",
+            pkg::watch::watch_functions
+        );
+        fs_module.set_custom_type::<std::path::PathBuf>("Path");
+        fs_module.set_custom_type::<pkg::file::SharedFile>("File");
+        fs_module.set_custom_type::<pkg::lockfile::SharedLockFile>("LockFile");
+        #[cfg(any(feature = "gzip", feature = "zstd", feature = "bzip2"))]
+        fs_module.set_custom_type::<pkg::compress::SharedCompressedFile>("CompressedFile");
+        #[cfg(feature = "mmap")]
+        fs_module.set_custom_type::<pkg::mmap::SharedMmap>("MappedFile");
+        #[cfg(feature = "watch")]
+        fs_module.set_custom_type::<pkg::watch::SharedWatcher>("Watcher");
         engine.register_global_module(fs_module.into());
 
         // Extract metadata
@@ -3,17 +3,18 @@
 use std::path::Path;
 
 use rhai::{packages::Package, Engine, EvalAltResult};
-use rhai_fs::FilesystemPackage;
+use rhai_fs::{FilesystemConfig, FilesystemPackage};
 
 fn main() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
 
-    // Register our filesystem package.
-    let package = FilesystemPackage::new();
+    // Register our filesystem package, resolving relative paths against the examples directory.
+    let package = FilesystemPackage::with_config(FilesystemConfig {
+        cwd: Some(Path::new(env!("CARGO_MANIFEST_DIR")).join("examples")),
+        ..Default::default()
+    });
     package.register_into_engine_as(&mut engine, "fs");
 
-    std::env::set_current_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("examples")).unwrap();
-
     engine.run(
         r#"
     let file = fs::open_file("hello.txt"); 
@@ -3,17 +3,18 @@
 use std::path::{Path, PathBuf};
 
 use rhai::{packages::Package, Engine, EvalAltResult};
-use rhai_fs::FilesystemPackage;
+use rhai_fs::{FilesystemConfig, FilesystemPackage};
 
 fn main() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
 
-    // Register our filesystem package.
-    let package = FilesystemPackage::new();
+    // Register our filesystem package, resolving relative paths against the examples directory.
+    let package = FilesystemPackage::with_config(FilesystemConfig {
+        cwd: Some(Path::new(env!("CARGO_MANIFEST_DIR")).join("examples")),
+        ..Default::default()
+    });
     package.register_into_engine(&mut engine);
 
-    std::env::set_current_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("examples")).unwrap();
-
     engine.register_fn("path", sandboxed_path);
 
     engine.run(
@@ -28,7 +29,11 @@ fn main() -> Result<(), Box<EvalAltResult>> {
 }
 
 fn sandboxed_path(str_path: &str) -> Result<PathBuf, Box<EvalAltResult>> {
-    let root_path = PathBuf::from("sandbox").canonicalize().unwrap();
+    let root_path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("examples")
+        .join("sandbox")
+        .canonicalize()
+        .unwrap();
     let mut path = PathBuf::from(str_path);
 
     if path.is_relative() {
@@ -36,7 +41,7 @@ fn sandboxed_path(str_path: &str) -> Result<PathBuf, Box<EvalAltResult>> {
     }
 
     match path.canonicalize() {
-        Ok(p) => p.starts_with(root_path).then(|| path),
+        Ok(p) => p.starts_with(root_path).then_some(path),
         Err(e) => return Err(e.to_string().into()),
     }
     .ok_or_else(|| "Path out of bounds".into())
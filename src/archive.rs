@@ -0,0 +1,838 @@
+#[allow(unused_imports)]
+use rhai::plugin::*;
+use rhai::{Array, Dynamic, Map};
+
+use std::fs::{File, Metadata};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Component, Path, PathBuf};
+
+use super::config::resolve;
+use super::file::{convert_to_int, create_temp_beside};
+
+/// Magic bytes at the start of a zip archive, and of an empty one.
+const ZIP_MAGIC: [&[u8]; 2] = [b"PK\x03\x04", b"PK\x05\x06"];
+/// Magic bytes at the start of a gzip stream.
+const GZIP_MAGIC: &[u8] = &[0x1F, 0x8B];
+
+/// An archive format, each behind a cargo feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    /// Uncompressed tar.
+    #[cfg(feature = "tar")]
+    Tar,
+    /// gzip compressed tar.
+    #[cfg(all(feature = "tar", feature = "gzip"))]
+    TarGz,
+    /// zip.
+    #[cfg(feature = "zip")]
+    Zip,
+}
+
+impl Format {
+    /// Looks up a format by name, ignoring case.
+    ///
+    /// Throws an exception when the format is unknown or its cargo features aren't enabled.
+    fn from_name(name: &str) -> Result<Self, Box<EvalAltResult>> {
+        // The second set of arms only matches formats whose features are disabled.
+        #[allow(unreachable_patterns)]
+        let features = match name.to_ascii_lowercase().as_str() {
+            #[cfg(feature = "tar")]
+            "tar" => return Ok(Format::Tar),
+            #[cfg(all(feature = "tar", feature = "gzip"))]
+            "tar.gz" | "tgz" => return Ok(Format::TarGz),
+            #[cfg(feature = "zip")]
+            "zip" => return Ok(Format::Zip),
+            "tar" => "`tar` feature",
+            "tar.gz" | "tgz" => "`tar` and `gzip` features",
+            "zip" => "`zip` feature",
+            _ => return Err(format!("Unknown archive format {name}").into()),
+        };
+        Err(format!("Archive format {name} is not enabled, it requires the {features}").into())
+    }
+
+    /// Detects the format of an archive from its first bytes, anything that isn't zip or gzip is taken to be tar.
+    fn detect(head: &[u8]) -> Result<Self, Box<EvalAltResult>> {
+        if ZIP_MAGIC.iter().any(|magic| head.starts_with(magic)) {
+            Format::from_name("zip")
+        } else if head.starts_with(GZIP_MAGIC) {
+            Format::from_name("tar.gz")
+        } else {
+            Format::from_name("tar")
+        }
+    }
+}
+
+/// The kind of an archive entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EntryKind {
+    /// A regular file.
+    File,
+    /// A directory.
+    Dir,
+    /// A symbolic link.
+    Symlink,
+    /// A hard link to an earlier entry, only found in tar archives.
+    #[cfg_attr(not(feature = "tar"), allow(dead_code))]
+    Hardlink,
+    /// Anything else, e.g. a device node, which is listed but never extracted.
+    #[cfg_attr(not(feature = "tar"), allow(dead_code))]
+    Other,
+}
+
+impl EntryKind {
+    /// Returns the name scripts see in the `kind` field.
+    fn name(self) -> &'static str {
+        match self {
+            EntryKind::File => "file",
+            EntryKind::Dir => "dir",
+            EntryKind::Symlink => "symlink",
+            EntryKind::Hardlink => "hardlink",
+            EntryKind::Other => "other",
+        }
+    }
+}
+
+/// An entry read from an archive.
+struct Entry {
+    /// The path as stored in the archive.
+    name: String,
+    /// What the entry is.
+    kind: EntryKind,
+    /// The size of the content, in bytes.
+    size: u64,
+    /// The Unix permissions, if the archive has them.
+    mode: Option<u32>,
+    /// The target of a link, as stored in the archive.
+    link: Option<String>,
+}
+
+impl Entry {
+    /// Converts the entry into the map handed to scripts.
+    fn into_map(self) -> Result<Map, Box<EvalAltResult>> {
+        let mut map = Map::new();
+        map.insert("path".into(), self.name.into());
+        map.insert("kind".into(), self.kind.name().into());
+        map.insert("size".into(), convert_to_int(self.size)?.into());
+        if let Some(link) = self.link {
+            map.insert("target".into(), link.into());
+        }
+        Ok(map)
+    }
+
+    /// Returns the path to extract the entry to relative to the destination, `None` if nothing is left after stripping.
+    ///
+    /// Throws an exception when the entry or the target of a link would end up outside of the destination.
+    fn checked_path(&self, strip: usize) -> Result<Option<PathBuf>, Box<EvalAltResult>> {
+        let Some(path) = entry_path(&self.name, strip)? else {
+            return Ok(None);
+        };
+        match (self.kind, &self.link) {
+            (EntryKind::Symlink, Some(target)) => check_symlink(&self.name, &path, target)?,
+            (EntryKind::Hardlink, Some(target)) if entry_path(target, strip)?.is_none() => {
+                return Err(format!("Archive entry {} links outside the archive", self.name).into())
+            }
+            (EntryKind::Symlink | EntryKind::Hardlink, None) => {
+                return Err(
+                    format!("Archive entry {} is a link without a target", self.name).into(),
+                )
+            }
+            _ => (),
+        }
+        Ok(Some(path))
+    }
+}
+
+/// Returns the error for an entry that would end up outside of the destination.
+fn escapes(name: &str) -> Box<EvalAltResult> {
+    format!("Archive entry {name} escapes the destination directory").into()
+}
+
+/// Turns an archive path into a relative path, dropping the first `strip` components and `None` if nothing is left.
+///
+/// Throws an exception when the path is absolute or contains `..`.
+fn entry_path(name: &str, strip: usize) -> Result<Option<PathBuf>, Box<EvalAltResult>> {
+    if name.starts_with(['/', '\\']) {
+        return Err(escapes(name));
+    }
+
+    let mut path = PathBuf::new();
+    for part in name
+        .split(['/', '\\'])
+        .filter(|part| !matches!(*part, "" | "."))
+    {
+        // Also catches Windows prefixes such as `C:`.
+        let mut components = Path::new(part).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(part)), None) => path.push(part),
+            _ => return Err(escapes(name)),
+        }
+    }
+
+    let path: PathBuf = path.components().skip(strip).collect();
+    Ok(Some(path).filter(|path| !path.as_os_str().is_empty()))
+}
+
+/// Checks that a symlink extracted to `path` points to somewhere inside the destination.
+fn check_symlink(name: &str, path: &Path, target: &str) -> Result<(), Box<EvalAltResult>> {
+    if target.starts_with(['/', '\\']) {
+        return Err(escapes(name));
+    }
+
+    // Targets are relative to the directory containing the link.
+    let mut depth = path.components().count() - 1;
+    for part in target.split(['/', '\\']) {
+        match part {
+            "" | "." => (),
+            ".." => depth = depth.checked_sub(1).ok_or_else(|| escapes(name))?,
+            part => match Path::new(part).components().next() {
+                Some(Component::Normal(_)) => depth += 1,
+                _ => return Err(escapes(name)),
+            },
+        }
+    }
+    Ok(())
+}
+
+/// Checks that a symlink created in `parent`, a real directory inside `root`, leads to somewhere inside `root`.
+///
+/// Unlike `check_symlink` this looks at what is on disk, as the link may be reached through symlinks extracted before it.
+/// `..` is only followed through real directories, since links and paths that don't exist yet may still change while extracting.
+fn check_symlink_on_disk(
+    root: &Path,
+    parent: &Path,
+    name: &str,
+    target: &str,
+) -> Result<(), Box<EvalAltResult>> {
+    let mut path = parent.to_path_buf();
+    let mut real = true;
+    for part in target.split(['/', '\\']) {
+        match part {
+            "" | "." => (),
+            ".." if real && path != root => {
+                path.pop();
+            }
+            ".." => return Err(escapes(name)),
+            part => {
+                path.push(part);
+                if !real {
+                    continue;
+                }
+                match std::fs::symlink_metadata(&path) {
+                    Ok(meta) if meta.is_dir() => (),
+                    Ok(meta) if meta.is_symlink() => {
+                        real = false;
+                        // Dangling links are checked when something is extracted where they lead.
+                        if path
+                            .canonicalize()
+                            .is_ok_and(|real| !real.starts_with(root))
+                        {
+                            return Err(escapes(name));
+                        }
+                    }
+                    _ => real = false,
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Receives each entry of an archive together with a reader for its content.
+type EntryVisitor<'a> = dyn FnMut(Entry, &mut dyn Read) -> Result<(), Box<EvalAltResult>> + 'a;
+
+/// Calls `f` with every entry of the archive at the path and a reader for its content, detecting the format.
+fn read_entries(path: &Path, f: &mut EntryVisitor) -> Result<(), Box<EvalAltResult>> {
+    let mut file = File::open(path).map_err(|e| format!("{}: {e}", path.to_string_lossy()))?;
+    let mut head = Vec::with_capacity(4);
+    (&file)
+        .take(4)
+        .read_to_end(&mut head)
+        .and_then(|_| file.seek(SeekFrom::Start(0)))
+        .map_err(|e| e.to_string())?;
+
+    match Format::detect(&head)? {
+        #[cfg(feature = "tar")]
+        Format::Tar => read_tar(file, f),
+        #[cfg(all(feature = "tar", feature = "gzip"))]
+        Format::TarGz => read_tar(flate2::read::GzDecoder::new(file), f),
+        #[cfg(feature = "zip")]
+        Format::Zip => read_zip(file, f),
+    }
+}
+
+/// Reads the entries of a tar archive.
+#[cfg(feature = "tar")]
+fn read_tar(reader: impl Read, f: &mut EntryVisitor) -> Result<(), Box<EvalAltResult>> {
+    use tar::EntryType;
+
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries().map_err(|e| e.to_string())? {
+        let mut entry = entry.map_err(|e| e.to_string())?;
+        let header = entry.header();
+        let kind = match header.entry_type() {
+            // Global pax headers only hold metadata for the entries that follow.
+            EntryType::XGlobalHeader => continue,
+            EntryType::Regular | EntryType::Continuous | EntryType::GNUSparse => EntryKind::File,
+            EntryType::Directory => EntryKind::Dir,
+            EntryType::Symlink => EntryKind::Symlink,
+            EntryType::Link => EntryKind::Hardlink,
+            _ => EntryKind::Other,
+        };
+        let info = Entry {
+            name: String::from_utf8_lossy(&entry.path_bytes()).into_owned(),
+            kind,
+            size: entry.size(),
+            mode: header.mode().ok(),
+            link: entry
+                .link_name_bytes()
+                .map(|link| String::from_utf8_lossy(&link).into_owned()),
+        };
+        f(info, &mut entry)?;
+    }
+    Ok(())
+}
+
+/// Reads the entries of a zip archive.
+#[cfg(feature = "zip")]
+fn read_zip(file: File, f: &mut EntryVisitor) -> Result<(), Box<EvalAltResult>> {
+    let mut archive = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
+    for idx in 0..archive.len() {
+        let mut file = archive.by_index(idx).map_err(|e| e.to_string())?;
+        let (kind, link) = if file.is_dir() {
+            (EntryKind::Dir, None)
+        } else if file.is_symlink() {
+            // Symlinks store their target as content.
+            let mut target = String::new();
+            file.read_to_string(&mut target)
+                .map_err(|e| format!("{}: {e}", file.name()))?;
+            (EntryKind::Symlink, Some(target))
+        } else {
+            (EntryKind::File, None)
+        };
+        let info = Entry {
+            name: file.name().to_string(),
+            kind,
+            size: file.size(),
+            mode: file.unix_mode(),
+            link,
+        };
+        f(info, &mut file)?;
+    }
+    Ok(())
+}
+
+/// Options accepted by `archive_extract`.
+struct ExtractOptions {
+    /// Whether existing files are replaced.
+    overwrite: bool,
+    /// Number of leading path components removed from every entry.
+    strip_components: usize,
+}
+
+impl ExtractOptions {
+    /// Reads the options from a script map, throwing on unknown keys and values of the wrong type.
+    fn from_map(map: &Map) -> Result<Self, Box<EvalAltResult>> {
+        let mut options = ExtractOptions {
+            overwrite: false,
+            strip_components: 0,
+        };
+
+        for (key, value) in map {
+            match key.as_str() {
+                "overwrite" => {
+                    options.overwrite = value
+                        .as_bool()
+                        .map_err(|_| "Extract option overwrite must be a bool")?
+                }
+                "strip_components" => {
+                    options.strip_components = value
+                        .as_int()
+                        .ok()
+                        .and_then(|strip| usize::try_from(strip).ok())
+                        .ok_or("Extract option strip_components must be a positive integer")?
+                }
+                _ => return Err(format!("Unknown extract option {key}").into()),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// Creates the directories of a relative path inside `root`, returning the real path of the innermost one.
+///
+/// Existing symlinks are only followed when they lead to a directory inside `root`, which must be canonical.
+fn ensure_dir(root: &Path, rel: &Path) -> Result<PathBuf, Box<EvalAltResult>> {
+    let mut dir = root.to_path_buf();
+    for part in rel.components() {
+        let next = dir.join(part);
+        match std::fs::symlink_metadata(&next) {
+            Ok(meta) if meta.is_dir() => dir = next,
+            Ok(meta) if meta.is_symlink() => {
+                let real = next
+                    .canonicalize()
+                    .ok()
+                    .filter(|real| real.starts_with(root) && real.is_dir());
+                dir = real.ok_or_else(|| {
+                    format!(
+                        "{}: symlink leads outside of the destination",
+                        next.to_string_lossy()
+                    )
+                })?;
+            }
+            Ok(_) => return Err(format!("{}: not a directory", next.to_string_lossy()).into()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                std::fs::create_dir(&next)
+                    .map_err(|e| format!("{}: {e}", next.to_string_lossy()))?;
+                dir = next;
+            }
+            Err(e) => return Err(format!("{}: {e}", next.to_string_lossy()).into()),
+        }
+    }
+    Ok(dir)
+}
+
+/// Returns where to create the entry at the relative path, removing what's there when overwriting.
+///
+/// A symlink in the way is replaced itself, never written through.
+fn prepare_target(root: &Path, rel: &Path, overwrite: bool) -> Result<PathBuf, Box<EvalAltResult>> {
+    let parent = ensure_dir(root, rel.parent().unwrap_or(Path::new("")))?;
+    let target = parent.join(rel.file_name().unwrap_or_default());
+    if let Ok(meta) = std::fs::symlink_metadata(&target) {
+        let display = target.to_string_lossy();
+        if !overwrite {
+            return Err(format!("{display}: already exists").into());
+        }
+        if meta.is_dir() {
+            return Err(format!("{display}: is a directory").into());
+        }
+        std::fs::remove_file(&target).map_err(|e| format!("{display}: {e}"))?;
+    }
+    Ok(target)
+}
+
+/// Writes one entry below `root`.
+fn extract_entry(
+    root: &Path,
+    rel: &Path,
+    entry: &Entry,
+    reader: &mut dyn Read,
+    options: &ExtractOptions,
+) -> Result<(), Box<EvalAltResult>> {
+    match entry.kind {
+        EntryKind::Dir => {
+            ensure_dir(root, rel)?;
+        }
+        EntryKind::File => {
+            let target = prepare_target(root, rel, options.overwrite)?;
+            let display = target.to_string_lossy();
+            let mut file = File::create_new(&target).map_err(|e| format!("{display}: {e}"))?;
+            std::io::copy(reader, &mut file).map_err(|e| format!("{display}: {e}"))?;
+            #[cfg(unix)]
+            if let Some(mode) = entry.mode {
+                use std::os::unix::fs::PermissionsExt;
+                // Only the permission bits, never setuid and friends.
+                file.set_permissions(std::fs::Permissions::from_mode(mode & 0o777))
+                    .map_err(|e| format!("{display}: {e}"))?;
+            }
+        }
+        EntryKind::Symlink => {
+            let link = entry.link.as_deref().unwrap_or_default();
+            let parent = ensure_dir(root, rel.parent().unwrap_or(Path::new("")))?;
+            check_symlink_on_disk(root, &parent, &entry.name, link)?;
+            let target = prepare_target(root, rel, options.overwrite)?;
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(link, &target)
+                .map_err(|e| format!("{}: {e}", target.to_string_lossy()))?;
+            #[cfg(not(unix))]
+            return Err(format!(
+                "{}: symlinks to {link} can only be extracted on Unix",
+                target.to_string_lossy()
+            )
+            .into());
+        }
+        EntryKind::Hardlink => {
+            let source = entry_path(
+                entry.link.as_deref().unwrap_or_default(),
+                options.strip_components,
+            )?
+            .unwrap_or_default();
+            let source = ensure_dir(root, source.parent().unwrap_or(Path::new("")))?
+                .join(source.file_name().unwrap_or_default());
+            let target = prepare_target(root, rel, options.overwrite)?;
+            std::fs::hard_link(&source, &target)
+                .map_err(|e| format!("{}: {e}", target.to_string_lossy()))?;
+        }
+        EntryKind::Other => (),
+    }
+    Ok(())
+}
+
+/// A file, directory or symlink to add to an archive.
+struct Source {
+    /// Where it is on disk.
+    path: PathBuf,
+    /// The path to store it under.
+    name: String,
+    /// Its metadata, without following symlinks.
+    #[cfg_attr(not(feature = "zip"), allow(dead_code))]
+    meta: Metadata,
+}
+
+/// Returns the name a path passed to `archive_create` is stored under.
+///
+/// Relative paths are kept, absolute ones are stored under their file name.
+fn source_name(path: &Path) -> Result<String, Box<EvalAltResult>> {
+    let display = path.to_string_lossy();
+    let components: Vec<_> = if path.is_absolute() {
+        path.file_name().into_iter().collect()
+    } else {
+        path.components()
+            .filter(|c| !matches!(c, Component::CurDir))
+            .map(|c| match c {
+                Component::Normal(part) => Ok(part),
+                _ => Err(format!("{display}: paths with .. can't be archived")),
+            })
+            .collect::<Result<_, _>>()?
+    };
+    components
+        .into_iter()
+        .map(|part| {
+            part.to_str()
+                .ok_or_else(|| format!("{display}: path isn't valid unicode").into())
+        })
+        .collect::<Result<Vec<_>, Box<EvalAltResult>>>()
+        .map(|parts| parts.join("/"))
+}
+
+/// Adds the path and, for directories, everything below it in name order, skipping the files of the archive being written.
+fn collect_sources(
+    path: PathBuf,
+    name: String,
+    skip: &[Option<PathBuf>],
+    sources: &mut Vec<Source>,
+) -> Result<(), Box<EvalAltResult>> {
+    let display = path.to_string_lossy().into_owned();
+    let meta = std::fs::symlink_metadata(&path).map_err(|e| format!("{display}: {e}"))?;
+    if meta.is_file()
+        && path
+            .canonicalize()
+            .is_ok_and(|real| skip.iter().flatten().any(|skip| *skip == real))
+    {
+        return Ok(());
+    }
+
+    if meta.is_dir() {
+        let mut children = std::fs::read_dir(&path)
+            .and_then(|dir| dir.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| format!("{display}: {e}"))?;
+        children.sort_by_key(|child| child.file_name());
+
+        // An empty name stands for the contents of the directory, e.g. when archiving `.`.
+        let prefix = if name.is_empty() {
+            String::new()
+        } else {
+            format!("{name}/")
+        };
+        if !name.is_empty() {
+            sources.push(Source { path, name, meta });
+        }
+        for child in children {
+            let file_name = child.file_name();
+            let file_name = file_name.to_str().ok_or_else(|| {
+                format!(
+                    "{}: path isn't valid unicode",
+                    child.path().to_string_lossy()
+                )
+            })?;
+            collect_sources(child.path(), format!("{prefix}{file_name}"), skip, sources)?;
+        }
+    } else if !name.is_empty() {
+        sources.push(Source { path, name, meta });
+    }
+    Ok(())
+}
+
+/// Writes the sources to a tar archive, returning the underlying writer.
+#[cfg(feature = "tar")]
+fn write_tar<W: std::io::Write>(writer: W, sources: &[Source]) -> Result<W, Box<EvalAltResult>> {
+    let mut builder = tar::Builder::new(writer);
+    builder.follow_symlinks(false);
+    for source in sources {
+        builder
+            .append_path_with_name(&source.path, &source.name)
+            .map_err(|e| format!("{}: {e}", source.path.to_string_lossy()))?;
+    }
+    builder.into_inner().map_err(|e| e.to_string().into())
+}
+
+/// Writes the sources to a zip archive, compressing files with deflate.
+#[cfg(feature = "zip")]
+fn write_zip(file: &File, sources: &[Source]) -> Result<(), Box<EvalAltResult>> {
+    use zip::write::SimpleFileOptions;
+
+    let mut zip = zip::ZipWriter::new(file);
+    for source in sources {
+        let display = source.path.to_string_lossy();
+        let mut options = SimpleFileOptions::default();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            options = options.unix_permissions(source.meta.permissions().mode() & 0o777);
+        }
+
+        let res = if source.meta.is_dir() {
+            zip.add_directory(source.name.as_str(), options)
+        } else if source.meta.is_symlink() {
+            let target = std::fs::read_link(&source.path).map_err(|e| format!("{display}: {e}"))?;
+            zip.add_symlink(source.name.as_str(), target.to_string_lossy(), options)
+        } else if source.meta.is_file() {
+            let options = options
+                .compression_method(zip::CompressionMethod::Deflated)
+                .large_file(source.meta.len() >= u32::MAX as u64);
+            zip.start_file(source.name.as_str(), options)
+                .and_then(|()| {
+                    let mut file = File::open(&source.path)?;
+                    std::io::copy(&mut file, &mut zip)?;
+                    Ok(())
+                })
+        } else {
+            return Err(
+                format!("{display}: only files, directories and symlinks can be zipped").into(),
+            );
+        };
+        res.map_err(|e| format!("{display}: {e}"))?;
+    }
+    zip.finish().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Writes the sources to a new archive file in the format.
+fn write_archive(
+    format: Format,
+    file: &File,
+    sources: &[Source],
+) -> Result<(), Box<EvalAltResult>> {
+    match format {
+        #[cfg(feature = "tar")]
+        Format::Tar => write_tar(file, sources).map(drop),
+        #[cfg(all(feature = "tar", feature = "gzip"))]
+        Format::TarGz => {
+            let encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+            write_tar(encoder, sources)?
+                .finish()
+                .map(drop)
+                .map_err(|e| e.to_string().into())
+        }
+        #[cfg(feature = "zip")]
+        Format::Zip => write_zip(file, sources),
+    }
+}
+
+#[export_module]
+pub mod archive_functions {
+    /// Lists the entries of a tar, tar.gz or zip archive, detecting the format from the content.
+    ///
+    /// Returns an array of maps with:
+    /// - `path`: the path as stored in the archive.
+    /// - `kind`: `"file"`, `"dir"`, `"symlink"`, `"hardlink"` or `"other"`.
+    /// - `size`: the size of the content, in bytes.
+    /// - `target`: the target of a link, only present for links.
+    ///
+    /// Throws an exception when:
+    /// - The archive can't be read or is corrupt.
+    /// - The format's cargo feature isn't enabled, `tar` and `zip`, plus `gzip` for tar.gz.
+    #[rhai_fn(return_raw)]
+    pub fn archive_list(
+        ctx: NativeCallContext,
+        path: PathBuf,
+    ) -> Result<Array, Box<EvalAltResult>> {
+        let mut entries = Array::new();
+        read_entries(&resolve(&ctx, &path)?, &mut |entry, _| {
+            entries.push(entry.into_map()?.into());
+            Ok(())
+        })?;
+        Ok(entries)
+    }
+
+    /// Helper function for `archive_list` that takes a string instead of `PathBuf`.
+    #[rhai_fn(return_raw, name = "archive_list")]
+    pub fn archive_list_str(
+        ctx: NativeCallContext,
+        path_raw: ImmutableString,
+    ) -> Result<Array, Box<EvalAltResult>> {
+        let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+        archive_list(ctx, path)
+    }
+
+    /// Extracts an archive into the destination directory with the default options.
+    ///
+    /// See `archive_extract(path, dest, options)` for details.
+    #[rhai_fn(return_raw)]
+    pub fn archive_extract(
+        ctx: NativeCallContext,
+        path: PathBuf,
+        dest: PathBuf,
+    ) -> Result<Array, Box<EvalAltResult>> {
+        archive_extract_with_options(ctx, path, dest, Map::new())
+    }
+
+    /// Helper function for `archive_extract` that takes strings instead of `PathBuf`.
+    #[rhai_fn(return_raw, name = "archive_extract")]
+    pub fn archive_extract_str(
+        ctx: NativeCallContext,
+        path_raw: ImmutableString,
+        dest_raw: ImmutableString,
+    ) -> Result<Array, Box<EvalAltResult>> {
+        let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+        let dest = ctx.call_native_fn::<PathBuf>("path", (dest_raw,))?;
+        archive_extract(ctx, path, dest)
+    }
+
+    /// Extracts a tar, tar.gz or zip archive into the destination directory, creating it if needed, and returns the extracted paths.
+    ///
+    /// Nothing is written outside of the destination: every entry is checked before extracting,
+    /// and archives with absolute paths, `..` components or links pointing outside are rejected as a whole.
+    /// Links are checked again when extracted, as they may be reached through links extracted before them.
+    /// Existing symlinks inside the destination are only followed when they stay inside it.
+    /// Entries that aren't files, directories or links, e.g. device nodes, are skipped.
+    ///
+    /// Options:
+    /// - `overwrite`: replace existing files, defaults to `false`.
+    /// - `strip_components`: number of leading path components to remove from every entry, defaults to `0`.
+    ///   Entries with no components left are skipped.
+    ///
+    /// Throws an exception when:
+    /// - The archive can't be read or is corrupt.
+    /// - The format's cargo feature isn't enabled.
+    /// - An entry would end up outside of the destination.
+    /// - A file already exists and `overwrite` isn't set.
+    /// - An option is unknown or has the wrong type.
+    #[rhai_fn(return_raw, name = "archive_extract")]
+    pub fn archive_extract_with_options(
+        ctx: NativeCallContext,
+        path: PathBuf,
+        dest: PathBuf,
+        options: Map,
+    ) -> Result<Array, Box<EvalAltResult>> {
+        let options = ExtractOptions::from_map(&options)?;
+        let path = resolve(&ctx, &path)?;
+        let dest = resolve(&ctx, &dest)?;
+
+        // Check every entry first, so a malicious archive leaves nothing behind.
+        read_entries(&path, &mut |entry, _| {
+            entry.checked_path(options.strip_components).map(drop)
+        })?;
+
+        std::fs::create_dir_all(&dest).map_err(|e| format!("{}: {e}", dest.to_string_lossy()))?;
+        let root = dest
+            .canonicalize()
+            .map_err(|e| format!("{}: {e}", dest.to_string_lossy()))?;
+
+        let mut extracted = Array::new();
+        read_entries(&path, &mut |entry, reader| {
+            let Some(rel) = entry.checked_path(options.strip_components)? else {
+                return Ok(());
+            };
+            extract_entry(&root, &rel, &entry, reader, &options)?;
+            if entry.kind != EntryKind::Other {
+                extracted.push(Dynamic::from(dest.join(rel)));
+            }
+            Ok(())
+        })?;
+        Ok(extracted)
+    }
+
+    /// Helper function for `archive_extract` that takes strings instead of `PathBuf`.
+    #[rhai_fn(return_raw, name = "archive_extract")]
+    pub fn archive_extract_with_options_str(
+        ctx: NativeCallContext,
+        path_raw: ImmutableString,
+        dest_raw: ImmutableString,
+        options: Map,
+    ) -> Result<Array, Box<EvalAltResult>> {
+        let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+        let dest = ctx.call_native_fn::<PathBuf>("path", (dest_raw,))?;
+        archive_extract_with_options(ctx, path, dest, options)
+    }
+
+    /// Creates an archive in the format, `"tar"`, `"tar.gz"` or `"zip"`, from an array of files and directories, returning the number of entries written.
+    ///
+    /// Directories are added with everything below them, in name order.
+    /// Relative paths are stored as given, absolute ones under their file name, and `.` stores the contents of the working directory.
+    /// Symlinks are stored as links rather than followed. The archive itself is never added.
+    ///
+    /// Throws an exception when:
+    /// - The format is unknown or its cargo features aren't enabled.
+    /// - An element isn't a path or string, contains `..`, or can't be read.
+    /// - The archive can't be written, in which case no partial archive is left behind and an existing file at the path is kept.
+    #[rhai_fn(return_raw)]
+    pub fn archive_create(
+        ctx: NativeCallContext,
+        path: PathBuf,
+        files: Array,
+        format: &str,
+    ) -> Result<rhai::INT, Box<EvalAltResult>> {
+        let format = Format::from_name(format)?;
+        let mut inputs = Vec::with_capacity(files.len());
+        for file in files {
+            let given = match file.clone().try_cast::<PathBuf>() {
+                Some(path) => path,
+                None => match file.into_immutable_string() {
+                    Ok(path_raw) => ctx.call_native_fn::<PathBuf>("path", (path_raw,))?,
+                    Err(type_name) => {
+                        return Err(format!(
+                            "Expected a path or string to archive, got {type_name}"
+                        )
+                        .into())
+                    }
+                },
+            };
+            inputs.push((resolve(&ctx, &given)?, source_name(&given)?));
+        }
+
+        let path = resolve(&ctx, &path)?;
+        let display = path.to_string_lossy();
+        let err = |e: std::io::Error| format!("{display}: {e}");
+
+        // Write next to the output and rename over it at the end, so a failure leaves an existing file untouched.
+        // An existing symlink is followed so that the file it points to is replaced.
+        let existing = path.canonicalize().ok();
+        let output = existing.clone().unwrap_or_else(|| path.clone());
+        let (tmp, file) = create_temp_beside(&output).map_err(err)?;
+        let skip = [tmp.canonicalize().ok(), existing.clone()];
+
+        let res = inputs
+            .into_iter()
+            .try_fold(Vec::new(), |mut sources, (input, name)| {
+                collect_sources(input, name, &skip, &mut sources).map(|()| sources)
+            })
+            .and_then(|sources| {
+                write_archive(format, &file, &sources)?;
+                if let Some(existing) = &existing {
+                    let permissions = std::fs::metadata(existing).map_err(err)?.permissions();
+                    file.set_permissions(permissions).map_err(err)?;
+                }
+                file.sync_all()
+                    .and_then(|_| std::fs::rename(&tmp, &output))
+                    .map_err(err)?;
+                convert_to_int(sources.len())
+            });
+        if res.is_err() {
+            let _ = std::fs::remove_file(&tmp);
+        }
+        res
+    }
+
+    /// Helper function for `archive_create` that takes a string instead of `PathBuf`.
+    #[rhai_fn(return_raw, name = "archive_create")]
+    pub fn archive_create_str(
+        ctx: NativeCallContext,
+        path_raw: ImmutableString,
+        files: Array,
+        format: &str,
+    ) -> Result<rhai::INT, Box<EvalAltResult>> {
+        let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+        archive_create(ctx, path, files, format)
+    }
+}
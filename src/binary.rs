@@ -0,0 +1,637 @@
+#[allow(unused_imports)]
+use rhai::plugin::*;
+
+use std::io::prelude::*;
+use std::io::{ErrorKind, SeekFrom};
+
+use super::file::{borrow_mut, convert_to_int, SharedFile};
+
+/// Reads exactly `buf.len()` bytes, restoring the stream position if the end of the file is reached first.
+pub(crate) fn read_exact_or_rewind(
+    file: &SharedFile,
+    buf: &mut [u8],
+) -> Result<(), Box<EvalAltResult>> {
+    let mut file = borrow_mut(file);
+    let start = file.stream_position().map_err(|e| e.to_string())?;
+
+    match file.read_exact(buf) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+            file.seek(SeekFrom::Start(start))
+                .map_err(|e| e.to_string())?;
+            Err(format!("Unexpected end of file reading {} bytes", buf.len()).into())
+        }
+        Err(e) => Err(e.to_string().into()),
+    }
+}
+
+/// Reads a fixed number of bytes for decoding a number.
+fn read_array<const N: usize>(file: &SharedFile) -> Result<[u8; N], Box<EvalAltResult>> {
+    let mut buf = [0; N];
+    read_exact_or_rewind(file, &mut buf)?;
+    Ok(buf)
+}
+
+/// Writes all the bytes of an encoded number, returning the number of bytes written.
+fn write_bytes(file: &SharedFile, bytes: &[u8]) -> Result<rhai::INT, Box<EvalAltResult>> {
+    borrow_mut(file)
+        .write_all(bytes)
+        .map_err(|e| e.to_string())?;
+    convert_to_int(bytes.len())
+}
+
+/// Converts a Rhai integer into a fixed-size number, failing if it is out of range.
+fn to_number<T: TryFrom<rhai::INT>>(value: rhai::INT) -> Result<T, Box<EvalAltResult>> {
+    T::try_from(value).map_err(|_| {
+        format!(
+            "Number {value} is out of range for {}",
+            std::any::type_name::<T>()
+        )
+        .into()
+    })
+}
+
+#[export_module]
+pub mod binary_functions {
+    /// Reads an unsigned 8-bit integer from the current stream position.
+    ///
+    /// Throws an exception when:
+    /// - The end of the file is reached first, leaving the stream position unchanged.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn read_u8(file: &mut SharedFile) -> Result<rhai::INT, Box<EvalAltResult>> {
+        convert_to_int(u8::from_ne_bytes(read_array(file)?))
+    }
+
+    /// Reads a signed 8-bit integer from the current stream position.
+    ///
+    /// Throws an exception when:
+    /// - The end of the file is reached first, leaving the stream position unchanged.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn read_i8(file: &mut SharedFile) -> Result<rhai::INT, Box<EvalAltResult>> {
+        convert_to_int(i8::from_ne_bytes(read_array(file)?))
+    }
+
+    /// Reads a native-endian unsigned 16-bit integer from the current stream position.
+    ///
+    /// Throws an exception when:
+    /// - The end of the file is reached first, leaving the stream position unchanged.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn read_u16(file: &mut SharedFile) -> Result<rhai::INT, Box<EvalAltResult>> {
+        convert_to_int(u16::from_ne_bytes(read_array(file)?))
+    }
+
+    /// Reads a little-endian unsigned 16-bit integer from the current stream position.
+    ///
+    /// Throws an exception when:
+    /// - The end of the file is reached first, leaving the stream position unchanged.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn read_u16_le(file: &mut SharedFile) -> Result<rhai::INT, Box<EvalAltResult>> {
+        convert_to_int(u16::from_le_bytes(read_array(file)?))
+    }
+
+    /// Reads a big-endian unsigned 16-bit integer from the current stream position.
+    ///
+    /// Throws an exception when:
+    /// - The end of the file is reached first, leaving the stream position unchanged.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn read_u16_be(file: &mut SharedFile) -> Result<rhai::INT, Box<EvalAltResult>> {
+        convert_to_int(u16::from_be_bytes(read_array(file)?))
+    }
+
+    /// Reads a native-endian signed 16-bit integer from the current stream position.
+    ///
+    /// Throws an exception when:
+    /// - The end of the file is reached first, leaving the stream position unchanged.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn read_i16(file: &mut SharedFile) -> Result<rhai::INT, Box<EvalAltResult>> {
+        convert_to_int(i16::from_ne_bytes(read_array(file)?))
+    }
+
+    /// Reads a little-endian signed 16-bit integer from the current stream position.
+    ///
+    /// Throws an exception when:
+    /// - The end of the file is reached first, leaving the stream position unchanged.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn read_i16_le(file: &mut SharedFile) -> Result<rhai::INT, Box<EvalAltResult>> {
+        convert_to_int(i16::from_le_bytes(read_array(file)?))
+    }
+
+    /// Reads a big-endian signed 16-bit integer from the current stream position.
+    ///
+    /// Throws an exception when:
+    /// - The end of the file is reached first, leaving the stream position unchanged.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn read_i16_be(file: &mut SharedFile) -> Result<rhai::INT, Box<EvalAltResult>> {
+        convert_to_int(i16::from_be_bytes(read_array(file)?))
+    }
+
+    /// Reads a native-endian unsigned 32-bit integer from the current stream position.
+    ///
+    /// Throws an exception when:
+    /// - The end of the file is reached first, leaving the stream position unchanged.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn read_u32(file: &mut SharedFile) -> Result<rhai::INT, Box<EvalAltResult>> {
+        convert_to_int(u32::from_ne_bytes(read_array(file)?))
+    }
+
+    /// Reads a little-endian unsigned 32-bit integer from the current stream position.
+    ///
+    /// Throws an exception when:
+    /// - The end of the file is reached first, leaving the stream position unchanged.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn read_u32_le(file: &mut SharedFile) -> Result<rhai::INT, Box<EvalAltResult>> {
+        convert_to_int(u32::from_le_bytes(read_array(file)?))
+    }
+
+    /// Reads a big-endian unsigned 32-bit integer from the current stream position.
+    ///
+    /// Throws an exception when:
+    /// - The end of the file is reached first, leaving the stream position unchanged.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn read_u32_be(file: &mut SharedFile) -> Result<rhai::INT, Box<EvalAltResult>> {
+        convert_to_int(u32::from_be_bytes(read_array(file)?))
+    }
+
+    /// Reads a native-endian signed 32-bit integer from the current stream position.
+    ///
+    /// Throws an exception when:
+    /// - The end of the file is reached first, leaving the stream position unchanged.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn read_i32(file: &mut SharedFile) -> Result<rhai::INT, Box<EvalAltResult>> {
+        convert_to_int(i32::from_ne_bytes(read_array(file)?))
+    }
+
+    /// Reads a little-endian signed 32-bit integer from the current stream position.
+    ///
+    /// Throws an exception when:
+    /// - The end of the file is reached first, leaving the stream position unchanged.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn read_i32_le(file: &mut SharedFile) -> Result<rhai::INT, Box<EvalAltResult>> {
+        convert_to_int(i32::from_le_bytes(read_array(file)?))
+    }
+
+    /// Reads a big-endian signed 32-bit integer from the current stream position.
+    ///
+    /// Throws an exception when:
+    /// - The end of the file is reached first, leaving the stream position unchanged.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn read_i32_be(file: &mut SharedFile) -> Result<rhai::INT, Box<EvalAltResult>> {
+        convert_to_int(i32::from_be_bytes(read_array(file)?))
+    }
+
+    /// Reads a native-endian unsigned 64-bit integer from the current stream position.
+    ///
+    /// Throws an exception when:
+    /// - The end of the file is reached first, leaving the stream position unchanged.
+    /// - The value doesn't fit into a Rhai integer.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn read_u64(file: &mut SharedFile) -> Result<rhai::INT, Box<EvalAltResult>> {
+        convert_to_int(u64::from_ne_bytes(read_array(file)?))
+    }
+
+    /// Reads a little-endian unsigned 64-bit integer from the current stream position.
+    ///
+    /// Throws an exception when:
+    /// - The end of the file is reached first, leaving the stream position unchanged.
+    /// - The value doesn't fit into a Rhai integer.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn read_u64_le(file: &mut SharedFile) -> Result<rhai::INT, Box<EvalAltResult>> {
+        convert_to_int(u64::from_le_bytes(read_array(file)?))
+    }
+
+    /// Reads a big-endian unsigned 64-bit integer from the current stream position.
+    ///
+    /// Throws an exception when:
+    /// - The end of the file is reached first, leaving the stream position unchanged.
+    /// - The value doesn't fit into a Rhai integer.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn read_u64_be(file: &mut SharedFile) -> Result<rhai::INT, Box<EvalAltResult>> {
+        convert_to_int(u64::from_be_bytes(read_array(file)?))
+    }
+
+    /// Reads a native-endian signed 64-bit integer from the current stream position.
+    ///
+    /// Throws an exception when:
+    /// - The end of the file is reached first, leaving the stream position unchanged.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn read_i64(file: &mut SharedFile) -> Result<rhai::INT, Box<EvalAltResult>> {
+        convert_to_int(i64::from_ne_bytes(read_array(file)?))
+    }
+
+    /// Reads a little-endian signed 64-bit integer from the current stream position.
+    ///
+    /// Throws an exception when:
+    /// - The end of the file is reached first, leaving the stream position unchanged.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn read_i64_le(file: &mut SharedFile) -> Result<rhai::INT, Box<EvalAltResult>> {
+        convert_to_int(i64::from_le_bytes(read_array(file)?))
+    }
+
+    /// Reads a big-endian signed 64-bit integer from the current stream position.
+    ///
+    /// Throws an exception when:
+    /// - The end of the file is reached first, leaving the stream position unchanged.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn read_i64_be(file: &mut SharedFile) -> Result<rhai::INT, Box<EvalAltResult>> {
+        convert_to_int(i64::from_be_bytes(read_array(file)?))
+    }
+
+    /// Writes the number as an unsigned 8-bit integer at the current stream position, returning the number of bytes written.
+    ///
+    /// Throws an exception when:
+    /// - The number is out of range for the type.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn write_u8(
+        file: &mut SharedFile,
+        value: rhai::INT,
+    ) -> Result<rhai::INT, Box<EvalAltResult>> {
+        write_bytes(file, &to_number::<u8>(value)?.to_ne_bytes())
+    }
+
+    /// Writes the number as a signed 8-bit integer at the current stream position, returning the number of bytes written.
+    ///
+    /// Throws an exception when:
+    /// - The number is out of range for the type.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn write_i8(
+        file: &mut SharedFile,
+        value: rhai::INT,
+    ) -> Result<rhai::INT, Box<EvalAltResult>> {
+        write_bytes(file, &to_number::<i8>(value)?.to_ne_bytes())
+    }
+
+    /// Writes the number as a native-endian unsigned 16-bit integer at the current stream position, returning the number of bytes written.
+    ///
+    /// Throws an exception when:
+    /// - The number is out of range for the type.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn write_u16(
+        file: &mut SharedFile,
+        value: rhai::INT,
+    ) -> Result<rhai::INT, Box<EvalAltResult>> {
+        write_bytes(file, &to_number::<u16>(value)?.to_ne_bytes())
+    }
+
+    /// Writes the number as a little-endian unsigned 16-bit integer at the current stream position, returning the number of bytes written.
+    ///
+    /// Throws an exception when:
+    /// - The number is out of range for the type.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn write_u16_le(
+        file: &mut SharedFile,
+        value: rhai::INT,
+    ) -> Result<rhai::INT, Box<EvalAltResult>> {
+        write_bytes(file, &to_number::<u16>(value)?.to_le_bytes())
+    }
+
+    /// Writes the number as a big-endian unsigned 16-bit integer at the current stream position, returning the number of bytes written.
+    ///
+    /// Throws an exception when:
+    /// - The number is out of range for the type.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn write_u16_be(
+        file: &mut SharedFile,
+        value: rhai::INT,
+    ) -> Result<rhai::INT, Box<EvalAltResult>> {
+        write_bytes(file, &to_number::<u16>(value)?.to_be_bytes())
+    }
+
+    /// Writes the number as a native-endian signed 16-bit integer at the current stream position, returning the number of bytes written.
+    ///
+    /// Throws an exception when:
+    /// - The number is out of range for the type.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn write_i16(
+        file: &mut SharedFile,
+        value: rhai::INT,
+    ) -> Result<rhai::INT, Box<EvalAltResult>> {
+        write_bytes(file, &to_number::<i16>(value)?.to_ne_bytes())
+    }
+
+    /// Writes the number as a little-endian signed 16-bit integer at the current stream position, returning the number of bytes written.
+    ///
+    /// Throws an exception when:
+    /// - The number is out of range for the type.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn write_i16_le(
+        file: &mut SharedFile,
+        value: rhai::INT,
+    ) -> Result<rhai::INT, Box<EvalAltResult>> {
+        write_bytes(file, &to_number::<i16>(value)?.to_le_bytes())
+    }
+
+    /// Writes the number as a big-endian signed 16-bit integer at the current stream position, returning the number of bytes written.
+    ///
+    /// Throws an exception when:
+    /// - The number is out of range for the type.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn write_i16_be(
+        file: &mut SharedFile,
+        value: rhai::INT,
+    ) -> Result<rhai::INT, Box<EvalAltResult>> {
+        write_bytes(file, &to_number::<i16>(value)?.to_be_bytes())
+    }
+
+    /// Writes the number as a native-endian unsigned 32-bit integer at the current stream position, returning the number of bytes written.
+    ///
+    /// Throws an exception when:
+    /// - The number is out of range for the type.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn write_u32(
+        file: &mut SharedFile,
+        value: rhai::INT,
+    ) -> Result<rhai::INT, Box<EvalAltResult>> {
+        write_bytes(file, &to_number::<u32>(value)?.to_ne_bytes())
+    }
+
+    /// Writes the number as a little-endian unsigned 32-bit integer at the current stream position, returning the number of bytes written.
+    ///
+    /// Throws an exception when:
+    /// - The number is out of range for the type.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn write_u32_le(
+        file: &mut SharedFile,
+        value: rhai::INT,
+    ) -> Result<rhai::INT, Box<EvalAltResult>> {
+        write_bytes(file, &to_number::<u32>(value)?.to_le_bytes())
+    }
+
+    /// Writes the number as a big-endian unsigned 32-bit integer at the current stream position, returning the number of bytes written.
+    ///
+    /// Throws an exception when:
+    /// - The number is out of range for the type.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn write_u32_be(
+        file: &mut SharedFile,
+        value: rhai::INT,
+    ) -> Result<rhai::INT, Box<EvalAltResult>> {
+        write_bytes(file, &to_number::<u32>(value)?.to_be_bytes())
+    }
+
+    /// Writes the number as a native-endian signed 32-bit integer at the current stream position, returning the number of bytes written.
+    ///
+    /// Throws an exception when:
+    /// - The number is out of range for the type.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn write_i32(
+        file: &mut SharedFile,
+        value: rhai::INT,
+    ) -> Result<rhai::INT, Box<EvalAltResult>> {
+        write_bytes(file, &to_number::<i32>(value)?.to_ne_bytes())
+    }
+
+    /// Writes the number as a little-endian signed 32-bit integer at the current stream position, returning the number of bytes written.
+    ///
+    /// Throws an exception when:
+    /// - The number is out of range for the type.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn write_i32_le(
+        file: &mut SharedFile,
+        value: rhai::INT,
+    ) -> Result<rhai::INT, Box<EvalAltResult>> {
+        write_bytes(file, &to_number::<i32>(value)?.to_le_bytes())
+    }
+
+    /// Writes the number as a big-endian signed 32-bit integer at the current stream position, returning the number of bytes written.
+    ///
+    /// Throws an exception when:
+    /// - The number is out of range for the type.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn write_i32_be(
+        file: &mut SharedFile,
+        value: rhai::INT,
+    ) -> Result<rhai::INT, Box<EvalAltResult>> {
+        write_bytes(file, &to_number::<i32>(value)?.to_be_bytes())
+    }
+
+    /// Writes the number as a native-endian unsigned 64-bit integer at the current stream position, returning the number of bytes written.
+    ///
+    /// Throws an exception when:
+    /// - The number is out of range for the type.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn write_u64(
+        file: &mut SharedFile,
+        value: rhai::INT,
+    ) -> Result<rhai::INT, Box<EvalAltResult>> {
+        write_bytes(file, &to_number::<u64>(value)?.to_ne_bytes())
+    }
+
+    /// Writes the number as a little-endian unsigned 64-bit integer at the current stream position, returning the number of bytes written.
+    ///
+    /// Throws an exception when:
+    /// - The number is out of range for the type.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn write_u64_le(
+        file: &mut SharedFile,
+        value: rhai::INT,
+    ) -> Result<rhai::INT, Box<EvalAltResult>> {
+        write_bytes(file, &to_number::<u64>(value)?.to_le_bytes())
+    }
+
+    /// Writes the number as a big-endian unsigned 64-bit integer at the current stream position, returning the number of bytes written.
+    ///
+    /// Throws an exception when:
+    /// - The number is out of range for the type.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn write_u64_be(
+        file: &mut SharedFile,
+        value: rhai::INT,
+    ) -> Result<rhai::INT, Box<EvalAltResult>> {
+        write_bytes(file, &to_number::<u64>(value)?.to_be_bytes())
+    }
+
+    /// Writes the number as a native-endian signed 64-bit integer at the current stream position, returning the number of bytes written.
+    ///
+    /// Throws an exception when:
+    /// - The number is out of range for the type.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn write_i64(
+        file: &mut SharedFile,
+        value: rhai::INT,
+    ) -> Result<rhai::INT, Box<EvalAltResult>> {
+        write_bytes(file, &to_number::<i64>(value)?.to_ne_bytes())
+    }
+
+    /// Writes the number as a little-endian signed 64-bit integer at the current stream position, returning the number of bytes written.
+    ///
+    /// Throws an exception when:
+    /// - The number is out of range for the type.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn write_i64_le(
+        file: &mut SharedFile,
+        value: rhai::INT,
+    ) -> Result<rhai::INT, Box<EvalAltResult>> {
+        write_bytes(file, &to_number::<i64>(value)?.to_le_bytes())
+    }
+
+    /// Writes the number as a big-endian signed 64-bit integer at the current stream position, returning the number of bytes written.
+    ///
+    /// Throws an exception when:
+    /// - The number is out of range for the type.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn write_i64_be(
+        file: &mut SharedFile,
+        value: rhai::INT,
+    ) -> Result<rhai::INT, Box<EvalAltResult>> {
+        write_bytes(file, &to_number::<i64>(value)?.to_be_bytes())
+    }
+
+    #[cfg(not(feature = "no_float"))]
+    // `rhai::FLOAT` is `f32` with the `f32_float` feature of Rhai.
+    #[allow(clippy::unnecessary_cast)]
+    pub mod float_functions {
+        /// Reads a native-endian 32-bit float from the current stream position.
+        ///
+        /// Throws an exception when:
+        /// - The end of the file is reached first, leaving the stream position unchanged.
+        #[rhai_fn(global, pure, return_raw)]
+        pub fn read_f32(file: &mut SharedFile) -> Result<rhai::FLOAT, Box<EvalAltResult>> {
+            Ok(f32::from_ne_bytes(read_array(file)?) as rhai::FLOAT)
+        }
+
+        /// Reads a little-endian 32-bit float from the current stream position.
+        ///
+        /// Throws an exception when:
+        /// - The end of the file is reached first, leaving the stream position unchanged.
+        #[rhai_fn(global, pure, return_raw)]
+        pub fn read_f32_le(file: &mut SharedFile) -> Result<rhai::FLOAT, Box<EvalAltResult>> {
+            Ok(f32::from_le_bytes(read_array(file)?) as rhai::FLOAT)
+        }
+
+        /// Reads a big-endian 32-bit float from the current stream position.
+        ///
+        /// Throws an exception when:
+        /// - The end of the file is reached first, leaving the stream position unchanged.
+        #[rhai_fn(global, pure, return_raw)]
+        pub fn read_f32_be(file: &mut SharedFile) -> Result<rhai::FLOAT, Box<EvalAltResult>> {
+            Ok(f32::from_be_bytes(read_array(file)?) as rhai::FLOAT)
+        }
+
+        /// Reads a native-endian 64-bit float from the current stream position.
+        ///
+        /// Throws an exception when:
+        /// - The end of the file is reached first, leaving the stream position unchanged.
+        #[rhai_fn(global, pure, return_raw)]
+        pub fn read_f64(file: &mut SharedFile) -> Result<rhai::FLOAT, Box<EvalAltResult>> {
+            Ok(f64::from_ne_bytes(read_array(file)?) as rhai::FLOAT)
+        }
+
+        /// Reads a little-endian 64-bit float from the current stream position.
+        ///
+        /// Throws an exception when:
+        /// - The end of the file is reached first, leaving the stream position unchanged.
+        #[rhai_fn(global, pure, return_raw)]
+        pub fn read_f64_le(file: &mut SharedFile) -> Result<rhai::FLOAT, Box<EvalAltResult>> {
+            Ok(f64::from_le_bytes(read_array(file)?) as rhai::FLOAT)
+        }
+
+        /// Reads a big-endian 64-bit float from the current stream position.
+        ///
+        /// Throws an exception when:
+        /// - The end of the file is reached first, leaving the stream position unchanged.
+        #[rhai_fn(global, pure, return_raw)]
+        pub fn read_f64_be(file: &mut SharedFile) -> Result<rhai::FLOAT, Box<EvalAltResult>> {
+            Ok(f64::from_be_bytes(read_array(file)?) as rhai::FLOAT)
+        }
+
+        /// Writes the number as a native-endian 32-bit float at the current stream position, returning the number of bytes written.
+        ///
+        /// The number is rounded to the nearest 32-bit float.
+        #[rhai_fn(global, pure, return_raw)]
+        pub fn write_f32(
+            file: &mut SharedFile,
+            value: rhai::FLOAT,
+        ) -> Result<rhai::INT, Box<EvalAltResult>> {
+            write_bytes(file, &(value as f32).to_ne_bytes())
+        }
+
+        /// Writes the number as a little-endian 32-bit float at the current stream position, returning the number of bytes written.
+        ///
+        /// The number is rounded to the nearest 32-bit float.
+        #[rhai_fn(global, pure, return_raw)]
+        pub fn write_f32_le(
+            file: &mut SharedFile,
+            value: rhai::FLOAT,
+        ) -> Result<rhai::INT, Box<EvalAltResult>> {
+            write_bytes(file, &(value as f32).to_le_bytes())
+        }
+
+        /// Writes the number as a big-endian 32-bit float at the current stream position, returning the number of bytes written.
+        ///
+        /// The number is rounded to the nearest 32-bit float.
+        #[rhai_fn(global, pure, return_raw)]
+        pub fn write_f32_be(
+            file: &mut SharedFile,
+            value: rhai::FLOAT,
+        ) -> Result<rhai::INT, Box<EvalAltResult>> {
+            write_bytes(file, &(value as f32).to_be_bytes())
+        }
+
+        /// Writes the number as a native-endian 64-bit float at the current stream position, returning the number of bytes written.
+        #[rhai_fn(global, pure, return_raw)]
+        pub fn write_f64(
+            file: &mut SharedFile,
+            value: rhai::FLOAT,
+        ) -> Result<rhai::INT, Box<EvalAltResult>> {
+            write_bytes(file, &(value as f64).to_ne_bytes())
+        }
+
+        /// Writes the number as a little-endian 64-bit float at the current stream position, returning the number of bytes written.
+        #[rhai_fn(global, pure, return_raw)]
+        pub fn write_f64_le(
+            file: &mut SharedFile,
+            value: rhai::FLOAT,
+        ) -> Result<rhai::INT, Box<EvalAltResult>> {
+            write_bytes(file, &(value as f64).to_le_bytes())
+        }
+
+        /// Writes the number as a big-endian 64-bit float at the current stream position, returning the number of bytes written.
+        #[rhai_fn(global, pure, return_raw)]
+        pub fn write_f64_be(
+            file: &mut SharedFile,
+            value: rhai::FLOAT,
+        ) -> Result<rhai::INT, Box<EvalAltResult>> {
+            write_bytes(file, &(value as f64).to_be_bytes())
+        }
+    }
+
+    #[cfg(not(feature = "no_index"))]
+    pub mod blob_functions {
+        use rhai::Blob;
+
+        /// Reads exactly `len` bytes from the current stream position and returns them as a `Blob`.
+        ///
+        /// Throws an exception when:
+        /// - The end of the file is reached first, leaving the stream position unchanged.
+        /// - `len` is negative or exceeds the engine's `max_array_size`.
+        #[rhai_fn(global, pure, return_raw)]
+        pub fn read_exact(
+            ctx: NativeCallContext,
+            file: &mut SharedFile,
+            len: rhai::INT,
+        ) -> Result<Blob, Box<EvalAltResult>> {
+            let max_len = ctx.engine().max_array_size();
+            let len = usize::try_from(len)
+                .ok()
+                .filter(|&len| max_len == 0 || len <= max_len)
+                .ok_or_else(|| format!("Invalid length {len}"))?;
+
+            let mut file = borrow_mut(file);
+            let start = file.stream_position().map_err(|e| e.to_string())?;
+
+            // Only allocate as much as the file actually holds.
+            let mut buf = Blob::new();
+            (&mut *file)
+                .take(len as u64)
+                .read_to_end(&mut buf)
+                .map_err(|e| e.to_string())?;
+
+            if buf.len() < len {
+                file.seek(SeekFrom::Start(start))
+                    .map_err(|e| e.to_string())?;
+                return Err(format!("Unexpected end of file reading {len} bytes").into());
+            }
+            Ok(buf)
+        }
+    }
+}
@@ -0,0 +1,598 @@
+#[allow(unused_imports)]
+use rhai::plugin::*;
+use rhai::{Map, Shared};
+
+use std::fs::File;
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+use super::config::resolve;
+use super::encoding::Encoding;
+#[cfg(not(feature = "no_index"))]
+use super::file::read_limited;
+use super::file::{
+    convert_to_int, open_options, read_limit, read_line_from, read_string_from, PushBack,
+};
+
+/// Magic bytes at the start of a gzip stream.
+#[cfg(feature = "gzip")]
+const GZIP_MAGIC: &[u8] = &[0x1F, 0x8B];
+/// Magic bytes at the start of a zstd frame.
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xB5, 0x2F, 0xFD];
+/// Magic bytes at the start of a bzip2 stream.
+#[cfg(feature = "bzip2")]
+const BZIP2_MAGIC: &[u8] = b"BZh";
+
+/// A compression format, each behind the cargo feature of the same name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Codec {
+    /// No compression.
+    None,
+    /// gzip.
+    #[cfg(feature = "gzip")]
+    Gzip,
+    /// Zstandard.
+    #[cfg(feature = "zstd")]
+    Zstd,
+    /// bzip2.
+    #[cfg(feature = "bzip2")]
+    Bzip2,
+}
+
+impl Codec {
+    /// Looks up a codec by name, ignoring case, returning `None` for `"auto"`.
+    ///
+    /// Throws an exception when the codec is unknown or its cargo feature isn't enabled.
+    fn from_name(name: &str) -> Result<Option<Self>, Box<EvalAltResult>> {
+        // The second set of arms only matches codecs whose feature is disabled.
+        #[allow(unreachable_patterns)]
+        let feature = match name.to_ascii_lowercase().as_str() {
+            "auto" => return Ok(None),
+            "none" => return Ok(Some(Codec::None)),
+            #[cfg(feature = "gzip")]
+            "gzip" | "gz" => return Ok(Some(Codec::Gzip)),
+            #[cfg(feature = "zstd")]
+            "zstd" | "zst" => return Ok(Some(Codec::Zstd)),
+            #[cfg(feature = "bzip2")]
+            "bzip2" | "bz2" => return Ok(Some(Codec::Bzip2)),
+            "gzip" | "gz" => "gzip",
+            "zstd" | "zst" => "zstd",
+            "bzip2" | "bz2" => "bzip2",
+            _ => return Err(format!("Unknown compression {name}").into()),
+        };
+        Err(
+            format!("Compression {name} is not enabled, it requires the `{feature}` feature")
+                .into(),
+        )
+    }
+
+    /// Detects the codec from the first bytes of a file, defaulting to no compression.
+    fn from_magic(head: &[u8]) -> Self {
+        match head {
+            #[cfg(feature = "gzip")]
+            _ if head.starts_with(GZIP_MAGIC) => Codec::Gzip,
+            #[cfg(feature = "zstd")]
+            _ if head.starts_with(ZSTD_MAGIC) => Codec::Zstd,
+            #[cfg(feature = "bzip2")]
+            _ if head.starts_with(BZIP2_MAGIC) => Codec::Bzip2,
+            _ => Codec::None,
+        }
+    }
+
+    /// Picks the codec from the extension of a path, defaulting to no compression.
+    fn from_extension(path: &Path) -> Self {
+        let ext = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default();
+        match ext.to_ascii_lowercase().as_str() {
+            #[cfg(feature = "gzip")]
+            "gz" | "gzip" => Codec::Gzip,
+            #[cfg(feature = "zstd")]
+            "zst" | "zstd" => Codec::Zstd,
+            #[cfg(feature = "bzip2")]
+            "bz2" => Codec::Bzip2,
+            _ => Codec::None,
+        }
+    }
+
+    /// Wraps a file opened for reading into a decoder, accepting concatenated streams.
+    fn decoder(self, file: impl Read + Send + 'static) -> std::io::Result<Box<dyn Read + Send>> {
+        Ok(match self {
+            Codec::None => Box::new(file),
+            #[cfg(feature = "gzip")]
+            Codec::Gzip => Box::new(flate2::read::MultiGzDecoder::new(file)),
+            #[cfg(feature = "zstd")]
+            Codec::Zstd => Box::new(zstd::Decoder::new(file)?),
+            #[cfg(feature = "bzip2")]
+            Codec::Bzip2 => Box::new(bzip2::read::MultiBzDecoder::new(file)),
+        })
+    }
+
+    /// Wraps a file opened for writing into an encoder with the compression level, `None` meaning the codec's default.
+    fn encoder(self, file: File, level: Option<u32>) -> Result<Encoder, Box<EvalAltResult>> {
+        let check = |range: std::ops::RangeInclusive<u32>, default: u32| match level {
+            Some(level) if !range.contains(&level) => Err(format!(
+                "Compression level {level} is out of range {}..={}",
+                range.start(),
+                range.end()
+            )),
+            level => Ok(level.unwrap_or(default)),
+        };
+
+        Ok(match self {
+            Codec::None => Encoder::Plain(file),
+            #[cfg(feature = "gzip")]
+            Codec::Gzip => Encoder::Gzip(flate2::write::GzEncoder::new(
+                file,
+                flate2::Compression::new(check(0..=9, 6)?),
+            )),
+            #[cfg(feature = "zstd")]
+            Codec::Zstd => Encoder::Zstd(
+                zstd::Encoder::new(file, check(1..=22, 3)? as i32).map_err(|e| e.to_string())?,
+            ),
+            #[cfg(feature = "bzip2")]
+            Codec::Bzip2 => Encoder::Bzip2(bzip2::write::BzEncoder::new(
+                file,
+                bzip2::Compression::new(check(1..=9, 6)?),
+            )),
+        })
+    }
+}
+
+/// The writing side of a compressed file.
+enum Encoder {
+    /// Uncompressed output.
+    Plain(File),
+    /// gzip output.
+    #[cfg(feature = "gzip")]
+    Gzip(flate2::write::GzEncoder<File>),
+    /// Zstandard output.
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::Encoder<'static, File>),
+    /// bzip2 output.
+    #[cfg(feature = "bzip2")]
+    Bzip2(bzip2::write::BzEncoder<File>),
+}
+
+impl Encoder {
+    /// Returns the encoder as a writer.
+    fn writer(&mut self) -> &mut dyn Write {
+        match self {
+            Encoder::Plain(w) => w,
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(w) => w,
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(w) => w,
+            #[cfg(feature = "bzip2")]
+            Encoder::Bzip2(w) => w,
+        }
+    }
+
+    /// Writes the end of the compressed stream and flushes the file.
+    fn finish(self) -> std::io::Result<()> {
+        let mut file = match self {
+            Encoder::Plain(file) => file,
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(w) => w.finish()?,
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(w) => w.finish()?,
+            #[cfg(feature = "bzip2")]
+            Encoder::Bzip2(w) => w.finish()?,
+        };
+        file.flush()
+    }
+}
+
+/// The reading side of a compressed file, which can't seek and so keeps given back bytes itself.
+struct Decoder {
+    /// The decompressed stream.
+    inner: Box<dyn Read + Send>,
+    /// Bytes given back, returned before reading more from `inner`.
+    pushed_back: Vec<u8>,
+    /// Number of decompressed bytes returned so far.
+    position: u64,
+    /// The first two decompressed bytes, where a byte order mark may be.
+    head: Vec<u8>,
+}
+
+impl Read for Decoder {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = if self.pushed_back.is_empty() {
+            self.inner.read(buf)?
+        } else {
+            let len = buf.len().min(self.pushed_back.len());
+            buf[..len].copy_from_slice(&self.pushed_back[..len]);
+            self.pushed_back.drain(..len);
+            len
+        };
+        // Bytes given back and read again are already in `head`.
+        if let Some(seen) = self.head.len().checked_sub(self.position as usize) {
+            let missing = 2 - self.head.len();
+            self.head.extend(buf[..len].iter().skip(seen).take(missing));
+        }
+        self.position += len as u64;
+        Ok(len)
+    }
+}
+
+impl PushBack for Decoder {
+    fn push_back(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        self.pushed_back.splice(0..0, bytes.iter().copied());
+        self.position -= bytes.len() as u64;
+        Ok(())
+    }
+}
+
+/// The open side of a compressed file.
+enum Stream {
+    /// Opened for reading.
+    Reading(Decoder),
+    /// Opened for writing.
+    Writing(Encoder),
+    /// Closed by the script.
+    Closed,
+}
+
+/// A compressed file opened for either reading or writing.
+pub struct CompressedFile {
+    /// The open stream, behind a mutex as codecs aren't `Sync`.
+    stream: Mutex<Stream>,
+}
+
+impl Drop for CompressedFile {
+    fn drop(&mut self) {
+        if let Ok(stream) = self.stream.get_mut() {
+            if let Stream::Writing(encoder) = std::mem::replace(stream, Stream::Closed) {
+                let _ = encoder.finish();
+            }
+        }
+    }
+}
+
+/// A compressed file shared between Rhai values.
+pub type SharedCompressedFile = Shared<CompressedFile>;
+
+/// Locks the stream of a compressed file.
+fn lock(file: &SharedCompressedFile) -> MutexGuard<'_, Stream> {
+    file.stream.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Runs `f` on the decoder of a compressed file opened for reading.
+fn with_decoder<T>(
+    file: &SharedCompressedFile,
+    f: impl FnOnce(&mut Decoder) -> Result<T, Box<EvalAltResult>>,
+) -> Result<T, Box<EvalAltResult>> {
+    match &mut *lock(file) {
+        Stream::Reading(decoder) => f(decoder),
+        Stream::Writing(_) => Err("Compressed file is not open for reading".into()),
+        Stream::Closed => Err("Compressed file is closed".into()),
+    }
+}
+
+/// Writes all bytes to a compressed file opened for writing, returning the number of uncompressed bytes written.
+fn write_all(file: &SharedCompressedFile, bytes: &[u8]) -> Result<rhai::INT, Box<EvalAltResult>> {
+    match &mut *lock(file) {
+        Stream::Writing(encoder) => encoder
+            .writer()
+            .write_all(bytes)
+            .map_err(|e| e.to_string())?,
+        Stream::Reading(_) => return Err("Compressed file is not open for writing".into()),
+        Stream::Closed => return Err("Compressed file is closed".into()),
+    }
+    convert_to_int(bytes.len())
+}
+
+/// Options accepted by `open_file(path, mode, options)`.
+struct CompressOptions {
+    /// The codec, `None` to detect it.
+    codec: Option<Codec>,
+    /// The compression level, `None` for the codec's default.
+    level: Option<u32>,
+}
+
+impl CompressOptions {
+    /// Reads the options from a script map, throwing on unknown keys and values of the wrong type.
+    fn from_map(map: &Map) -> Result<Self, Box<EvalAltResult>> {
+        let mut options = CompressOptions {
+            codec: None,
+            level: None,
+        };
+
+        for (key, value) in map {
+            match key.as_str() {
+                "compression" => {
+                    let name = value
+                        .read_lock::<ImmutableString>()
+                        .ok_or("Option compression must be a string")?;
+                    options.codec = Codec::from_name(&name)?;
+                }
+                "level" => {
+                    let level = value
+                        .as_int()
+                        .map_err(|_| "Option level must be an integer")?;
+                    let level = u32::try_from(level)
+                        .map_err(|_| format!("Compression level {level} is out of range"))?;
+                    options.level = Some(level);
+                }
+                _ => return Err(format!("Unknown compression option {key}").into()),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// Opens a compressed file for reading with mode `"r"`, or for writing with `"w"`, `"wx"`, `"a"` or `"ax"`.
+fn open(
+    path: &Path,
+    mode: &str,
+    options: CompressOptions,
+) -> Result<SharedCompressedFile, Box<EvalAltResult>> {
+    let file = match mode {
+        "r" | "w" | "wx" | "a" | "ax" => open_options(mode)
+            .open(path)
+            .map_err(|e| format!("{}: {e}", path.to_string_lossy()))?,
+        _ => {
+            return Err(format!(
+                "Compressed files can't be opened with mode {mode}, only r, w, wx, a or ax"
+            )
+            .into())
+        }
+    };
+
+    let stream = if mode == "r" {
+        let (codec, reader): (Codec, Box<dyn Read + Send>) = match options.codec {
+            Some(codec) => (codec, Box::new(file)),
+            None => {
+                let mut head = Vec::with_capacity(ZSTD_MAGIC.len());
+                (&file)
+                    .take(ZSTD_MAGIC.len() as u64)
+                    .read_to_end(&mut head)
+                    .map_err(|e| e.to_string())?;
+                (
+                    Codec::from_magic(&head),
+                    Box::new(Cursor::new(head).chain(file)),
+                )
+            }
+        };
+        Stream::Reading(Decoder {
+            inner: codec.decoder(reader).map_err(|e| e.to_string())?,
+            pushed_back: Vec::new(),
+            position: 0,
+            head: Vec::new(),
+        })
+    } else {
+        let codec = options.codec.unwrap_or_else(|| Codec::from_extension(path));
+        Stream::Writing(codec.encoder(file, options.level)?)
+    };
+
+    Ok(Shared::new(CompressedFile {
+        stream: Mutex::new(stream),
+    }))
+}
+
+#[export_module]
+pub mod compress_functions {
+    /// Opens a compressed file for reading with mode `"r"`, or for writing with `"w"`, `"wx"`, `"a"` or `"ax"` (see `open_file(path, options)`).
+    ///
+    /// The returned handle decompresses when reading and compresses when writing, it can't seek.
+    ///
+    /// Options:
+    /// - `compression`: `"gzip"`, `"zstd"`, `"bzip2"`, `"none"` or `"auto"`, the default.
+    ///   `"auto"` detects the format from the magic bytes at the start of the file when reading,
+    ///   and from the extension (`.gz`, `.zst`, `.bz2`) when writing, using no compression if neither matches.
+    /// - `level`: the compression level, 0-9 for gzip, 1-22 for zstd and 1-9 for bzip2.
+    ///
+    /// Each format requires the cargo feature of the same name.
+    /// Appending adds a new compressed stream to the end of the file, which is read back as if it were one.
+    ///
+    /// Throws an exception when:
+    /// - The mode is unsupported, compressed files can't be read and written at once.
+    /// - An option is unknown or has the wrong type.
+    /// - The format isn't enabled.
+    /// - The file can't be opened.
+    #[rhai_fn(return_raw, name = "open_file")]
+    pub fn open_compressed(
+        ctx: NativeCallContext,
+        path: PathBuf,
+        mode: &str,
+        options: Map,
+    ) -> Result<SharedCompressedFile, Box<EvalAltResult>> {
+        let options = CompressOptions::from_map(&options)?;
+        open(&resolve(&ctx, &path)?, mode, options)
+    }
+
+    /// Helper function for `open_file(path, mode, options)` that takes a string instead of `PathBuf`.
+    #[rhai_fn(return_raw, name = "open_file")]
+    pub fn open_compressed_str(
+        ctx: NativeCallContext,
+        path_raw: ImmutableString,
+        mode: &str,
+        options: Map,
+    ) -> Result<SharedCompressedFile, Box<EvalAltResult>> {
+        let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+        open_compressed(ctx, path, mode, options)
+    }
+
+    /// Reads and decompresses until EOF and returns it as a string, respects the engine's `max_string_size`.
+    ///
+    /// Throws an exception when:
+    /// - The file isn't open for reading.
+    /// - The data is corrupt or the text isn't valid UTF-8.
+    #[rhai_fn(global, pure, return_raw, name = "read_string")]
+    pub fn read_to_string(
+        ctx: NativeCallContext,
+        file: &mut SharedCompressedFile,
+    ) -> Result<String, Box<EvalAltResult>> {
+        read_to_string_with_len_encoding(ctx, file, 0, "utf-8")
+    }
+
+    /// Reads and decompresses up to the passed `len` and returns it as a string, respects the engine's `max_string_size`.
+    ///
+    /// A character cut in two by the limit is left for the next read.
+    ///
+    /// Throws an exception when:
+    /// - The file isn't open for reading.
+    /// - The data is corrupt or the text isn't valid UTF-8.
+    #[rhai_fn(global, pure, return_raw, name = "read_string")]
+    pub fn read_to_string_with_len(
+        ctx: NativeCallContext,
+        file: &mut SharedCompressedFile,
+        len: rhai::INT,
+    ) -> Result<String, Box<EvalAltResult>> {
+        read_to_string_with_len_encoding(ctx, file, len, "utf-8")
+    }
+
+    /// Reads and decompresses until EOF and decodes it with the passed encoding, see `read_string(file, encoding)`.
+    ///
+    /// Throws an exception when:
+    /// - The file isn't open for reading.
+    /// - The encoding isn't supported.
+    /// - The data is corrupt or the text isn't valid in the encoding.
+    #[rhai_fn(global, pure, return_raw, name = "read_string")]
+    pub fn read_to_string_with_encoding(
+        ctx: NativeCallContext,
+        file: &mut SharedCompressedFile,
+        encoding: &str,
+    ) -> Result<String, Box<EvalAltResult>> {
+        read_to_string_with_len_encoding(ctx, file, 0, encoding)
+    }
+
+    /// Reads and decompresses up to the passed `len` and decodes it with the passed encoding, see `read_string(file, len, encoding)`.
+    ///
+    /// Throws an exception when:
+    /// - The file isn't open for reading.
+    /// - The encoding isn't supported.
+    /// - The data is corrupt or the text isn't valid in the encoding.
+    #[rhai_fn(global, pure, return_raw, name = "read_string")]
+    pub fn read_to_string_with_len_encoding(
+        ctx: NativeCallContext,
+        file: &mut SharedCompressedFile,
+        len: rhai::INT,
+        encoding: &str,
+    ) -> Result<String, Box<EvalAltResult>> {
+        let encoding = Encoding::from_name(encoding)?;
+        let limit = read_limit(len, ctx.engine().max_string_size());
+        with_decoder(file, |decoder| {
+            let at_start = decoder.position == 0;
+            let encoding = match at_start {
+                true => encoding,
+                false => encoding.with_byte_order(&decoder.head),
+            };
+            read_string_from(decoder, limit, encoding, at_start)
+        })
+    }
+
+    /// Reads and decompresses up to and including the next newline and returns it as a string, respects the engine's `max_string_size`.
+    ///
+    /// The newline is kept, so an empty string is only returned at EOF.
+    ///
+    /// Throws an exception when:
+    /// - The file isn't open for reading.
+    /// - The data is corrupt or the text isn't valid UTF-8.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn read_line(
+        ctx: NativeCallContext,
+        file: &mut SharedCompressedFile,
+    ) -> Result<String, Box<EvalAltResult>> {
+        let max_len = ctx.engine().max_string_size();
+        with_decoder(file, |decoder| read_line_from(decoder, max_len))
+    }
+
+    /// Compresses the string and writes it to the file, returning the number of uncompressed bytes written.
+    ///
+    /// Throws an exception when:
+    /// - The file isn't open for writing.
+    /// - The write function encounters an I/O error.
+    #[rhai_fn(global, pure, return_raw, name = "write")]
+    pub fn write_with_string(
+        file: &mut SharedCompressedFile,
+        str: &str,
+    ) -> Result<rhai::INT, Box<EvalAltResult>> {
+        write_all(file, str.as_bytes())
+    }
+
+    /// Encodes the string with the passed encoding, compresses it and writes it to the file, returning the number of uncompressed bytes written.
+    ///
+    /// Throws an exception when:
+    /// - The file isn't open for writing.
+    /// - The encoding isn't supported or can't represent the string.
+    /// - The write function encounters an I/O error.
+    #[rhai_fn(global, pure, return_raw, name = "write")]
+    pub fn write_with_string_encoding(
+        file: &mut SharedCompressedFile,
+        str: &str,
+        encoding: &str,
+    ) -> Result<rhai::INT, Box<EvalAltResult>> {
+        let bytes = Encoding::from_name(encoding)?.encode(str)?;
+        write_all(file, &bytes)
+    }
+
+    /// Finishes the compressed stream and closes the file.
+    ///
+    /// Files are also closed when the last handle is dropped, but any error is lost then.
+    ///
+    /// Throws an exception when:
+    /// - The end of the stream can't be written.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn close(file: &mut SharedCompressedFile) -> Result<(), Box<EvalAltResult>> {
+        match std::mem::replace(&mut *lock(file), Stream::Closed) {
+            Stream::Writing(encoder) => encoder.finish().map_err(|e| e.to_string().into()),
+            Stream::Reading(_) | Stream::Closed => Ok(()),
+        }
+    }
+
+    /// Returns `"CompressedFile"`.
+    #[rhai_fn(global, pure)]
+    pub fn to_string(_file: &mut SharedCompressedFile) -> String {
+        "CompressedFile".into()
+    }
+
+    #[cfg(not(feature = "no_index"))]
+    pub mod blob_functions {
+        use rhai::Blob;
+
+        /// Reads and decompresses until EOF and returns it as a `Blob`, respects the engine's `max_array_size`.
+        ///
+        /// Throws an exception when:
+        /// - The file isn't open for reading.
+        /// - The data is corrupt.
+        #[rhai_fn(global, pure, return_raw, name = "read_blob")]
+        pub fn read_to_blob(
+            ctx: NativeCallContext,
+            file: &mut SharedCompressedFile,
+        ) -> Result<Blob, Box<EvalAltResult>> {
+            read_to_blob_with_len(ctx, file, 0)
+        }
+
+        /// Reads and decompresses up to the passed `len` and returns it as a `Blob`, respects the engine's `max_array_size`.
+        ///
+        /// Throws an exception when:
+        /// - The file isn't open for reading.
+        /// - The data is corrupt.
+        #[rhai_fn(global, pure, return_raw, name = "read_blob")]
+        pub fn read_to_blob_with_len(
+            ctx: NativeCallContext,
+            file: &mut SharedCompressedFile,
+            len: rhai::INT,
+        ) -> Result<Blob, Box<EvalAltResult>> {
+            let limit = read_limit(len, ctx.engine().max_array_size());
+            with_decoder(file, |decoder| read_limited(decoder, limit))
+        }
+
+        /// Compresses the blob and writes it to the file, returning the number of uncompressed bytes written.
+        ///
+        /// Throws an exception when:
+        /// - The file isn't open for writing.
+        /// - The write function encounters an I/O error.
+        #[rhai_fn(global, pure, return_raw, name = "write")]
+        pub fn write_with_blob(
+            file: &mut SharedCompressedFile,
+            blob: Blob,
+        ) -> Result<rhai::INT, Box<EvalAltResult>> {
+            write_all(file, &blob)
+        }
+    }
+}
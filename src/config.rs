@@ -0,0 +1,117 @@
+use rhai::plugin::*;
+use rhai::{FuncRegistration, Locked, Shared};
+
+use std::collections::BTreeMap;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+
+/// Name of the function that hands the package state to the package's own functions.
+///
+/// The `$` makes it impossible to call from a script.
+const STATE_FN: &str = "fs$state";
+
+/// Configuration for a [`FilesystemPackage`](crate::FilesystemPackage).
+///
+/// Use [`FilesystemPackage::with_config`](crate::FilesystemPackage::with_config) to create a package with a non-default configuration.
+#[derive(Debug, Clone, Default)]
+pub struct FilesystemConfig {
+    /// How environment variables are looked up, e.g. by `home_dir()` and `expand()`.
+    pub env: EnvAccess,
+    /// The initial working directory that relative paths are resolved against, which should be absolute.
+    ///
+    /// When `None` the process' current directory is used until a script calls `set_cwd`.
+    pub cwd: Option<PathBuf>,
+}
+
+/// Controls how the package reads environment variables.
+#[derive(Debug, Clone, Default)]
+pub enum EnvAccess {
+    /// Read variables from the process environment.
+    #[default]
+    Process,
+    /// Only the provided variables are visible, the process environment is never read.
+    Vars(BTreeMap<String, String>),
+    /// Environment access is disabled, anything that needs a variable throws an exception.
+    Disabled,
+}
+
+impl EnvAccess {
+    /// Looks up an environment variable, returning `None` if it isn't set or isn't valid unicode.
+    ///
+    /// Throws an exception when environment access is disabled.
+    pub(crate) fn var(&self, name: &str) -> Result<Option<String>, Box<EvalAltResult>> {
+        match self {
+            EnvAccess::Process => Ok(std::env::var(name).ok()),
+            EnvAccess::Vars(vars) => Ok(vars.get(name).cloned()),
+            EnvAccess::Disabled => Err("Environment access is disabled".into()),
+        }
+    }
+}
+
+/// State shared by all functions of one package instance.
+#[derive(Debug)]
+pub(crate) struct PackageState {
+    /// The configuration the package was created with.
+    pub(crate) config: FilesystemConfig,
+    /// The virtual working directory, `None` while following the process' current directory.
+    cwd: Locked<Option<PathBuf>>,
+}
+
+impl PackageState {
+    /// Returns the virtual working directory, if one has been set.
+    pub(crate) fn cwd(&self) -> Option<PathBuf> {
+        #[cfg(not(feature = "sync"))]
+        return self.cwd.borrow().deref().clone();
+
+        #[cfg(feature = "sync")]
+        return self.cwd.read().unwrap().deref().clone();
+    }
+
+    /// Replaces the virtual working directory, returning the previous one.
+    pub(crate) fn replace_cwd(&self, cwd: Option<PathBuf>) -> Option<PathBuf> {
+        #[cfg(not(feature = "sync"))]
+        let mut guard = self.cwd.borrow_mut();
+
+        #[cfg(feature = "sync")]
+        let mut guard = self.cwd.write().unwrap();
+
+        std::mem::replace(guard.deref_mut(), cwd)
+    }
+
+    /// Resolves a relative path against the virtual working directory.
+    ///
+    /// Paths are returned unchanged while following the process' current directory, as the OS resolves them the same way.
+    pub(crate) fn resolve(&self, path: &Path) -> PathBuf {
+        match self.cwd() {
+            Some(cwd) if path.is_relative() => cwd.join(path),
+            _ => path.to_path_buf(),
+        }
+    }
+}
+
+/// Registers the package state into the package module so that it can be retrieved with [`state`].
+pub(crate) fn set_config(lib: &mut Module, config: FilesystemConfig) {
+    let cwd = Locked::new(config.cwd.clone());
+    let state = Shared::new(PackageState { config, cwd });
+    FuncRegistration::new(STATE_FN)
+        .in_global_namespace()
+        .with_volatility(true)
+        .set_into_module(lib, move || state.clone());
+}
+
+/// Retrieves the state of the package the calling function belongs to.
+pub(crate) fn state(ctx: &NativeCallContext) -> Result<Shared<PackageState>, Box<EvalAltResult>> {
+    ctx.call_native_fn::<Shared<PackageState>>(STATE_FN, ())
+}
+
+/// Resolves a relative path against the working directory of the package the calling function belongs to.
+pub(crate) fn resolve(ctx: &NativeCallContext, path: &Path) -> Result<PathBuf, Box<EvalAltResult>> {
+    Ok(state(ctx)?.resolve(path))
+}
+
+/// Gives the engine's progress callback a chance to terminate a long running function.
+///
+/// Native calls count as operations, so this makes a cheap one and propagates any termination.
+pub(crate) fn tick(ctx: &NativeCallContext) -> Result<(), Box<EvalAltResult>> {
+    state(ctx).map(|_| ())
+}
@@ -0,0 +1,616 @@
+#[allow(unused_imports)]
+use rhai::plugin::*;
+#[cfg(any(feature = "json", feature = "toml", feature = "yaml", feature = "csv"))]
+use rhai::Map;
+
+use std::path::Path;
+
+use super::config::resolve;
+use super::file::read_text;
+
+/// Writes the text to the file, creating or truncating it.
+fn write_text(path: &Path, text: &str) -> Result<(), Box<EvalAltResult>> {
+    std::fs::write(path, text).map_err(|e| format!("{}: {e}", path.to_string_lossy()).into())
+}
+
+/// Formats an error raised while parsing or serializing the file at the path.
+fn data_error(path: &Path, e: impl std::fmt::Display) -> Box<EvalAltResult> {
+    format!("{}: {e}", path.to_string_lossy()).into()
+}
+
+/// Reads the `pretty` option of `write_json` and `write_toml`, throwing on unknown keys and values of the wrong type.
+#[cfg(any(feature = "json", feature = "toml"))]
+fn pretty_option(options: &Map) -> Result<bool, Box<EvalAltResult>> {
+    let mut pretty = true;
+    for (key, value) in options {
+        match key.as_str() {
+            "pretty" => {
+                pretty = value
+                    .as_bool()
+                    .map_err(|_| format!("Option {key} must be a bool"))?
+            }
+            _ => return Err(format!("Unknown option {key}").into()),
+        }
+    }
+    Ok(pretty)
+}
+
+/// Reads the options of `write_yaml`, which has none yet, throwing on unknown keys like `pretty_option` does.
+#[cfg(feature = "yaml")]
+fn yaml_options(options: &Map) -> Result<(), Box<EvalAltResult>> {
+    match options.keys().next() {
+        Some(key) => Err(format!("Unknown option {key}").into()),
+        None => Ok(()),
+    }
+}
+
+/// Replaces the maps the TOML parser uses for dates and times with the string they hold.
+#[cfg(feature = "toml")]
+fn toml_datetimes(value: Dynamic) -> Dynamic {
+    /// The key of the single entry of a map holding a TOML date or time.
+    const DATETIME_KEY: &str = "$__toml_private_datetime";
+
+    if value.is_map() {
+        let mut map = value.cast::<Map>();
+        if map.len() == 1 {
+            if let Some(datetime) = map.remove(DATETIME_KEY) {
+                return datetime;
+            }
+        }
+        let map: Map = map
+            .into_iter()
+            .map(|(key, value)| (key, toml_datetimes(value)))
+            .collect();
+        return map.into();
+    }
+    #[cfg(not(feature = "no_index"))]
+    if value.is_array() {
+        let array: rhai::Array = value
+            .cast::<rhai::Array>()
+            .into_iter()
+            .map(toml_datetimes)
+            .collect();
+        return array.into();
+    }
+    value
+}
+
+/// Options of `read_csv` and `write_csv`.
+#[cfg(feature = "csv")]
+struct CsvOptions {
+    /// Whether the first row holds the column names.
+    headers: bool,
+    /// The byte separating fields.
+    delimiter: u8,
+    /// The columns to write, in order.
+    columns: Option<Vec<String>>,
+}
+
+#[cfg(feature = "csv")]
+impl CsvOptions {
+    /// Reads the options from a script map, throwing on unknown keys and values of the wrong type.
+    ///
+    /// `columns` is only accepted when `write` is set.
+    fn from_map(map: &Map, write: bool) -> Result<Self, Box<EvalAltResult>> {
+        let mut options = CsvOptions {
+            headers: true,
+            delimiter: b',',
+            columns: None,
+        };
+
+        for (key, value) in map {
+            let invalid = |kind: &str| format!("Option {key} must be {kind}");
+            match key.as_str() {
+                "headers" => options.headers = value.as_bool().map_err(|_| invalid("a bool"))?,
+                "delimiter" => {
+                    let delimiter = value
+                        .read_lock::<ImmutableString>()
+                        .filter(|delimiter| delimiter.len() == 1)
+                        .ok_or_else(|| invalid("a single ASCII character"))?;
+                    options.delimiter = delimiter.as_bytes()[0];
+                }
+                "columns" if write => {
+                    let columns = value
+                        .read_lock::<rhai::Array>()
+                        .ok_or_else(|| invalid("an array of strings"))?
+                        .iter()
+                        .map(|column| column.clone().into_string())
+                        .collect::<Result<_, _>>()
+                        .map_err(|_| invalid("an array of strings"))?;
+                    options.columns = Some(columns);
+                }
+                _ => return Err(format!("Unknown option {key}").into()),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// Formats a value as a CSV field, `()` being an empty field.
+#[cfg(feature = "csv")]
+fn csv_field(value: &Dynamic) -> String {
+    if value.is_unit() {
+        String::new()
+    } else {
+        value.to_string()
+    }
+}
+
+#[export_module]
+pub mod data_functions {
+    #[cfg(feature = "json")]
+    pub mod json_functions {
+        use super::super::{data_error, pretty_option, read_text, resolve, write_text};
+        use rhai::Map;
+        use std::path::PathBuf;
+
+        /// Reads a JSON file and returns its value, objects becoming maps and arrays becoming arrays.
+        ///
+        /// The whole file is read, so it must not be larger than the engine's `max_string_size`.
+        ///
+        /// Throws an exception when:
+        /// - The file can't be read, isn't valid UTF-8 or is too large.
+        /// - The file isn't valid JSON.
+        #[rhai_fn(return_raw)]
+        pub fn read_json(
+            ctx: NativeCallContext,
+            path: PathBuf,
+        ) -> Result<Dynamic, Box<EvalAltResult>> {
+            let path = resolve(&ctx, &path)?;
+            let text = read_text(&ctx, &path)?;
+            serde_json::from_str(&text).map_err(|e| data_error(&path, e))
+        }
+
+        /// Helper function for `read_json` that takes a string instead of `PathBuf`.
+        #[rhai_fn(return_raw, name = "read_json")]
+        pub fn read_json_str(
+            ctx: NativeCallContext,
+            path_raw: ImmutableString,
+        ) -> Result<Dynamic, Box<EvalAltResult>> {
+            let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+            read_json(ctx, path)
+        }
+
+        /// Writes the value to a JSON file, pretty-printed, creating or truncating it.
+        ///
+        /// See `write_json(path, value, options)` for details.
+        #[rhai_fn(return_raw)]
+        pub fn write_json(
+            ctx: NativeCallContext,
+            path: PathBuf,
+            value: Dynamic,
+        ) -> Result<(), Box<EvalAltResult>> {
+            write_json_with_options(ctx, path, value, Map::new())
+        }
+
+        /// Helper function for `write_json` that takes a string instead of `PathBuf`.
+        #[rhai_fn(return_raw, name = "write_json")]
+        pub fn write_json_str(
+            ctx: NativeCallContext,
+            path_raw: ImmutableString,
+            value: Dynamic,
+        ) -> Result<(), Box<EvalAltResult>> {
+            let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+            write_json(ctx, path, value)
+        }
+
+        /// Writes the value to a JSON file, creating or truncating it.
+        ///
+        /// Options:
+        /// - `pretty`: indent the JSON over several lines, defaults to `true`.
+        ///
+        /// Throws an exception when:
+        /// - The value can't be represented in JSON.
+        /// - The file can't be written.
+        /// - An option is unknown or has the wrong type.
+        #[rhai_fn(return_raw, name = "write_json")]
+        pub fn write_json_with_options(
+            ctx: NativeCallContext,
+            path: PathBuf,
+            value: Dynamic,
+            options: Map,
+        ) -> Result<(), Box<EvalAltResult>> {
+            let path = resolve(&ctx, &path)?;
+            let text = if pretty_option(&options)? {
+                serde_json::to_string_pretty(&value)
+            } else {
+                serde_json::to_string(&value)
+            };
+            write_text(&path, &(text.map_err(|e| data_error(&path, e))? + "\n"))
+        }
+
+        /// Helper function for `write_json` that takes a string instead of `PathBuf`.
+        #[rhai_fn(return_raw, name = "write_json")]
+        pub fn write_json_with_options_str(
+            ctx: NativeCallContext,
+            path_raw: ImmutableString,
+            value: Dynamic,
+            options: Map,
+        ) -> Result<(), Box<EvalAltResult>> {
+            let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+            write_json_with_options(ctx, path, value, options)
+        }
+    }
+
+    #[cfg(feature = "toml")]
+    pub mod toml_functions {
+        use super::super::{
+            data_error, pretty_option, read_text, resolve, toml_datetimes, write_text,
+        };
+        use rhai::Map;
+        use std::path::PathBuf;
+
+        /// Reads a TOML file and returns its table as a map.
+        ///
+        /// Dates and times are returned as strings. The whole file is read, so it must not be larger than the engine's `max_string_size`.
+        ///
+        /// Throws an exception when:
+        /// - The file can't be read, isn't valid UTF-8 or is too large.
+        /// - The file isn't valid TOML.
+        #[rhai_fn(return_raw)]
+        pub fn read_toml(
+            ctx: NativeCallContext,
+            path: PathBuf,
+        ) -> Result<Dynamic, Box<EvalAltResult>> {
+            let path = resolve(&ctx, &path)?;
+            let text = read_text(&ctx, &path)?;
+            toml::from_str(&text)
+                .map(toml_datetimes)
+                .map_err(|e| data_error(&path, e))
+        }
+
+        /// Helper function for `read_toml` that takes a string instead of `PathBuf`.
+        #[rhai_fn(return_raw, name = "read_toml")]
+        pub fn read_toml_str(
+            ctx: NativeCallContext,
+            path_raw: ImmutableString,
+        ) -> Result<Dynamic, Box<EvalAltResult>> {
+            let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+            read_toml(ctx, path)
+        }
+
+        /// Writes the map to a TOML file, pretty-printed, creating or truncating it.
+        ///
+        /// See `write_toml(path, value, options)` for details.
+        #[rhai_fn(return_raw)]
+        pub fn write_toml(
+            ctx: NativeCallContext,
+            path: PathBuf,
+            value: Map,
+        ) -> Result<(), Box<EvalAltResult>> {
+            write_toml_with_options(ctx, path, value, Map::new())
+        }
+
+        /// Helper function for `write_toml` that takes a string instead of `PathBuf`.
+        #[rhai_fn(return_raw, name = "write_toml")]
+        pub fn write_toml_str(
+            ctx: NativeCallContext,
+            path_raw: ImmutableString,
+            value: Map,
+        ) -> Result<(), Box<EvalAltResult>> {
+            let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+            write_toml(ctx, path, value)
+        }
+
+        /// Writes the map to a TOML file as its table, creating or truncating it.
+        ///
+        /// Options:
+        /// - `pretty`: write arrays over several lines, defaults to `true`.
+        ///
+        /// Throws an exception when:
+        /// - The map can't be represented in TOML, e.g. it holds `()`.
+        /// - The file can't be written.
+        /// - An option is unknown or has the wrong type.
+        #[rhai_fn(return_raw, name = "write_toml")]
+        pub fn write_toml_with_options(
+            ctx: NativeCallContext,
+            path: PathBuf,
+            value: Map,
+            options: Map,
+        ) -> Result<(), Box<EvalAltResult>> {
+            let path = resolve(&ctx, &path)?;
+            let value = Dynamic::from_map(value);
+            let text = if pretty_option(&options)? {
+                toml::to_string_pretty(&value)
+            } else {
+                toml::to_string(&value)
+            };
+            write_text(&path, &text.map_err(|e| data_error(&path, e))?)
+        }
+
+        /// Helper function for `write_toml` that takes a string instead of `PathBuf`.
+        #[rhai_fn(return_raw, name = "write_toml")]
+        pub fn write_toml_with_options_str(
+            ctx: NativeCallContext,
+            path_raw: ImmutableString,
+            value: Map,
+            options: Map,
+        ) -> Result<(), Box<EvalAltResult>> {
+            let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+            write_toml_with_options(ctx, path, value, options)
+        }
+    }
+
+    #[cfg(feature = "yaml")]
+    pub mod yaml_functions {
+        use super::super::{data_error, read_text, resolve, write_text, yaml_options};
+        use rhai::Map;
+        use std::path::PathBuf;
+
+        /// Reads a YAML file and returns its value, mappings becoming maps and sequences becoming arrays.
+        ///
+        /// Only the first document of the file is read. The whole file is read, so it must not be larger than the engine's `max_string_size`.
+        ///
+        /// Throws an exception when:
+        /// - The file can't be read, isn't valid UTF-8 or is too large.
+        /// - The file isn't valid YAML.
+        #[rhai_fn(return_raw)]
+        pub fn read_yaml(
+            ctx: NativeCallContext,
+            path: PathBuf,
+        ) -> Result<Dynamic, Box<EvalAltResult>> {
+            let path = resolve(&ctx, &path)?;
+            let text = read_text(&ctx, &path)?;
+            serde_yaml::from_str(&text).map_err(|e| data_error(&path, e))
+        }
+
+        /// Helper function for `read_yaml` that takes a string instead of `PathBuf`.
+        #[rhai_fn(return_raw, name = "read_yaml")]
+        pub fn read_yaml_str(
+            ctx: NativeCallContext,
+            path_raw: ImmutableString,
+        ) -> Result<Dynamic, Box<EvalAltResult>> {
+            let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+            read_yaml(ctx, path)
+        }
+
+        /// Writes the value to a YAML file in block style, creating or truncating it.
+        ///
+        /// See `write_yaml(path, value, options)` for details.
+        #[rhai_fn(return_raw)]
+        pub fn write_yaml(
+            ctx: NativeCallContext,
+            path: PathBuf,
+            value: Dynamic,
+        ) -> Result<(), Box<EvalAltResult>> {
+            write_yaml_with_options(ctx, path, value, Map::new())
+        }
+
+        /// Helper function for `write_yaml` that takes a string instead of `PathBuf`.
+        #[rhai_fn(return_raw, name = "write_yaml")]
+        pub fn write_yaml_str(
+            ctx: NativeCallContext,
+            path_raw: ImmutableString,
+            value: Dynamic,
+        ) -> Result<(), Box<EvalAltResult>> {
+            let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+            write_yaml(ctx, path, value)
+        }
+
+        /// Writes the value to a YAML file in block style, creating or truncating it.
+        ///
+        /// No options are supported yet, the map is there to match the other writers.
+        ///
+        /// Throws an exception when:
+        /// - The value can't be represented in YAML.
+        /// - The file can't be written.
+        /// - An option is unknown.
+        #[rhai_fn(return_raw, name = "write_yaml")]
+        pub fn write_yaml_with_options(
+            ctx: NativeCallContext,
+            path: PathBuf,
+            value: Dynamic,
+            options: Map,
+        ) -> Result<(), Box<EvalAltResult>> {
+            yaml_options(&options)?;
+            let path = resolve(&ctx, &path)?;
+            let text = serde_yaml::to_string(&value).map_err(|e| data_error(&path, e))?;
+            write_text(&path, &text)
+        }
+
+        /// Helper function for `write_yaml` that takes a string instead of `PathBuf`.
+        #[rhai_fn(return_raw, name = "write_yaml")]
+        pub fn write_yaml_with_options_str(
+            ctx: NativeCallContext,
+            path_raw: ImmutableString,
+            value: Dynamic,
+            options: Map,
+        ) -> Result<(), Box<EvalAltResult>> {
+            let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+            write_yaml_with_options(ctx, path, value, options)
+        }
+    }
+
+    #[cfg(feature = "csv")]
+    pub mod csv_functions {
+        use super::super::{csv_field, data_error, read_text, resolve, write_text, CsvOptions};
+        use rhai::{Array, Map};
+        use std::path::PathBuf;
+
+        /// Reads a CSV file with a header row and returns an array with a map for each record.
+        ///
+        /// See `read_csv(path, options)` for details.
+        #[rhai_fn(return_raw)]
+        pub fn read_csv(
+            ctx: NativeCallContext,
+            path: PathBuf,
+        ) -> Result<Array, Box<EvalAltResult>> {
+            read_csv_with_options(ctx, path, Map::new())
+        }
+
+        /// Helper function for `read_csv` that takes a string instead of `PathBuf`.
+        #[rhai_fn(return_raw, name = "read_csv")]
+        pub fn read_csv_str(
+            ctx: NativeCallContext,
+            path_raw: ImmutableString,
+        ) -> Result<Array, Box<EvalAltResult>> {
+            let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+            read_csv(ctx, path)
+        }
+
+        /// Reads a CSV file and returns an array of its records.
+        ///
+        /// With a header row each record is a map from column names to fields, otherwise it is an array of fields.
+        /// Fields are returned as strings. The whole file is read, so it must not be larger than the engine's `max_string_size`.
+        ///
+        /// Options:
+        /// - `headers`: whether the first row holds the column names, defaults to `true`.
+        /// - `delimiter`: the character separating fields, defaults to `","`.
+        ///
+        /// Throws an exception when:
+        /// - The file can't be read, isn't valid UTF-8 or is too large.
+        /// - A record has a different number of fields than the first one.
+        /// - An option is unknown or has the wrong type.
+        #[rhai_fn(return_raw, name = "read_csv")]
+        pub fn read_csv_with_options(
+            ctx: NativeCallContext,
+            path: PathBuf,
+            options: Map,
+        ) -> Result<Array, Box<EvalAltResult>> {
+            let options = CsvOptions::from_map(&options, false)?;
+            let path = resolve(&ctx, &path)?;
+            let text = read_text(&ctx, &path)?;
+            let mut reader = csv::ReaderBuilder::new()
+                .has_headers(options.headers)
+                .delimiter(options.delimiter)
+                .from_reader(text.as_bytes());
+
+            let headers = match options.headers {
+                true => Some(reader.headers().map_err(|e| data_error(&path, e))?.clone()),
+                false => None,
+            };
+            reader
+                .records()
+                .map(|record| {
+                    let record = record.map_err(|e| data_error(&path, e))?;
+                    let fields = record.iter().map(ImmutableString::from);
+                    Ok(match &headers {
+                        Some(headers) => headers
+                            .iter()
+                            .map(Into::into)
+                            .zip(fields.map(Into::into))
+                            .collect::<Map>()
+                            .into(),
+                        None => fields.map(Into::into).collect::<Array>().into(),
+                    })
+                })
+                .collect()
+        }
+
+        /// Helper function for `read_csv` that takes a string instead of `PathBuf`.
+        #[rhai_fn(return_raw, name = "read_csv")]
+        pub fn read_csv_with_options_str(
+            ctx: NativeCallContext,
+            path_raw: ImmutableString,
+            options: Map,
+        ) -> Result<Array, Box<EvalAltResult>> {
+            let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+            read_csv_with_options(ctx, path, options)
+        }
+
+        /// Writes the records to a CSV file with the default options, creating or truncating it.
+        ///
+        /// See `write_csv(path, records, options)` for details.
+        #[rhai_fn(return_raw)]
+        pub fn write_csv(
+            ctx: NativeCallContext,
+            path: PathBuf,
+            records: Array,
+        ) -> Result<(), Box<EvalAltResult>> {
+            write_csv_with_options(ctx, path, records, Map::new())
+        }
+
+        /// Helper function for `write_csv` that takes a string instead of `PathBuf`.
+        #[rhai_fn(return_raw, name = "write_csv")]
+        pub fn write_csv_str(
+            ctx: NativeCallContext,
+            path_raw: ImmutableString,
+            records: Array,
+        ) -> Result<(), Box<EvalAltResult>> {
+            let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+            write_csv(ctx, path, records)
+        }
+
+        /// Writes the records to a CSV file, creating or truncating it.
+        ///
+        /// Each record is either an array of fields, or a map from column names to fields.
+        /// Maps are written in the order of `columns`, by default the sorted keys of the first map, and missing keys are left empty.
+        /// Fields are written as strings, `()` being an empty field.
+        ///
+        /// Options:
+        /// - `headers`: write the column names as the first row, defaults to `true`. Needs `columns` when the records are arrays.
+        /// - `delimiter`: the character separating fields, defaults to `","`.
+        /// - `columns`: an array of the column names to write.
+        ///
+        /// Throws an exception when:
+        /// - A record is neither an array nor a map.
+        /// - `headers` is set, the first record is an array and `columns` isn't passed.
+        /// - The file can't be written.
+        /// - An option is unknown or has the wrong type.
+        #[rhai_fn(return_raw, name = "write_csv")]
+        pub fn write_csv_with_options(
+            ctx: NativeCallContext,
+            path: PathBuf,
+            records: Array,
+            options: Map,
+        ) -> Result<(), Box<EvalAltResult>> {
+            let options = CsvOptions::from_map(&options, true)?;
+            let path = resolve(&ctx, &path)?;
+            let columns = options.columns.or_else(|| {
+                let first = records.first()?.read_lock::<Map>()?;
+                Some(first.keys().map(|key| key.to_string()).collect())
+            });
+            if options.headers && columns.is_none() && !records.is_empty() {
+                return Err(
+                    "Option columns is needed to write headers when the records are arrays".into(),
+                );
+            }
+
+            let mut writer = csv::WriterBuilder::new()
+                .delimiter(options.delimiter)
+                .flexible(true)
+                .from_writer(Vec::new());
+            let err = |e: csv::Error| data_error(&path, e);
+            if let Some(columns) = columns.as_ref().filter(|_| options.headers) {
+                writer.write_record(columns).map_err(err)?;
+            }
+            for record in &records {
+                if let Some(map) = record.read_lock::<Map>() {
+                    let columns = columns.as_deref().unwrap_or_default();
+                    let fields = columns
+                        .iter()
+                        .map(|column| map.get(column.as_str()).map(csv_field).unwrap_or_default());
+                    writer.write_record(fields).map_err(err)?;
+                } else if let Some(array) = record.read_lock::<Array>() {
+                    writer
+                        .write_record(array.iter().map(csv_field))
+                        .map_err(err)?;
+                } else {
+                    return Err(format!(
+                        "Record must be an array or a map, not {}",
+                        record.type_name()
+                    )
+                    .into());
+                }
+            }
+
+            let bytes = writer
+                .into_inner()
+                .map_err(|e| data_error(&path, e.error()))?;
+            write_text(&path, &String::from_utf8_lossy(&bytes))
+        }
+
+        /// Helper function for `write_csv` that takes a string instead of `PathBuf`.
+        #[rhai_fn(return_raw, name = "write_csv")]
+        pub fn write_csv_with_options_str(
+            ctx: NativeCallContext,
+            path_raw: ImmutableString,
+            records: Array,
+            options: Map,
+        ) -> Result<(), Box<EvalAltResult>> {
+            let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+            write_csv_with_options(ctx, path, records, options)
+        }
+    }
+}
@@ -0,0 +1,770 @@
+#[allow(unused_imports)]
+use rhai::plugin::*;
+use rhai::{Array, Map};
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use super::config::{resolve, tick};
+#[cfg(feature = "diff")]
+use super::file::read_text;
+
+/// Size of the chunks files are compared in.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// A glob pattern matched against paths relative to a directory, using `/` as the separator.
+///
+/// `*` and `?` never match a `/`, while `**` matches across directories.
+/// Patterns without a `/` match the name of an entry at any depth, others match the whole relative path.
+pub(crate) struct Glob {
+    /// The pattern, without a leading `/`.
+    pattern: Vec<char>,
+    /// Whether the pattern is matched against the whole relative path rather than the name.
+    anchored: bool,
+}
+
+impl Glob {
+    /// Parses a pattern.
+    pub(crate) fn new(pattern: &str) -> Self {
+        let pattern = pattern.trim_end_matches('/');
+        Glob {
+            anchored: pattern.contains('/'),
+            pattern: pattern.trim_start_matches('/').chars().collect(),
+        }
+    }
+
+    /// Parses a pattern matched against the whole relative path, even without a `/`.
+    pub(crate) fn anchored(pattern: &str) -> Self {
+        Glob {
+            anchored: true,
+            ..Glob::new(pattern)
+        }
+    }
+
+    /// Parses an array of patterns from a script option.
+    pub(crate) fn from_array(
+        option: &str,
+        value: &Dynamic,
+    ) -> Result<Vec<Self>, Box<EvalAltResult>> {
+        let invalid = || format!("Option {option} must be an array of strings");
+        value
+            .read_lock::<Array>()
+            .ok_or_else(invalid)?
+            .iter()
+            .map(|pattern| {
+                pattern
+                    .read_lock::<ImmutableString>()
+                    .map(|pattern| Glob::new(&pattern))
+                    .ok_or_else(|| invalid().into())
+            })
+            .collect()
+    }
+
+    /// Returns `true` if the relative path matches.
+    pub(crate) fn matches(&self, rel: &str) -> bool {
+        let target = if self.anchored {
+            rel
+        } else {
+            rel.rsplit('/').next().unwrap_or(rel)
+        };
+        glob_match(&self.pattern, &target.chars().collect::<Vec<_>>())
+    }
+}
+
+/// Matches a glob pattern against the whole text.
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        ['*', '*', rest @ ..] => {
+            // `**/` also matches no directory at all.
+            let after_slash = rest.strip_prefix(&['/']);
+            after_slash.is_some_and(|rest| glob_match(rest, text))
+                || (0..=text.len()).any(|idx| glob_match(rest, &text[idx..]))
+        }
+        ['*', rest @ ..] => {
+            let end = text.iter().position(|&c| c == '/').unwrap_or(text.len());
+            (0..=end).any(|idx| glob_match(rest, &text[idx..]))
+        }
+        ['?', rest @ ..] => matches!(text, [c, ..] if *c != '/') && glob_match(rest, &text[1..]),
+        [c, rest @ ..] => text.first() == Some(c) && glob_match(rest, &text[1..]),
+    }
+}
+
+/// Reads into the buffer until it is full or EOF is reached, returning the number of bytes read.
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut len = 0;
+    while len < buf.len() {
+        match reader.read(&mut buf[len..]) {
+            Ok(0) => break,
+            Ok(read) => len += read,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(len)
+}
+
+/// Returns `true` if both files have the same content, comparing sizes first and then chunks until the first difference.
+pub(crate) fn same_content(a: &Path, b: &Path) -> Result<bool, Box<EvalAltResult>> {
+    let open = |path: &Path| {
+        File::open(path)
+            .and_then(|file| file.metadata().map(|meta| (file, meta.len())))
+            .map_err(|e| format!("{}: {e}", path.to_string_lossy()))
+    };
+    let (mut file_a, len_a) = open(a)?;
+    let (mut file_b, len_b) = open(b)?;
+    if len_a != len_b {
+        return Ok(false);
+    }
+
+    let mut buf_a = vec![0; CHUNK_SIZE];
+    let mut buf_b = vec![0; CHUNK_SIZE];
+    loop {
+        let read_a = read_full(&mut file_a, &mut buf_a).map_err(|e| e.to_string())?;
+        let read_b = read_full(&mut file_b, &mut buf_b).map_err(|e| e.to_string())?;
+        if buf_a[..read_a] != buf_b[..read_b] {
+            return Ok(false);
+        }
+        if read_a == 0 {
+            return Ok(true);
+        }
+    }
+}
+
+/// How files present in both trees are compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Compare {
+    /// Files differ when their size or modification time differs.
+    Metadata,
+    /// Files differ when their content differs.
+    Content,
+}
+
+/// The kind of an entry in a directory tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    /// A regular file.
+    File,
+    /// A directory.
+    Dir,
+    /// A symbolic link, which is never followed.
+    Symlink,
+}
+
+/// An entry found while scanning a directory tree.
+struct TreeEntry {
+    /// Where the entry is on disk.
+    path: PathBuf,
+    /// What the entry is.
+    kind: Kind,
+    /// The size of a file, in bytes.
+    len: u64,
+    /// The modification time, if the platform has it.
+    modified: Option<SystemTime>,
+    /// The target of a symlink.
+    link: Option<PathBuf>,
+}
+
+/// The entries of a directory tree by their `/` separated path relative to its root.
+type Tree = BTreeMap<String, TreeEntry>;
+
+/// Scans the directory tree at the root, skipping excluded entries and everything below excluded directories.
+///
+/// Special files such as sockets are ignored.
+fn scan(
+    ctx: &NativeCallContext,
+    root: &Path,
+    exclude: &[Glob],
+) -> Result<Tree, Box<EvalAltResult>> {
+    if !root.is_dir() {
+        return Err(format!("{}: not a directory", root.to_string_lossy()).into());
+    }
+    let mut tree = Tree::new();
+    scan_into(ctx, root, "", exclude, &mut tree)?;
+    Ok(tree)
+}
+
+/// Adds the entries below `dir` to the tree, `prefix` being the relative path of `dir`.
+fn scan_into(
+    ctx: &NativeCallContext,
+    dir: &Path,
+    prefix: &str,
+    exclude: &[Glob],
+    tree: &mut Tree,
+) -> Result<(), Box<EvalAltResult>> {
+    tick(ctx)?;
+    let display = dir.to_string_lossy();
+    let children = std::fs::read_dir(dir)
+        .and_then(|dir| dir.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("{display}: {e}"))?;
+
+    for child in children {
+        let path = child.path();
+        let name = child.file_name();
+        let name = name
+            .to_str()
+            .ok_or_else(|| format!("{}: path isn't valid unicode", path.to_string_lossy()))?;
+        let rel = format!("{prefix}{name}");
+        if exclude.iter().any(|glob| glob.matches(&rel)) {
+            continue;
+        }
+
+        let meta = std::fs::symlink_metadata(&path)
+            .map_err(|e| format!("{}: {e}", path.to_string_lossy()))?;
+        let (kind, link) = if meta.is_symlink() {
+            let link = std::fs::read_link(&path)
+                .map_err(|e| format!("{}: {e}", path.to_string_lossy()))?;
+            (Kind::Symlink, Some(link))
+        } else if meta.is_dir() {
+            (Kind::Dir, None)
+        } else if meta.is_file() {
+            (Kind::File, None)
+        } else {
+            continue;
+        };
+
+        if kind == Kind::Dir {
+            scan_into(ctx, &path, &format!("{rel}/"), exclude, tree)?;
+        }
+        tree.insert(
+            rel,
+            TreeEntry {
+                path,
+                kind,
+                len: meta.len(),
+                modified: meta.modified().ok(),
+                link,
+            },
+        );
+    }
+    Ok(())
+}
+
+/// Returns `true` if an entry present in both trees differs.
+fn differs(a: &TreeEntry, b: &TreeEntry, compare: Compare) -> Result<bool, Box<EvalAltResult>> {
+    match (a.kind, b.kind) {
+        (Kind::File, Kind::File) => match compare {
+            Compare::Metadata => Ok(a.len != b.len || a.modified != b.modified),
+            Compare::Content => same_content(&a.path, &b.path).map(|same| !same),
+        },
+        (Kind::Symlink, Kind::Symlink) => Ok(a.link != b.link),
+        (Kind::Dir, Kind::Dir) => Ok(false),
+        _ => Ok(true),
+    }
+}
+
+/// Options shared by `diff_dirs` and `sync_dir`.
+struct TreeOptions {
+    /// How files in both trees are compared.
+    compare: Compare,
+    /// Patterns of entries to leave alone.
+    exclude: Vec<Glob>,
+    /// Whether `sync_dir` deletes entries missing from the source.
+    delete: bool,
+    /// Whether `sync_dir` only reports what it would do.
+    dry_run: bool,
+}
+
+impl TreeOptions {
+    /// Reads the options from a script map, throwing on unknown keys and values of the wrong type.
+    ///
+    /// `delete` and `dry_run` are only accepted when `sync` is set.
+    fn from_map(map: &Map, sync: bool) -> Result<Self, Box<EvalAltResult>> {
+        let mut options = TreeOptions {
+            compare: Compare::Metadata,
+            exclude: Vec::new(),
+            delete: false,
+            dry_run: false,
+        };
+
+        for (key, value) in map {
+            let invalid = |kind: &str| format!("Option {key} must be {kind}");
+            match key.as_str() {
+                "compare" => {
+                    let compare = value
+                        .read_lock::<ImmutableString>()
+                        .ok_or_else(|| invalid("a string"))?;
+                    options.compare = match compare.as_str() {
+                        "metadata" => Compare::Metadata,
+                        "content" => Compare::Content,
+                        _ => {
+                            return Err(format!(
+                                "Unknown comparison {}, expected metadata or content",
+                                compare.as_str()
+                            )
+                            .into())
+                        }
+                    };
+                }
+                "exclude" => options.exclude = Glob::from_array(key, value)?,
+                "delete" if sync => {
+                    options.delete = value.as_bool().map_err(|_| invalid("a bool"))?
+                }
+                "dry_run" if sync => {
+                    options.dry_run = value.as_bool().map_err(|_| invalid("a bool"))?
+                }
+                _ => return Err(format!("Unknown option {key}").into()),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// Converts relative paths into a script array.
+fn to_array(paths: Vec<&String>) -> Array {
+    paths.into_iter().map(|path| path.as_str().into()).collect()
+}
+
+/// Checks that the directory at `rel` in the destination tree can be replaced by a file or symlink.
+///
+/// Replacing a directory deletes what's in it, so that needs the `delete` option,
+/// and excluded entries are never deleted, so a directory holding any can't be replaced.
+fn check_replaceable(
+    ctx: &NativeCallContext,
+    dst_tree: &Tree,
+    rel: &str,
+    old: &TreeEntry,
+    options: &TreeOptions,
+) -> Result<(), Box<EvalAltResult>> {
+    let display = old.path.to_string_lossy();
+    if !options.delete {
+        return Err(format!("{display}: replacing a directory needs the delete option").into());
+    }
+
+    let prefix = format!("{rel}/");
+    let listed = dst_tree
+        .range(prefix.clone()..)
+        .take_while(|(path, _)| path.starts_with(&prefix))
+        .count();
+    if scan(ctx, &old.path, &[])?.len() != listed {
+        return Err(
+            format!("{display}: can't replace a directory holding excluded entries").into(),
+        );
+    }
+    Ok(())
+}
+
+/// Replaces the entry at `target` with a copy of the source entry, never writing through a symlink.
+///
+/// A directory at `target` is only removed once it is empty, so nothing in it is removed unreported.
+fn copy_entry(entry: &TreeEntry, target: &Path) -> Result<(), Box<EvalAltResult>> {
+    let display = target.to_string_lossy();
+    let err = |e: std::io::Error| format!("{display}: {e}");
+
+    match std::fs::symlink_metadata(target) {
+        Ok(meta) if meta.is_dir() && entry.kind == Kind::Dir => return Ok(()),
+        Ok(meta) if meta.is_dir() => std::fs::remove_dir(target).map_err(err)?,
+        Ok(_) => std::fs::remove_file(target).map_err(err)?,
+        Err(_) => (),
+    }
+
+    match entry.kind {
+        Kind::Dir => std::fs::create_dir(target).map_err(err)?,
+        Kind::File => {
+            std::fs::copy(&entry.path, target).map_err(err)?;
+            // Keep the modification time, so the next sync sees the file as unchanged.
+            if let Some(modified) = entry.modified {
+                File::options()
+                    .write(true)
+                    .open(target)
+                    .and_then(|file| file.set_modified(modified))
+                    .map_err(err)?;
+            }
+        }
+        Kind::Symlink => {
+            let link = entry.link.as_deref().unwrap_or(Path::new(""));
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(link, target).map_err(err)?;
+            #[cfg(not(unix))]
+            return Err(format!(
+                "{display}: symlinks to {} can only be synced on Unix",
+                link.to_string_lossy()
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// Number of unchanged lines shown around each change of a unified diff by default.
+#[cfg(feature = "diff")]
+pub(crate) const DEFAULT_CONTEXT: usize = 3;
+
+/// Options of `diff_text`.
+#[cfg(feature = "diff")]
+struct DiffOptions {
+    /// Number of unchanged lines shown around each change.
+    context: usize,
+}
+
+#[cfg(feature = "diff")]
+impl DiffOptions {
+    /// Reads the options from a script map, throwing on unknown keys and values of the wrong type.
+    fn from_map(map: &Map) -> Result<Self, Box<EvalAltResult>> {
+        let mut options = DiffOptions {
+            context: DEFAULT_CONTEXT,
+        };
+
+        for (key, value) in map {
+            match key.as_str() {
+                "context" => {
+                    options.context = value
+                        .as_int()
+                        .ok()
+                        .and_then(|context| usize::try_from(context).ok())
+                        .ok_or_else(|| format!("Option {key} must be a non-negative integer"))?
+                }
+                _ => return Err(format!("Unknown option {key}").into()),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// Returns the unified diff from `a` to `b`, labelled with the passed names, or an empty string when they are equal.
+#[cfg(feature = "diff")]
+pub(crate) fn unified_diff(a: &str, b: &str, names: (&str, &str), context: usize) -> String {
+    similar::TextDiff::from_lines(a, b)
+        .unified_diff()
+        .context_radius(context)
+        .header(names.0, names.1)
+        .to_string()
+}
+
+#[export_module]
+pub mod diff_functions {
+    /// Compares two directory trees with the default options.
+    ///
+    /// See `diff_dirs(a, b, options)` for details.
+    #[rhai_fn(return_raw)]
+    pub fn diff_dirs(
+        ctx: NativeCallContext,
+        a: PathBuf,
+        b: PathBuf,
+    ) -> Result<Map, Box<EvalAltResult>> {
+        diff_dirs_with_options(ctx, a, b, Map::new())
+    }
+
+    /// Helper function for `diff_dirs` that takes strings instead of `PathBuf`.
+    #[rhai_fn(return_raw, name = "diff_dirs")]
+    pub fn diff_dirs_str(
+        ctx: NativeCallContext,
+        a_raw: ImmutableString,
+        b_raw: ImmutableString,
+    ) -> Result<Map, Box<EvalAltResult>> {
+        let a = ctx.call_native_fn::<PathBuf>("path", (a_raw,))?;
+        let b = ctx.call_native_fn::<PathBuf>("path", (b_raw,))?;
+        diff_dirs(ctx, a, b)
+    }
+
+    /// Compares the directory tree at `a` to the one at `b`, returning a map of `/` separated paths relative to them:
+    /// - `added`: entries only in `b`.
+    /// - `removed`: entries only in `a`.
+    /// - `modified`: files and symlinks in both that differ, and entries whose kind changed.
+    ///
+    /// Each array is sorted, and directories are listed along with everything below them.
+    /// Symlinks are compared by their target and never followed.
+    ///
+    /// Options:
+    /// - `compare`: `"metadata"` to compare files by size and modification time, the default, or `"content"` to compare their bytes.
+    /// - `exclude`: an array of glob patterns of entries to skip, where `*` and `?` stay within a directory and `**` matches across directories.
+    ///   Patterns without a `/` match entry names at any depth, others match the whole relative path.
+    ///
+    /// Throws an exception when:
+    /// - Either path isn't a directory.
+    /// - An entry can't be read.
+    /// - An option is unknown or has the wrong type.
+    #[rhai_fn(return_raw, name = "diff_dirs")]
+    pub fn diff_dirs_with_options(
+        ctx: NativeCallContext,
+        a: PathBuf,
+        b: PathBuf,
+        options: Map,
+    ) -> Result<Map, Box<EvalAltResult>> {
+        let options = TreeOptions::from_map(&options, false)?;
+        let tree_a = scan(&ctx, &resolve(&ctx, &a)?, &options.exclude)?;
+        let tree_b = scan(&ctx, &resolve(&ctx, &b)?, &options.exclude)?;
+
+        let mut modified = Vec::new();
+        for (rel, entry) in &tree_b {
+            if let Some(old) = tree_a.get(rel) {
+                tick(&ctx)?;
+                if differs(old, entry, options.compare)? {
+                    modified.push(rel);
+                }
+            }
+        }
+        let added = tree_b
+            .keys()
+            .filter(|rel| !tree_a.contains_key(*rel))
+            .collect();
+        let removed = tree_a
+            .keys()
+            .filter(|rel| !tree_b.contains_key(*rel))
+            .collect();
+
+        let mut result = Map::new();
+        result.insert("added".into(), to_array(added).into());
+        result.insert("removed".into(), to_array(removed).into());
+        result.insert("modified".into(), to_array(modified).into());
+        Ok(result)
+    }
+
+    /// Helper function for `diff_dirs` that takes strings instead of `PathBuf`.
+    #[rhai_fn(return_raw, name = "diff_dirs")]
+    pub fn diff_dirs_with_options_str(
+        ctx: NativeCallContext,
+        a_raw: ImmutableString,
+        b_raw: ImmutableString,
+        options: Map,
+    ) -> Result<Map, Box<EvalAltResult>> {
+        let a = ctx.call_native_fn::<PathBuf>("path", (a_raw,))?;
+        let b = ctx.call_native_fn::<PathBuf>("path", (b_raw,))?;
+        diff_dirs_with_options(ctx, a, b, options)
+    }
+
+    /// Mirrors a directory tree with the default options.
+    ///
+    /// See `sync_dir(src, dst, options)` for details.
+    #[rhai_fn(return_raw)]
+    pub fn sync_dir(
+        ctx: NativeCallContext,
+        src: PathBuf,
+        dst: PathBuf,
+    ) -> Result<Map, Box<EvalAltResult>> {
+        sync_dir_with_options(ctx, src, dst, Map::new())
+    }
+
+    /// Helper function for `sync_dir` that takes strings instead of `PathBuf`.
+    #[rhai_fn(return_raw, name = "sync_dir")]
+    pub fn sync_dir_str(
+        ctx: NativeCallContext,
+        src_raw: ImmutableString,
+        dst_raw: ImmutableString,
+    ) -> Result<Map, Box<EvalAltResult>> {
+        let src = ctx.call_native_fn::<PathBuf>("path", (src_raw,))?;
+        let dst = ctx.call_native_fn::<PathBuf>("path", (dst_raw,))?;
+        sync_dir(ctx, src, dst)
+    }
+
+    /// Copies what changed in the directory tree at `src` to `dst`, creating it if needed, and returns a report map.
+    ///
+    /// Entries are compared as in `diff_dirs`, and copied files keep their modification time so that unchanged files are skipped next time.
+    /// A symlink in `dst` is replaced rather than written through.
+    ///
+    /// Options:
+    /// - `compare`: `"metadata"`, the default, or `"content"`, see `diff_dirs`.
+    /// - `exclude`: an array of glob patterns, see `diff_dirs`. Excluded entries are neither copied nor deleted.
+    /// - `delete`: remove entries from `dst` that aren't in `src`, defaults to `false`. Needed to replace a directory in `dst` with a file.
+    /// - `dry_run`: only report what would be done, defaults to `false`.
+    ///
+    /// The report has `/` separated paths relative to the directories:
+    /// - `created`: entries copied to `dst` that weren't there.
+    /// - `updated`: entries in `dst` that were replaced.
+    /// - `deleted`: entries removed from `dst`, a directory is kept when excluded entries are left in it.
+    /// - `dry_run`: whether nothing was actually changed.
+    ///
+    /// Throws an exception when:
+    /// - `src` isn't a directory, or `dst` exists and isn't one.
+    /// - A directory in `dst` would be replaced by a file or symlink without the `delete` option, or while it holds excluded entries.
+    ///   This is checked before anything is changed.
+    /// - An entry can't be read, copied or deleted, in which case the changes made so far are kept.
+    /// - An option is unknown or has the wrong type.
+    #[rhai_fn(return_raw, name = "sync_dir")]
+    pub fn sync_dir_with_options(
+        ctx: NativeCallContext,
+        src: PathBuf,
+        dst: PathBuf,
+        options: Map,
+    ) -> Result<Map, Box<EvalAltResult>> {
+        let options = TreeOptions::from_map(&options, true)?;
+        let src_tree = scan(&ctx, &resolve(&ctx, &src)?, &options.exclude)?;
+        let dst = resolve(&ctx, &dst)?;
+        let dst_tree = match std::fs::symlink_metadata(&dst) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Tree::new(),
+            _ => scan(&ctx, &dst, &options.exclude)?,
+        };
+
+        if !options.dry_run {
+            std::fs::create_dir_all(&dst).map_err(|e| format!("{}: {e}", dst.to_string_lossy()))?;
+        }
+
+        // Parents sort before their children, so directories are created before what's in them.
+        let mut created = Vec::new();
+        let mut updated = Vec::new();
+        let mut copies = Vec::new();
+        for (rel, entry) in &src_tree {
+            tick(&ctx)?;
+            match dst_tree.get(rel) {
+                None => created.push(rel),
+                Some(old) if differs(entry, old, options.compare)? => {
+                    if old.kind == Kind::Dir {
+                        check_replaceable(&ctx, &dst_tree, rel, old, &options)?;
+                    }
+                    updated.push(rel);
+                }
+                Some(_) => continue,
+            }
+            copies.push((rel, entry));
+        }
+
+        // Children are deleted before their parents, and before the copies so that a directory
+        // replaced by a file is emptied first.
+        let mut deleted = Vec::new();
+        if options.delete {
+            for (rel, entry) in dst_tree.iter().rev() {
+                if src_tree.contains_key(rel) {
+                    continue;
+                }
+                tick(&ctx)?;
+                if !options.dry_run {
+                    let res = match entry.kind {
+                        Kind::Dir => std::fs::remove_dir(&entry.path),
+                        Kind::File | Kind::Symlink => std::fs::remove_file(&entry.path),
+                    };
+                    match res {
+                        Ok(()) => (),
+                        // Already gone, or kept for its excluded entries.
+                        Err(e) if e.kind() == std::io::ErrorKind::NotFound => (),
+                        Err(e) if e.kind() == std::io::ErrorKind::DirectoryNotEmpty => continue,
+                        Err(e) => {
+                            return Err(format!("{}: {e}", entry.path.to_string_lossy()).into())
+                        }
+                    }
+                }
+                deleted.push(rel);
+            }
+            deleted.reverse();
+        }
+
+        if !options.dry_run {
+            for (rel, entry) in copies {
+                tick(&ctx)?;
+                copy_entry(entry, &dst.join(rel))?;
+            }
+        }
+
+        let mut report = Map::new();
+        report.insert("created".into(), to_array(created).into());
+        report.insert("updated".into(), to_array(updated).into());
+        report.insert("deleted".into(), to_array(deleted).into());
+        report.insert("dry_run".into(), options.dry_run.into());
+        Ok(report)
+    }
+
+    /// Helper function for `sync_dir` that takes strings instead of `PathBuf`.
+    #[rhai_fn(return_raw, name = "sync_dir")]
+    pub fn sync_dir_with_options_str(
+        ctx: NativeCallContext,
+        src_raw: ImmutableString,
+        dst_raw: ImmutableString,
+        options: Map,
+    ) -> Result<Map, Box<EvalAltResult>> {
+        let src = ctx.call_native_fn::<PathBuf>("path", (src_raw,))?;
+        let dst = ctx.call_native_fn::<PathBuf>("path", (dst_raw,))?;
+        sync_dir_with_options(ctx, src, dst, options)
+    }
+
+    /// Returns `true` if both files have the same content.
+    ///
+    /// Sizes are compared first, then the files are read in chunks until the first difference, so neither is loaded whole.
+    ///
+    /// Throws an exception when either file can't be opened or read.
+    #[rhai_fn(return_raw)]
+    pub fn files_equal(
+        ctx: NativeCallContext,
+        a: PathBuf,
+        b: PathBuf,
+    ) -> Result<bool, Box<EvalAltResult>> {
+        same_content(&resolve(&ctx, &a)?, &resolve(&ctx, &b)?)
+    }
+
+    /// Helper function for `files_equal` that takes strings instead of `PathBuf`.
+    #[rhai_fn(return_raw, name = "files_equal")]
+    pub fn files_equal_str(
+        ctx: NativeCallContext,
+        a_raw: ImmutableString,
+        b_raw: ImmutableString,
+    ) -> Result<bool, Box<EvalAltResult>> {
+        let a = ctx.call_native_fn::<PathBuf>("path", (a_raw,))?;
+        let b = ctx.call_native_fn::<PathBuf>("path", (b_raw,))?;
+        files_equal(ctx, a, b)
+    }
+
+    #[cfg(feature = "diff")]
+    pub mod text_functions {
+        use super::super::{read_text, resolve, unified_diff, DiffOptions};
+        use rhai::Map;
+        use std::path::PathBuf;
+
+        /// Returns a unified diff of two UTF-8 text files with the default options, or an empty string when they are equal.
+        ///
+        /// See `diff_text(a, b, options)` for details.
+        #[rhai_fn(return_raw, name = "diff_text")]
+        pub fn diff_files(
+            ctx: NativeCallContext,
+            a: PathBuf,
+            b: PathBuf,
+        ) -> Result<ImmutableString, Box<EvalAltResult>> {
+            diff_files_with_options(ctx, a, b, Map::new())
+        }
+
+        /// Returns a unified diff from the text file at `a` to the one at `b`, or an empty string when they are equal.
+        ///
+        /// The headers name the files by the passed paths. Pass strings instead of paths to compare text.
+        ///
+        /// Options:
+        /// - `context`: the number of unchanged lines shown around each change, defaults to 3.
+        ///
+        /// Throws an exception when:
+        /// - Either file can't be read, isn't valid UTF-8 or is larger than the engine's `max_string_size`.
+        /// - An option is unknown or has the wrong type.
+        #[rhai_fn(return_raw, name = "diff_text")]
+        pub fn diff_files_with_options(
+            ctx: NativeCallContext,
+            a: PathBuf,
+            b: PathBuf,
+            options: Map,
+        ) -> Result<ImmutableString, Box<EvalAltResult>> {
+            let options = DiffOptions::from_map(&options)?;
+            let text_a = read_text(&ctx, &resolve(&ctx, &a)?)?;
+            let text_b = read_text(&ctx, &resolve(&ctx, &b)?)?;
+            let names = (a.to_string_lossy(), b.to_string_lossy());
+            Ok(unified_diff(&text_a, &text_b, (&names.0, &names.1), options.context).into())
+        }
+
+        /// Returns a unified diff of two strings with the default options, or an empty string when they are equal.
+        ///
+        /// See `diff_text(a, b, options)` for details.
+        #[rhai_fn(return_raw, name = "diff_text")]
+        pub fn diff_text(
+            a: ImmutableString,
+            b: ImmutableString,
+        ) -> Result<ImmutableString, Box<EvalAltResult>> {
+            diff_text_with_options(a, b, Map::new())
+        }
+
+        /// Returns a unified diff from the string `a` to the string `b`, or an empty string when they are equal.
+        ///
+        /// The strings are compared as text, the headers name them `a` and `b`. Pass paths instead of strings to compare files.
+        ///
+        /// Options:
+        /// - `context`: the number of unchanged lines shown around each change, defaults to 3.
+        ///
+        /// Throws an exception when an option is unknown or has the wrong type.
+        #[rhai_fn(return_raw, name = "diff_text")]
+        pub fn diff_text_with_options(
+            a: ImmutableString,
+            b: ImmutableString,
+            options: Map,
+        ) -> Result<ImmutableString, Box<EvalAltResult>> {
+            let options = DiffOptions::from_map(&options)?;
+            Ok(unified_diff(&a, &b, ("a", "b"), options.context).into())
+        }
+    }
+}
@@ -1,14 +1,185 @@
 #[allow(unused_imports)]
 use rhai::plugin::*;
 
+use rhai::Map;
+
+use std::collections::HashSet;
+use std::fs::Metadata;
+use std::path::Path;
+
+use super::config::{resolve, tick};
+use super::file::convert_to_int;
+
+/// What identifies a file or directory, so that it is only counted once.
+#[cfg(unix)]
+type FileKey = (u64, u64);
+
+/// What identifies a file or directory, so that it is only counted once.
+#[cfg(not(unix))]
+type FileKey = std::path::PathBuf;
+
+/// Returns the device and inode of an entry.
+#[cfg(unix)]
+fn file_key(_path: &Path, meta: &Metadata) -> Option<FileKey> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some((meta.dev(), meta.ino()))
+}
+
+/// Returns the canonical path of an entry.
+#[cfg(not(unix))]
+fn file_key(path: &Path, _meta: &Metadata) -> Option<FileKey> {
+    std::fs::canonicalize(path).ok()
+}
+
+/// Returns `true` if the file has other hard links, which may be found again.
+#[cfg(unix)]
+fn is_hard_linked(meta: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    meta.nlink() > 1
+}
+
+/// Returns `true` if the file has other hard links, which may be found again.
+///
+/// The link count isn't available on this platform.
+#[cfg(not(unix))]
+fn is_hard_linked(_meta: &Metadata) -> bool {
+    false
+}
+
+/// Returns the device an entry is on.
+#[cfg(unix)]
+fn device(meta: &Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some(meta.dev())
+}
+
+/// Returns the device an entry is on, which isn't available on this platform.
+#[cfg(not(unix))]
+fn device(_meta: &Metadata) -> Option<u64> {
+    None
+}
+
+/// Options of `dir_size`.
+struct SizeOptions {
+    /// Whether symlinks are followed rather than skipped.
+    follow_symlinks: bool,
+    /// Whether directories on other devices than the root are skipped.
+    same_device: bool,
+}
+
+impl SizeOptions {
+    /// Reads the options from a script map, throwing on unknown keys and values of the wrong type.
+    fn from_map(map: &Map) -> Result<Self, Box<EvalAltResult>> {
+        let mut options = SizeOptions {
+            follow_symlinks: false,
+            same_device: false,
+        };
+
+        for (key, value) in map {
+            let flag = value
+                .as_bool()
+                .map_err(|_| format!("Option {key} must be a bool"));
+            match key.as_str() {
+                "follow_symlinks" => options.follow_symlinks = flag?,
+                "same_device" => options.same_device = flag?,
+                _ => return Err(format!("Unknown option {key}").into()),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// Returns the total size of the files below the directory, counting each file once.
+fn total_size(
+    ctx: &NativeCallContext,
+    root: &Path,
+    options: &SizeOptions,
+) -> Result<u64, Box<EvalAltResult>> {
+    let err = |path: &Path, e: std::io::Error| format!("{}: {e}", path.to_string_lossy());
+    let root_meta = std::fs::metadata(root).map_err(|e| err(root, e))?;
+    if !root_meta.is_dir() {
+        return Err(format!("{}: not a directory", root.to_string_lossy()).into());
+    }
+    let root_device = device(&root_meta);
+    if options.same_device && root_device.is_none() {
+        return Err("Option same_device isn't supported on this platform".into());
+    }
+
+    let mut seen = HashSet::new();
+    seen.extend(file_key(root, &root_meta));
+    let mut total = 0u64;
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        tick(ctx)?;
+        let children = match std::fs::read_dir(&dir) {
+            Ok(children) => children,
+            // Removed while walking.
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(err(&dir, e).into()),
+        };
+
+        for child in children {
+            let path = child.map_err(|e| err(&dir, e))?.path();
+            let meta = if options.follow_symlinks {
+                std::fs::metadata(&path)
+            } else {
+                std::fs::symlink_metadata(&path)
+            };
+            let meta = match meta {
+                Ok(meta) => meta,
+                // Removed while walking, or a dangling symlink.
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(err(&path, e).into()),
+            };
+            if options.same_device && device(&meta) != root_device {
+                continue;
+            }
+
+            // Followed symlinks and hard links can lead back to an entry already counted.
+            let may_repeat = options.follow_symlinks || (meta.is_file() && is_hard_linked(&meta));
+            if may_repeat && file_key(&path, &meta).is_some_and(|key| !seen.insert(key)) {
+                continue;
+            }
+
+            if meta.is_dir() {
+                pending.push(path);
+            } else if meta.is_file() {
+                total = total.saturating_add(meta.len());
+            }
+        }
+    }
+    Ok(total)
+}
+
+/// Returns the statistics of the filesystem containing the path.
+#[cfg(unix)]
+fn statvfs(path: &Path) -> std::io::Result<libc::statvfs> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    let mut stats = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: the path is NUL terminated and `stats` is only read once `statvfs` succeeded and filled it in.
+    unsafe {
+        if libc::statvfs(c_path.as_ptr(), stats.as_mut_ptr()) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(stats.assume_init())
+    }
+}
+
 #[export_module]
 pub mod dir_functions {
     use std::path::PathBuf;
 
     /// Recursively create a directory and all of its parent components if they are missing.
     #[rhai_fn(return_raw)]
-    pub fn create_dir(path: PathBuf) -> Result<(), Box<EvalAltResult>> {
-        std::fs::create_dir_all(path).map_err(|e| e.to_string().into())
+    pub fn create_dir(ctx: NativeCallContext, path: PathBuf) -> Result<(), Box<EvalAltResult>> {
+        std::fs::create_dir_all(resolve(&ctx, &path)?).map_err(|e| e.to_string().into())
     }
 
     /// Helper function for `create_dir` that takes a string instead of `PathBuf`.
@@ -18,7 +189,7 @@ pub mod dir_functions {
         path_raw: ImmutableString,
     ) -> Result<(), Box<EvalAltResult>> {
         let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
-        create_dir(path)
+        create_dir(ctx, path)
     }
 
     /// Removes an empty directory.
@@ -29,8 +200,8 @@ pub mod dir_functions {
     /// - The process lacks permissions to remove the directory.
     /// - The directory isn't empty.
     #[rhai_fn(return_raw)]
-    pub fn remove_dir(path: PathBuf) -> Result<(), Box<EvalAltResult>> {
-        std::fs::remove_dir(path).map_err(|e| e.to_string().into())
+    pub fn remove_dir(ctx: NativeCallContext, path: PathBuf) -> Result<(), Box<EvalAltResult>> {
+        std::fs::remove_dir(resolve(&ctx, &path)?).map_err(|e| e.to_string().into())
     }
 
     /// Helper function for `remove_dir` that takes a string instead of `PathBuf`.
@@ -40,7 +211,7 @@ pub mod dir_functions {
         path_raw: ImmutableString,
     ) -> Result<(), Box<EvalAltResult>> {
         let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
-        remove_dir(path)
+        remove_dir(ctx, path)
     }
 
     /// Returns an array of paths in the directory.
@@ -50,8 +221,11 @@ pub mod dir_functions {
     /// - The provided path isn't a directory.
     /// - The process lacks permissions to view the contents.
     #[rhai_fn(return_raw)]
-    pub fn open_dir(path: PathBuf) -> Result<rhai::Array, Box<EvalAltResult>> {
-        match std::fs::read_dir(path) {
+    pub fn open_dir(
+        ctx: NativeCallContext,
+        path: PathBuf,
+    ) -> Result<rhai::Array, Box<EvalAltResult>> {
+        match std::fs::read_dir(resolve(&ctx, &path)?) {
             Ok(read_dir) => Ok(read_dir
                 .filter_map(|e| e.ok())
                 .map(|e| Dynamic::from(e.path()))
@@ -67,6 +241,108 @@ pub mod dir_functions {
         path_raw: ImmutableString,
     ) -> Result<rhai::Array, Box<EvalAltResult>> {
         let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
-        open_dir(path)
+        open_dir(ctx, path)
+    }
+
+    /// Returns the total size in bytes of the files below the directory, with the default options.
+    ///
+    /// See `dir_size(path, options)` for details.
+    #[rhai_fn(return_raw)]
+    pub fn dir_size(
+        ctx: NativeCallContext,
+        path: PathBuf,
+    ) -> Result<rhai::INT, Box<EvalAltResult>> {
+        dir_size_with_options(ctx, path, Map::new())
+    }
+
+    /// Helper function for `dir_size` that takes a string instead of `PathBuf`.
+    #[rhai_fn(return_raw, name = "dir_size")]
+    pub fn dir_size_str(
+        ctx: NativeCallContext,
+        path_raw: ImmutableString,
+    ) -> Result<rhai::INT, Box<EvalAltResult>> {
+        let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+        dir_size(ctx, path)
+    }
+
+    /// Returns the total size in bytes of the files below the directory, walking it recursively.
+    ///
+    /// Sizes are the length of the files, not the space allocated for them, and files with several hard links are counted once.
+    /// Entries removed while walking are skipped.
+    ///
+    /// Options:
+    /// - `follow_symlinks`: count what symlinks point to, defaults to `false` which skips them. Directories are still only walked once.
+    /// - `same_device`: skip entries on other devices than the directory, such as mounted filesystems, defaults to `false`. Only supported on Unix.
+    ///
+    /// Throws an exception when:
+    /// - The provided path isn't a directory.
+    /// - The process lacks permissions to read an entry.
+    /// - An option is unknown or has the wrong type.
+    #[rhai_fn(return_raw, name = "dir_size")]
+    pub fn dir_size_with_options(
+        ctx: NativeCallContext,
+        path: PathBuf,
+        options: Map,
+    ) -> Result<rhai::INT, Box<EvalAltResult>> {
+        let options = SizeOptions::from_map(&options)?;
+        convert_to_int(total_size(&ctx, &resolve(&ctx, &path)?, &options)?)
+    }
+
+    /// Helper function for `dir_size` that takes a string instead of `PathBuf`.
+    #[rhai_fn(return_raw, name = "dir_size")]
+    pub fn dir_size_with_options_str(
+        ctx: NativeCallContext,
+        path_raw: ImmutableString,
+        options: Map,
+    ) -> Result<rhai::INT, Box<EvalAltResult>> {
+        let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+        dir_size_with_options(ctx, path, options)
+    }
+
+    /// Returns statistics of the filesystem containing the path as a map:
+    /// - `total`, `free` and `available`: the size of the filesystem, the free space, and the free space available to unprivileged users, in bytes.
+    /// - `inodes`, `inodes_free` and `inodes_available`: the same for the number of inodes.
+    ///
+    /// Throws an exception when:
+    /// - The provided path doesn't exist.
+    /// - The platform isn't Unix.
+    #[rhai_fn(return_raw)]
+    pub fn fs_stats(ctx: NativeCallContext, path: PathBuf) -> Result<Map, Box<EvalAltResult>> {
+        let path = resolve(&ctx, &path)?;
+
+        #[cfg(unix)]
+        {
+            let stats = statvfs(&path).map_err(|e| format!("{}: {e}", path.to_string_lossy()))?;
+            let block = stats.f_frsize as u64;
+            let fields = [
+                ("total", (stats.f_blocks as u64).saturating_mul(block)),
+                ("free", (stats.f_bfree as u64).saturating_mul(block)),
+                ("available", (stats.f_bavail as u64).saturating_mul(block)),
+                ("inodes", stats.f_files as u64),
+                ("inodes_free", stats.f_ffree as u64),
+                ("inodes_available", stats.f_favail as u64),
+            ];
+            let mut map = Map::new();
+            for (name, value) in fields {
+                map.insert(name.into(), convert_to_int(value)?.into());
+            }
+            Ok(map)
+        }
+        #[cfg(not(unix))]
+        Err(format!(
+            "{}: fs_stats is only supported on Unix",
+            path.to_string_lossy()
+        )
+        .into())
+    }
+
+    /// Helper function for `fs_stats` that takes a string instead of `PathBuf`.
+    #[rhai_fn(return_raw, name = "fs_stats")]
+    pub fn fs_stats_str(
+        ctx: NativeCallContext,
+        path_raw: ImmutableString,
+    ) -> Result<Map, Box<EvalAltResult>> {
+        let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+        fs_stats(ctx, path)
     }
 }
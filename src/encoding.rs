@@ -0,0 +1,234 @@
+#[allow(unused_imports)]
+use rhai::plugin::*;
+
+/// Byte order mark of UTF-8 text.
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+/// Byte order mark of little endian UTF-16 text.
+const UTF16LE_BOM: &[u8] = &[0xFF, 0xFE];
+/// Byte order mark of big endian UTF-16 text.
+const UTF16BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+/// Characters of Windows-1252 bytes `0x80` to `0x9F`, the rest of the code page matches Latin-1.
+///
+/// Bytes the code page leaves undefined map to the C1 control character of the same value.
+const WINDOWS_1252: [char; 32] = [
+    '\u{20AC}', '\u{0081}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{008D}', '\u{017D}', '\u{008F}',
+    '\u{0090}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{009D}', '\u{017E}', '\u{0178}',
+];
+
+/// A text encoding accepted by `read_string` and `write`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Encoding {
+    /// UTF-8, failing on invalid sequences.
+    Utf8,
+    /// UTF-8, replacing invalid sequences with `U+FFFD`.
+    Utf8Lossy,
+    /// UTF-16 with the byte order taken from the BOM, little endian without one.
+    Utf16,
+    /// Little endian UTF-16.
+    Utf16Le,
+    /// Big endian UTF-16.
+    Utf16Be,
+    /// ISO-8859-1, where every byte is the code point of the same value.
+    Latin1,
+    /// Windows-1252, the Western European code page used by Windows.
+    Windows1252,
+    /// 7-bit ASCII.
+    Ascii,
+}
+
+impl Encoding {
+    /// Looks up an encoding by name, ignoring case.
+    ///
+    /// Throws an exception when the encoding isn't supported.
+    pub(crate) fn from_name(name: &str) -> Result<Self, Box<EvalAltResult>> {
+        match name.to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Ok(Encoding::Utf8),
+            "utf-8-lossy" | "utf8-lossy" => Ok(Encoding::Utf8Lossy),
+            "utf-16" | "utf16" => Ok(Encoding::Utf16),
+            "utf-16le" | "utf16le" => Ok(Encoding::Utf16Le),
+            "utf-16be" | "utf16be" => Ok(Encoding::Utf16Be),
+            "latin1" | "latin-1" | "iso-8859-1" => Ok(Encoding::Latin1),
+            "windows-1252" | "cp1252" => Ok(Encoding::Windows1252),
+            "ascii" | "us-ascii" => Ok(Encoding::Ascii),
+            _ => Err(format!("Unsupported encoding {name}").into()),
+        }
+    }
+
+    /// Decodes bytes into a string.
+    ///
+    /// When `at_start` is set the bytes are the start of the file, and a byte order mark matching the encoding is skipped.
+    pub(crate) fn decode(
+        self,
+        bytes: Vec<u8>,
+        at_start: bool,
+    ) -> Result<String, Box<EvalAltResult>> {
+        match self {
+            Encoding::Utf8 | Encoding::Utf8Lossy => {
+                let bytes = match bytes.strip_prefix(UTF8_BOM) {
+                    Some(rest) if at_start => rest.to_vec(),
+                    _ => bytes,
+                };
+                if self == Encoding::Utf8Lossy {
+                    Ok(String::from_utf8_lossy(&bytes).into_owned())
+                } else {
+                    String::from_utf8(bytes).map_err(|e| e.to_string().into())
+                }
+            }
+            Encoding::Utf16 | Encoding::Utf16Le | Encoding::Utf16Be => {
+                let (big_endian, bom_len) = self.utf16_order(&bytes, at_start);
+                decode_utf16(&bytes[bom_len..], big_endian)
+            }
+            Encoding::Latin1 => Ok(bytes.into_iter().map(char::from).collect()),
+            Encoding::Windows1252 => Ok(bytes
+                .into_iter()
+                .map(|b| match b {
+                    0x80..=0x9F => WINDOWS_1252[(b - 0x80) as usize],
+                    _ => char::from(b),
+                })
+                .collect()),
+            Encoding::Ascii => match bytes.iter().position(|b| !b.is_ascii()) {
+                Some(idx) => {
+                    Err(format!("Invalid ASCII byte 0x{:02X} at index {idx}", bytes[idx]).into())
+                }
+                None => Ok(bytes.into_iter().map(char::from).collect()),
+            },
+        }
+    }
+
+    /// Returns the number of bytes at the end that start a character without completing it.
+    ///
+    /// A read that stops at a length limit may cut a character in two, these bytes belong to the next read.
+    /// Invalid sequences aren't counted and are left for `decode` to report.
+    pub(crate) fn incomplete_tail(self, bytes: &[u8], at_start: bool) -> usize {
+        match self {
+            Encoding::Utf8 | Encoding::Utf8Lossy => {
+                // A UTF-8 character is at most four bytes, starting with a byte that isn't `0b10xxxxxx`.
+                let Some(lead) = bytes
+                    .iter()
+                    .rev()
+                    .take(4)
+                    .position(|&b| b & 0xC0 != 0x80)
+                    .map(|idx| bytes.len() - 1 - idx)
+                else {
+                    return 0;
+                };
+                match std::str::from_utf8(&bytes[lead..]) {
+                    Err(e) if e.error_len().is_none() => bytes.len() - lead,
+                    _ => 0,
+                }
+            }
+            Encoding::Utf16 | Encoding::Utf16Le | Encoding::Utf16Be => {
+                let (big_endian, bom_len) = self.utf16_order(bytes, at_start);
+                let bytes = &bytes[bom_len..];
+                let odd = bytes.len() % 2;
+                let units = &bytes[..bytes.len() - odd];
+                match units
+                    .len()
+                    .checked_sub(2)
+                    .map(|idx| [units[idx], units[idx + 1]])
+                {
+                    // A high surrogate needs the low surrogate that follows it.
+                    Some(pair) if is_high_surrogate(pair, big_endian) => odd + 2,
+                    _ => odd,
+                }
+            }
+            Encoding::Latin1 | Encoding::Windows1252 | Encoding::Ascii => 0,
+        }
+    }
+
+    /// Fixes the byte order of `utf-16` from the first bytes of the file, for reads that don't start there.
+    ///
+    /// The byte order mark is only seen by the read at the start of the file, later reads need it to keep the same order.
+    pub(crate) fn with_byte_order(self, head: &[u8]) -> Self {
+        match self {
+            Encoding::Utf16 if head.starts_with(UTF16BE_BOM) => Encoding::Utf16Be,
+            Encoding::Utf16 => Encoding::Utf16Le,
+            _ => self,
+        }
+    }
+
+    /// Returns whether UTF-16 bytes are big endian and the length of their byte order mark.
+    ///
+    /// A byte order mark is only looked for when `at_start` is set.
+    fn utf16_order(self, bytes: &[u8], at_start: bool) -> (bool, usize) {
+        match self {
+            _ if !at_start => (self == Encoding::Utf16Be, 0),
+            Encoding::Utf16 if bytes.starts_with(UTF16BE_BOM) => (true, 2),
+            Encoding::Utf16 if bytes.starts_with(UTF16LE_BOM) => (false, 2),
+            Encoding::Utf16Be if bytes.starts_with(UTF16BE_BOM) => (true, 2),
+            Encoding::Utf16Le if bytes.starts_with(UTF16LE_BOM) => (false, 2),
+            _ => (self == Encoding::Utf16Be, 0),
+        }
+    }
+
+    /// Encodes a string into bytes.
+    ///
+    /// `utf-16` output starts with a little endian byte order mark, the other encodings never write one.
+    pub(crate) fn encode(self, str: &str) -> Result<Vec<u8>, Box<EvalAltResult>> {
+        match self {
+            Encoding::Utf8 | Encoding::Utf8Lossy => Ok(str.as_bytes().to_vec()),
+            Encoding::Utf16 => {
+                let mut bytes = UTF16LE_BOM.to_vec();
+                bytes.extend(str.encode_utf16().flat_map(u16::to_le_bytes));
+                Ok(bytes)
+            }
+            Encoding::Utf16Le => Ok(str.encode_utf16().flat_map(u16::to_le_bytes).collect()),
+            Encoding::Utf16Be => Ok(str.encode_utf16().flat_map(u16::to_be_bytes).collect()),
+            Encoding::Latin1 => encode_bytes(str, "Latin-1", |c| u8::try_from(c).ok()),
+            Encoding::Windows1252 => encode_bytes(str, "Windows-1252", |c| {
+                match WINDOWS_1252.iter().position(|&w| w == c) {
+                    Some(idx) => Some(0x80 + idx as u8),
+                    None => u8::try_from(c).ok().filter(|b| !(0x80..=0x9F).contains(b)),
+                }
+            }),
+            Encoding::Ascii => {
+                encode_bytes(str, "ASCII", |c| u8::try_from(c).ok().filter(u8::is_ascii))
+            }
+        }
+    }
+}
+
+/// Returns `true` if the UTF-16 code unit is the first half of a surrogate pair.
+fn is_high_surrogate(pair: [u8; 2], big_endian: bool) -> bool {
+    let unit = if big_endian {
+        u16::from_be_bytes(pair)
+    } else {
+        u16::from_le_bytes(pair)
+    };
+    (0xD800..=0xDBFF).contains(&unit)
+}
+
+/// Decodes UTF-16 text without a byte order mark.
+fn decode_utf16(bytes: &[u8], big_endian: bool) -> Result<String, Box<EvalAltResult>> {
+    if !bytes.len().is_multiple_of(2) {
+        return Err(format!("Invalid UTF-16, odd length of {} bytes", bytes.len()).into());
+    }
+
+    let units = bytes.chunks_exact(2).map(|pair| {
+        let pair = [pair[0], pair[1]];
+        if big_endian {
+            u16::from_be_bytes(pair)
+        } else {
+            u16::from_le_bytes(pair)
+        }
+    });
+    char::decode_utf16(units)
+        .collect::<Result<String, _>>()
+        .map_err(|e| format!("Invalid UTF-16, {e}").into())
+}
+
+/// Encodes a string with a single byte encoding, failing on the first character it can't represent.
+fn encode_bytes(
+    str: &str,
+    name: &str,
+    to_byte: impl Fn(char) -> Option<u8>,
+) -> Result<Vec<u8>, Box<EvalAltResult>> {
+    str.chars()
+        .map(|c| {
+            to_byte(c).ok_or_else(|| format!("Character {c:?} can't be encoded as {name}").into())
+        })
+        .collect()
+}
@@ -0,0 +1,212 @@
+#[allow(unused_imports)]
+use rhai::plugin::*;
+
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use super::config::{state, EnvAccess};
+
+/// Variable holding the current user's home directory.
+#[cfg(windows)]
+const HOME_VAR: &str = "USERPROFILE";
+/// Variable holding the current user's home directory.
+#[cfg(not(windows))]
+const HOME_VAR: &str = "HOME";
+
+/// Looks up a variable, treating empty values the same as unset ones.
+fn non_empty_var(env: &EnvAccess, name: &str) -> Result<Option<String>, Box<EvalAltResult>> {
+    Ok(env.var(name)?.filter(|v| !v.is_empty()))
+}
+
+/// Returns the current user's home directory.
+fn home(env: &EnvAccess) -> Result<PathBuf, Box<EvalAltResult>> {
+    non_empty_var(env, HOME_VAR)?
+        .map(PathBuf::from)
+        .ok_or_else(|| {
+            format!("Home directory could not be determined, {HOME_VAR} is not set").into()
+        })
+}
+
+/// Returns the directory named by an XDG base directory variable, falling back to `fallback` inside the home directory.
+#[cfg(not(windows))]
+fn xdg_dir(env: &EnvAccess, name: &str, fallback: &str) -> Result<PathBuf, Box<EvalAltResult>> {
+    // The XDG spec says relative paths are invalid and should be ignored.
+    match non_empty_var(env, name)?.map(PathBuf::from) {
+        Some(path) if path.is_absolute() => Ok(path),
+        _ => Ok(home(env)?.join(fallback)),
+    }
+}
+
+/// Returns the directory named by a Windows known folder variable.
+#[cfg(windows)]
+fn known_dir(env: &EnvAccess, name: &str) -> Result<PathBuf, Box<EvalAltResult>> {
+    non_empty_var(env, name)?
+        .map(PathBuf::from)
+        .ok_or_else(|| format!("Directory could not be determined, {name} is not set").into())
+}
+
+/// Expands a leading `~` to the home directory and substitutes `$VAR` and `${VAR}` with the values of environment variables.
+fn expand_str(env: &EnvAccess, input: &str) -> Result<PathBuf, Box<EvalAltResult>> {
+    let mut expanded = OsString::with_capacity(input.len());
+    let mut rest = input;
+
+    if let Some(after) = rest.strip_prefix('~') {
+        if after.is_empty() || after.starts_with(std::path::is_separator) {
+            expanded.push(home(env)?);
+            rest = after;
+        }
+    }
+
+    while let Some(idx) = rest.find('$') {
+        expanded.push(&rest[..idx]);
+        let after = &rest[idx + 1..];
+
+        let (name, remainder) = match after.strip_prefix('{') {
+            Some(braced) => {
+                let end = braced
+                    .find('}')
+                    .ok_or_else(|| format!("Unterminated variable in {input}"))?;
+                if end == 0 {
+                    return Err(format!("Empty variable name in {input}").into());
+                }
+                (&braced[..end], &braced[end + 1..])
+            }
+            None => {
+                let end = after
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(after.len());
+                (&after[..end], &after[end..])
+            }
+        };
+
+        // A `$` that doesn't start a variable is kept as-is.
+        if name.is_empty() {
+            expanded.push("$");
+            rest = after;
+            continue;
+        }
+
+        let value = env
+            .var(name)?
+            .ok_or_else(|| format!("Environment variable {name} is not set"))?;
+        expanded.push(value);
+        rest = remainder;
+    }
+    expanded.push(rest);
+
+    Ok(expanded.into())
+}
+
+#[export_module]
+#[allow(clippy::ptr_arg)]
+pub mod env_functions {
+    /// Returns the current user's home directory, taken from `HOME` (`USERPROFILE` on Windows).
+    ///
+    /// Throws an exception when:
+    /// - The variable isn't set.
+    /// - Environment access is disabled.
+    #[rhai_fn(return_raw)]
+    pub fn home_dir(ctx: NativeCallContext) -> Result<PathBuf, Box<EvalAltResult>> {
+        home(&state(&ctx)?.config.env)
+    }
+
+    /// Returns the directory for temporary files, taken from `TMPDIR` (`TMP` or `TEMP` on Windows).
+    ///
+    /// Falls back to `/tmp` when the variable isn't set on platforms other than Windows.
+    ///
+    /// Throws an exception when:
+    /// - The variables aren't set on Windows.
+    /// - Environment access is disabled.
+    #[rhai_fn(return_raw)]
+    pub fn temp_dir(ctx: NativeCallContext) -> Result<PathBuf, Box<EvalAltResult>> {
+        let env = &state(&ctx)?.config.env;
+
+        #[cfg(windows)]
+        return match non_empty_var(env, "TMP")? {
+            Some(dir) => Ok(dir.into()),
+            None => known_dir(env, "TEMP"),
+        };
+
+        #[cfg(not(windows))]
+        Ok(non_empty_var(env, "TMPDIR")?
+            .unwrap_or_else(|| "/tmp".into())
+            .into())
+    }
+
+    /// Returns the directory for user configuration files, taken from `XDG_CONFIG_HOME` or defaulting to `~/.config` (`APPDATA` on Windows).
+    ///
+    /// Throws an exception when:
+    /// - Neither the variable nor the home directory is set.
+    /// - Environment access is disabled.
+    #[rhai_fn(return_raw)]
+    pub fn config_dir(ctx: NativeCallContext) -> Result<PathBuf, Box<EvalAltResult>> {
+        let env = &state(&ctx)?.config.env;
+
+        #[cfg(windows)]
+        return known_dir(env, "APPDATA");
+
+        #[cfg(not(windows))]
+        xdg_dir(env, "XDG_CONFIG_HOME", ".config")
+    }
+
+    /// Returns the directory for user cache files, taken from `XDG_CACHE_HOME` or defaulting to `~/.cache` (`LOCALAPPDATA` on Windows).
+    ///
+    /// Throws an exception when:
+    /// - Neither the variable nor the home directory is set.
+    /// - Environment access is disabled.
+    #[rhai_fn(return_raw)]
+    pub fn cache_dir(ctx: NativeCallContext) -> Result<PathBuf, Box<EvalAltResult>> {
+        let env = &state(&ctx)?.config.env;
+
+        #[cfg(windows)]
+        return known_dir(env, "LOCALAPPDATA");
+
+        #[cfg(not(windows))]
+        xdg_dir(env, "XDG_CACHE_HOME", ".cache")
+    }
+
+    /// Returns the directory for user data files, taken from `XDG_DATA_HOME` or defaulting to `~/.local/share` (`APPDATA` on Windows).
+    ///
+    /// Throws an exception when:
+    /// - Neither the variable nor the home directory is set.
+    /// - Environment access is disabled.
+    #[rhai_fn(return_raw)]
+    pub fn data_dir(ctx: NativeCallContext) -> Result<PathBuf, Box<EvalAltResult>> {
+        let env = &state(&ctx)?.config.env;
+
+        #[cfg(windows)]
+        return known_dir(env, "APPDATA");
+
+        #[cfg(not(windows))]
+        xdg_dir(env, "XDG_DATA_HOME", ".local/share")
+    }
+
+    /// Creates a path from the passed string, expanding a leading `~` to the home directory and substituting `$VAR` and `${VAR}` with environment variables.
+    ///
+    /// Throws an exception when:
+    /// - A referenced variable isn't set.
+    /// - A `${` isn't closed.
+    /// - Environment access is disabled.
+    #[rhai_fn(return_raw)]
+    pub fn expand(ctx: NativeCallContext, path: &str) -> Result<PathBuf, Box<EvalAltResult>> {
+        expand_str(&state(&ctx)?.config.env, path)
+    }
+
+    /// Returns the path with a leading `~` expanded to the home directory and `$VAR` and `${VAR}` substituted with environment variables.
+    ///
+    /// Throws an exception when:
+    /// - The path isn't valid unicode.
+    /// - A referenced variable isn't set.
+    /// - A `${` isn't closed.
+    /// - Environment access is disabled.
+    #[rhai_fn(global, pure, return_raw, name = "expand")]
+    pub fn expand_path(
+        ctx: NativeCallContext,
+        path: &mut PathBuf,
+    ) -> Result<PathBuf, Box<EvalAltResult>> {
+        let str = path
+            .to_str()
+            .ok_or_else(|| format!("Path {} is not valid unicode", path.to_string_lossy()))?;
+        expand_str(&state(&ctx)?.config.env, str)
+    }
+}
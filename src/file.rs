@@ -2,18 +2,298 @@
 use rhai::plugin::*;
 use rhai::{Locked, Shared};
 
-use std::fs::{File, OpenOptions};
+use std::fs::{File, OpenOptions, TryLockError};
 use std::io::prelude::*;
-use std::ops::DerefMut;
-use std::path::PathBuf;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 
-fn convert_to_int(val: impl TryInto<rhai::INT>) -> Result<rhai::INT, Box<EvalAltResult>> {
+use super::config::resolve;
+use super::encoding::Encoding;
+
+/// Converts a number into the Rhai integer type, failing if it does not fit.
+pub(crate) fn convert_to_int(
+    val: impl TryInto<rhai::INT>,
+) -> Result<rhai::INT, Box<EvalAltResult>> {
     val.try_into()
         .map_err(|_| "Error converting number {new_pos} to rhai number type".into())
 }
 
+/// Translates the flags accepted by `open_file` into `OpenOptions`, see `open_file_with_opts` for the table.
+pub(crate) fn open_options(options: &str) -> OpenOptions {
+    let mut opts = OpenOptions::new();
+    match options {
+        "r" => opts.read(true),
+        "r+" => opts.read(true).write(true),
+        "w" => opts.write(true).create(true),
+        "wx" => opts.write(true).create_new(true),
+        "w+" => opts.read(true).write(true).create(true),
+        "a" => opts.append(true).create(true),
+        "ax" => opts.append(true).create_new(true),
+        "a+" => opts.read(true).append(true).create(true),
+        "ax+" => opts.read(true).append(true).create_new(true),
+        _ => &mut opts,
+    };
+    opts
+}
+
+/// Creates the exception thrown when a lock couldn't be acquired.
+///
+/// The thrown value is a map with a `kind` of `"would_block"` so scripts can tell it apart from other errors.
+pub(crate) fn would_block_error(message: &str) -> Box<EvalAltResult> {
+    let mut map = rhai::Map::new();
+    map.insert("kind".into(), "would_block".into());
+    map.insert("message".into(), message.into());
+    EvalAltResult::ErrorRuntime(map.into(), Position::NONE).into()
+}
+
+/// How long to wait between attempts to take a lock with a timeout.
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Repeatedly tries to take a lock until it succeeds or the timeout, in milliseconds, elapses.
+fn lock_with_timeout(
+    file: &SharedFile,
+    timeout: rhai::INT,
+    try_lock: fn(&File) -> Result<(), TryLockError>,
+) -> Result<(), Box<EvalAltResult>> {
+    let deadline = Instant::now() + Duration::from_millis(timeout.max(0) as u64);
+
+    loop {
+        match try_lock(&borrow_mut(file)) {
+            Ok(()) => return Ok(()),
+            Err(TryLockError::WouldBlock) if Instant::now() < deadline => {
+                std::thread::sleep(LOCK_POLL_INTERVAL)
+            }
+            Err(TryLockError::WouldBlock) => {
+                return Err(would_block_error(&format!(
+                    "Lock could not be acquired within {timeout}ms"
+                )))
+            }
+            Err(TryLockError::Error(e)) => return Err(e.to_string().into()),
+        }
+    }
+}
+
+/// Returns how many bytes a read of `len` bytes may return given the engine limit `max_len`, `None` meaning until EOF.
+///
+/// A `len` of zero or less reads until EOF, a `max_len` of zero means the engine has no limit.
+pub(crate) fn read_limit(len: rhai::INT, max_len: usize) -> Option<u64> {
+    let len = u64::try_from(len).ok().filter(|&len| len > 0);
+    let max_len = Some(max_len as u64).filter(|&max_len| max_len > 0);
+    match (len, max_len) {
+        (Some(len), Some(max_len)) => Some(len.min(max_len)),
+        (len, max_len) => len.or(max_len),
+    }
+}
+
+/// Reads from a stream until `limit` bytes have been read or EOF is reached.
+///
+/// Unlike a single `read` call this never stops short before EOF.
+pub(crate) fn read_limited(
+    reader: &mut impl Read,
+    limit: Option<u64>,
+) -> Result<Vec<u8>, Box<EvalAltResult>> {
+    let mut buf = Vec::new();
+    let res = match limit {
+        Some(limit) => reader.take(limit).read_to_end(&mut buf),
+        None => reader.read_to_end(&mut buf),
+    };
+    res.map_err(|e| e.to_string())?;
+    Ok(buf)
+}
+
+/// Reads a file meant as text, throwing when it is larger than the engine's `max_string_size`.
+pub(crate) fn read_text_bytes(
+    ctx: &NativeCallContext,
+    path: &Path,
+) -> Result<Vec<u8>, Box<EvalAltResult>> {
+    let display = path.to_string_lossy();
+    let max_len = ctx.engine().max_string_size() as u64;
+    let mut file = File::open(path).map_err(|e| format!("{display}: {e}"))?;
+    let buf = read_limited(&mut file, Some(max_len + 1).filter(|_| max_len > 0))?;
+    if max_len > 0 && buf.len() as u64 > max_len {
+        return Err(format!("{display}: file is larger than the maximum string size").into());
+    }
+    Ok(buf)
+}
+
+/// Reads a UTF-8 text file, throwing when it is larger than the engine's `max_string_size`.
+pub(crate) fn read_text(
+    ctx: &NativeCallContext,
+    path: &Path,
+) -> Result<String, Box<EvalAltResult>> {
+    String::from_utf8(read_text_bytes(ctx, path)?)
+        .map_err(|_| format!("{}: file isn't valid UTF-8", path.to_string_lossy()).into())
+}
+
+/// A stream that can give back the bytes it returned last, so the next read returns them again.
+pub(crate) trait PushBack: Read {
+    /// Gives back `bytes`, which must be the last bytes read from the stream.
+    fn push_back(&mut self, bytes: &[u8]) -> std::io::Result<()>;
+}
+
+impl PushBack for File {
+    fn push_back(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        self.seek(std::io::SeekFrom::Current(-(bytes.len() as i64)))
+            .map(|_| ())
+    }
+}
+
+/// Gives back a character at the end of `buf` that a length limit cut in two, leaving it for the next read.
+///
+/// Throws an exception when `buf` doesn't even hold one complete character.
+fn push_back_incomplete(
+    reader: &mut impl PushBack,
+    buf: &mut Vec<u8>,
+    encoding: Encoding,
+    at_start: bool,
+) -> Result<(), Box<EvalAltResult>> {
+    let tail = encoding.incomplete_tail(buf, at_start);
+    if tail > 0 {
+        reader
+            .push_back(&buf[buf.len() - tail..])
+            .map_err(|e| e.to_string())?;
+        if tail == buf.len() {
+            return Err(format!("Length {tail} is too short to read a character").into());
+        }
+        buf.truncate(buf.len() - tail);
+    }
+    Ok(())
+}
+
+/// How many names are tried for a temporary file before giving up.
+const TEMP_ATTEMPTS: usize = 100;
+
+/// Counter making the names of concurrently created temporary files unique within the process.
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Creates a temporary file next to `path`, meant to be renamed over it once completely written.
+///
+/// Being in the same directory keeps the rename on the same filesystem, where it's atomic.
+pub(crate) fn create_temp_beside(path: &Path) -> std::io::Result<(PathBuf, File)> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+
+    let mut attempts = 0;
+    loop {
+        let counter = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let tmp = dir.join(format!(".{name}.{}.{counter}.tmp", std::process::id()));
+        match File::options().write(true).create_new(true).open(&tmp) {
+            Ok(file) => return Ok((tmp, file)),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists && attempts < TEMP_ATTEMPTS => {
+                attempts += 1
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Reads the first bytes of the file, where a byte order mark may be, keeping the stream position.
+fn read_head(file: &mut File) -> std::io::Result<Vec<u8>> {
+    let position = file.stream_position()?;
+    file.seek(std::io::SeekFrom::Start(0))?;
+    let mut head = Vec::with_capacity(2);
+    let result = (&mut *file).take(2).read_to_end(&mut head);
+    file.seek(std::io::SeekFrom::Start(position))?;
+    result.map(|_| head)
+}
+
+/// Reads up to `limit` bytes and decodes them, giving back a character cut in two by the limit.
+///
+/// `at_start` tells whether the stream is at the start of the file, where a byte order mark may be.
+pub(crate) fn read_string_from(
+    reader: &mut impl PushBack,
+    limit: Option<u64>,
+    encoding: Encoding,
+    at_start: bool,
+) -> Result<String, Box<EvalAltResult>> {
+    let mut buf = read_limited(reader, limit)?;
+
+    // Stopping at the limit may cut the last character in two, leave it for the next read.
+    if limit.is_some_and(|limit| buf.len() as u64 == limit) {
+        push_back_incomplete(reader, &mut buf, encoding, at_start)?;
+    }
+
+    encoding.decode(buf, at_start)
+}
+
+/// Most bytes `read_some` asks for in its single read, so a huge `len` isn't allocated up front.
+#[cfg(not(feature = "no_index"))]
+const READ_SOME_MAX_LEN: usize = 64 * 1024;
+
+/// Size of the chunks `read_line` reads, anything past the end of the line is given back.
+const LINE_CHUNK_SIZE: usize = 256;
+
+/// Reads UTF-8 text up to and including the next `\n`, or up to `max_len` bytes if that comes first.
+///
+/// A `max_len` of zero means no limit, an empty string is returned at EOF.
+pub(crate) fn read_line_from(
+    reader: &mut impl PushBack,
+    max_len: usize,
+) -> Result<String, Box<EvalAltResult>> {
+    let mut line = Vec::new();
+    let mut chunk = [0; LINE_CHUNK_SIZE];
+
+    loop {
+        let want = match max_len {
+            0 => LINE_CHUNK_SIZE,
+            _ => LINE_CHUNK_SIZE.min(max_len - line.len()),
+        };
+        let read_len = match reader.read(&mut chunk[..want]) {
+            Ok(0) => break,
+            Ok(read_len) => read_len,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.to_string().into()),
+        };
+
+        match chunk[..read_len].iter().position(|&b| b == b'\n') {
+            Some(idx) => {
+                line.extend_from_slice(&chunk[..=idx]);
+                reader
+                    .push_back(&chunk[idx + 1..read_len])
+                    .map_err(|e| e.to_string())?;
+                break;
+            }
+            None => line.extend_from_slice(&chunk[..read_len]),
+        }
+
+        if max_len > 0 && line.len() >= max_len {
+            push_back_incomplete(reader, &mut line, Encoding::Utf8, false)?;
+            break;
+        }
+    }
+
+    String::from_utf8(line).map_err(|e| e.to_string().into())
+}
+
+/// Copies from one file handle to another with `std::io::copy`, up to `limit` bytes or until EOF.
+fn copy_between(
+    src: &SharedFile,
+    dst: &SharedFile,
+    limit: Option<u64>,
+) -> Result<rhai::INT, Box<EvalAltResult>> {
+    // Both handles are borrowed at once, which would panic or deadlock for the same handle.
+    if Shared::ptr_eq(src, dst) {
+        return Err("Cannot copy a file handle to itself".into());
+    }
+
+    let src = borrow_mut(src);
+    let mut dst = borrow_mut(dst);
+    let res = match limit {
+        Some(limit) => std::io::copy(&mut src.deref().take(limit), dst.deref_mut()),
+        None => std::io::copy(&mut src.deref(), dst.deref_mut()),
+    };
+    res.map_err(|e| e.to_string().into())
+        .and_then(convert_to_int)
+}
+
+/// A file handle shared between Rhai values.
+pub type SharedFile = Shared<Locked<File>>;
+
+/// Mutably borrows the underlying file, regardless of whether the `sync` feature is enabled.
 #[inline(always)]
-fn borrow_mut(file: &Shared<Locked<File>>) -> impl DerefMut<Target = File> + '_ {
+pub(crate) fn borrow_mut(file: &SharedFile) -> impl DerefMut<Target = File> + '_ {
     #[cfg(not(feature = "sync"))]
     return file.borrow_mut();
 
@@ -23,12 +303,13 @@ fn borrow_mut(file: &Shared<Locked<File>>) -> impl DerefMut<Target = File> + '_
 
 #[export_module]
 pub mod file_functions {
-    pub type SharedFile = Shared<Locked<File>>;
-
     /// Creates or opens a file for reading and writing.
     #[rhai_fn(return_raw)]
-    pub fn open_file(path: PathBuf) -> Result<SharedFile, Box<EvalAltResult>> {
-        open_file_with_opts(path, "w+")
+    pub fn open_file(
+        ctx: NativeCallContext,
+        path: PathBuf,
+    ) -> Result<SharedFile, Box<EvalAltResult>> {
+        open_file_with_opts(ctx, path, "w+")
     }
 
     /// Helper function for `open_file(path)` that takes a string instead of `PathBuf`.
@@ -38,7 +319,7 @@ pub mod file_functions {
         path_raw: ImmutableString,
     ) -> Result<SharedFile, Box<EvalAltResult>> {
         let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
-        open_file(path)
+        open_file(ctx, path)
     }
 
     /// Available options for opening a file.
@@ -57,23 +338,11 @@ pub mod file_functions {
     ///
     #[rhai_fn(return_raw, name = "open_file")]
     pub fn open_file_with_opts(
+        ctx: NativeCallContext,
         path: PathBuf,
         options: &str,
     ) -> Result<SharedFile, Box<EvalAltResult>> {
-        let mut opts = OpenOptions::new();
-        let final_opts = match options {
-            "r" => opts.read(true),
-            "r+" => opts.read(true).write(true),
-            "w" => opts.write(true).create(true),
-            "wx" => opts.write(true).create_new(true),
-            "w+" => opts.read(true).write(true).create(true),
-            "a" => opts.append(true).create(true),
-            "ax" => opts.append(true).create_new(true),
-            "a+" => opts.read(true).append(true).create(true),
-            "ax+" => opts.read(true).append(true).create_new(true),
-            _ => &mut opts,
-        };
-        match final_opts.open(path) {
+        match open_options(options).open(resolve(&ctx, &path)?) {
             Ok(file) => Ok(Shared::new(Locked::new(file))),
             Err(e) => Err(format!("{}", &e).into()),
         }
@@ -87,7 +356,7 @@ pub mod file_functions {
         options: &str,
     ) -> Result<SharedFile, Box<EvalAltResult>> {
         let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
-        open_file_with_opts(path, options)
+        open_file_with_opts(ctx, path, options)
     }
 
     /// Remove a file at the given path.
@@ -97,8 +366,8 @@ pub mod file_functions {
     /// - The file doesn't exist.
     /// - The user lacks permissions to remove the file.
     #[rhai_fn(return_raw)]
-    pub fn remove_file(path: PathBuf) -> Result<(), Box<EvalAltResult>> {
-        std::fs::remove_file(path).map_err(|e| e.to_string().into())
+    pub fn remove_file(ctx: NativeCallContext, path: PathBuf) -> Result<(), Box<EvalAltResult>> {
+        std::fs::remove_file(resolve(&ctx, &path)?).map_err(|e| e.to_string().into())
     }
 
     /// Helper function for `remove_file` that takes a string instead of `PathBuf`.
@@ -108,7 +377,7 @@ pub mod file_functions {
         path_raw: ImmutableString,
     ) -> Result<(), Box<EvalAltResult>> {
         let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
-        std::fs::remove_file(path).map_err(|e| e.to_string().into())
+        remove_file(ctx, path)
     }
 
     /// Reads from the current stream position until EOF and returns it as a string, respects the engine's `max_string_size`.
@@ -125,7 +394,12 @@ pub mod file_functions {
 
     /// Reads from the current stream position up to the passed `len` and returns it as a string, respects the engine's `max_string_size`.
     ///
+    /// Fewer bytes are only returned when EOF is reached first, or when the limit cuts a character in two.
+    /// In that case the read stops before the character and the stream is left at its start, so reading in chunks never splits one.
+    ///
     /// Throws an exception when:
+    /// - The text isn't valid UTF-8.
+    /// - `len` is too short to hold the next character, leaving the stream position unchanged.
     /// - The read function encounters an I/O error.
     #[rhai_fn(global, pure, return_raw, name = "read_string")]
     pub fn read_to_string_with_len(
@@ -133,32 +407,75 @@ pub mod file_functions {
         file: &mut SharedFile,
         len: rhai::INT,
     ) -> Result<String, Box<EvalAltResult>> {
-        let mut buf: Vec<u8> = Vec::new();
-
-        let max_len = ctx.engine().max_string_size();
-        let res = match max_len {
-            0 if len == 0 => borrow_mut(file).read_to_end(&mut buf),
-            0 if len > 0 => {
-                buf.resize(len as usize, 0);
-                borrow_mut(file).read(&mut buf)
-            }
-            _ if len == 0 => {
-                buf.resize(max_len, 0);
-                borrow_mut(file).read(&mut buf)
-            }
-            _ => {
-                buf.resize(max_len.min(len as usize), 0);
-                borrow_mut(file).read(&mut buf)
+        read_to_string_with_len_encoding(ctx, file, len, "utf-8")
+    }
+
+    /// Reads from the current stream position until EOF and decodes it with the passed encoding, respects the engine's `max_string_size`.
+    ///
+    /// Supported encodings are `"utf-8"`, `"utf-8-lossy"`, `"utf-16"`, `"utf-16le"`, `"utf-16be"`, `"latin1"`, `"windows-1252"` and `"ascii"`.
+    /// A byte order mark at the start of the file is skipped, `"utf-16"` uses it to pick the byte order and defaults to little endian.
+    ///
+    /// Throws an exception when:
+    /// - The encoding isn't supported.
+    /// - The text isn't valid in the encoding.
+    /// - The read function encounters an I/O error.
+    #[rhai_fn(global, pure, return_raw, name = "read_string")]
+    pub fn read_to_string_with_encoding(
+        ctx: NativeCallContext,
+        file: &mut SharedFile,
+        encoding: &str,
+    ) -> Result<String, Box<EvalAltResult>> {
+        read_to_string_with_len_encoding(ctx, file, 0, encoding)
+    }
+
+    /// Reads from the current stream position up to the passed `len` in bytes and decodes it with the passed encoding, respects the engine's `max_string_size`.
+    ///
+    /// See `read_string(encoding)` for the supported encodings.
+    /// A character cut in two by the limit is left for the next read, like `read_string(len)`.
+    ///
+    /// Throws an exception when:
+    /// - The encoding isn't supported.
+    /// - The text isn't valid in the encoding.
+    /// - `len` is too short to hold the next character, leaving the stream position unchanged.
+    /// - The read function encounters an I/O error.
+    #[rhai_fn(global, pure, return_raw, name = "read_string")]
+    pub fn read_to_string_with_len_encoding(
+        ctx: NativeCallContext,
+        file: &mut SharedFile,
+        len: rhai::INT,
+        encoding: &str,
+    ) -> Result<String, Box<EvalAltResult>> {
+        let encoding = Encoding::from_name(encoding)?;
+        let at_start = borrow_mut(file)
+            .stream_position()
+            .map_err(|e| e.to_string())?
+            == 0;
+        // `utf-16` takes its byte order from the start of the file, which later reads have to look back at.
+        let encoding = match encoding {
+            Encoding::Utf16 if !at_start => {
+                let head = read_head(borrow_mut(file).deref_mut()).map_err(|e| e.to_string())?;
+                encoding.with_byte_order(&head)
             }
+            _ => encoding,
         };
+        let limit = read_limit(len, ctx.engine().max_string_size());
+        read_string_from(borrow_mut(file).deref_mut(), limit, encoding, at_start)
+    }
 
-        match res {
-            Ok(read_len) => {
-                buf.truncate(read_len);
-                String::from_utf8(buf).map_err(|e| e.to_string().into())
-            }
-            Err(e) => Err(format!("{}", &e).into()),
-        }
+    /// Reads from the current stream position up to and including the next newline and returns it as a string, respects the engine's `max_string_size`.
+    ///
+    /// The newline is kept, so an empty string is only returned at EOF.
+    /// A line longer than `max_string_size` is returned in parts, never splitting a character.
+    ///
+    /// Throws an exception when:
+    /// - The text isn't valid UTF-8.
+    /// - The read function encounters an I/O error.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn read_line(
+        ctx: NativeCallContext,
+        file: &mut SharedFile,
+    ) -> Result<String, Box<EvalAltResult>> {
+        read_line_from(borrow_mut(file).deref_mut(), ctx.engine().max_string_size())
     }
 
     /// Writes the string into the file at the current stream position.
@@ -176,6 +493,27 @@ pub mod file_functions {
         }
     }
 
+    /// Encodes the string with the passed encoding and writes it into the file at the current stream position, returning the number of bytes written.
+    ///
+    /// See `read_string(encoding)` for the supported encodings, only `"utf-16"` writes a byte order mark.
+    ///
+    /// Throws an exception when:
+    /// - The encoding isn't supported.
+    /// - The string contains characters the encoding can't represent.
+    /// - The write function encounters an I/O error.
+    #[rhai_fn(global, pure, return_raw, name = "write")]
+    pub fn write_with_string_encoding(
+        file: &mut SharedFile,
+        str: &str,
+        encoding: &str,
+    ) -> Result<rhai::INT, Box<EvalAltResult>> {
+        let bytes = Encoding::from_name(encoding)?.encode(str)?;
+        match borrow_mut(file).write_all(&bytes) {
+            Ok(()) => convert_to_int(bytes.len()),
+            Err(e) => Err(format!("{}", &e).into()),
+        }
+    }
+
     /// Sets the stream to the provided position, relative to the start of the file.
     ///
     /// Throws an exception when:
@@ -197,6 +535,100 @@ pub mod file_functions {
         }
     }
 
+    /// Returns the friendly type name of a file.
+    #[rhai_fn(global, name = "to_string", pure)]
+    pub fn to_string(_file: &mut SharedFile) -> String {
+        "File".into()
+    }
+
+    /// Returns the debug representation of a file.
+    #[rhai_fn(global, name = "to_debug", pure)]
+    pub fn to_debug(file: &mut SharedFile) -> String {
+        format!("{:?}", *borrow_mut(file))
+    }
+
+    /// Takes an exclusive advisory lock on the file, blocking until it is available.
+    ///
+    /// The lock is released by `unlock` or when the last handle to the file is dropped.
+    ///
+    /// Throws an exception when:
+    /// - The OS doesn't support file locking.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn lock(file: &mut SharedFile) -> Result<(), Box<EvalAltResult>> {
+        borrow_mut(file).lock().map_err(|e| e.to_string().into())
+    }
+
+    /// Takes an exclusive advisory lock on the file, waiting at most `timeout` milliseconds for it to become available.
+    ///
+    /// Throws an exception when:
+    /// - The lock isn't available in time, the thrown value is a map with `kind` set to `"would_block"`.
+    /// - The OS doesn't support file locking.
+    #[rhai_fn(global, pure, return_raw, name = "lock")]
+    pub fn lock_with_timeout_ms(
+        file: &mut SharedFile,
+        timeout: rhai::INT,
+    ) -> Result<(), Box<EvalAltResult>> {
+        lock_with_timeout(file, timeout, File::try_lock)
+    }
+
+    /// Takes a shared advisory lock on the file, blocking until no exclusive lock is held.
+    ///
+    /// The lock is released by `unlock` or when the last handle to the file is dropped.
+    ///
+    /// Throws an exception when:
+    /// - The OS doesn't support file locking.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn lock_shared(file: &mut SharedFile) -> Result<(), Box<EvalAltResult>> {
+        borrow_mut(file)
+            .lock_shared()
+            .map_err(|e| e.to_string().into())
+    }
+
+    /// Takes a shared advisory lock on the file, waiting at most `timeout` milliseconds for it to become available.
+    ///
+    /// Throws an exception when:
+    /// - The lock isn't available in time, the thrown value is a map with `kind` set to `"would_block"`.
+    /// - The OS doesn't support file locking.
+    #[rhai_fn(global, pure, return_raw, name = "lock_shared")]
+    pub fn lock_shared_with_timeout_ms(
+        file: &mut SharedFile,
+        timeout: rhai::INT,
+    ) -> Result<(), Box<EvalAltResult>> {
+        lock_with_timeout(file, timeout, File::try_lock_shared)
+    }
+
+    /// Tries to take an exclusive advisory lock on the file without blocking, returning `false` if it is held elsewhere.
+    ///
+    /// Throws an exception when:
+    /// - The OS doesn't support file locking.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn try_lock(file: &mut SharedFile) -> Result<bool, Box<EvalAltResult>> {
+        match borrow_mut(file).try_lock() {
+            Ok(()) => Ok(true),
+            Err(TryLockError::WouldBlock) => Ok(false),
+            Err(TryLockError::Error(e)) => Err(e.to_string().into()),
+        }
+    }
+
+    /// Tries to take a shared advisory lock on the file without blocking, returning `false` if an exclusive lock is held elsewhere.
+    ///
+    /// Throws an exception when:
+    /// - The OS doesn't support file locking.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn try_lock_shared(file: &mut SharedFile) -> Result<bool, Box<EvalAltResult>> {
+        match borrow_mut(file).try_lock_shared() {
+            Ok(()) => Ok(true),
+            Err(TryLockError::WouldBlock) => Ok(false),
+            Err(TryLockError::Error(e)) => Err(e.to_string().into()),
+        }
+    }
+
+    /// Releases any advisory lock held on the file through this handle.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn unlock(file: &mut SharedFile) -> Result<(), Box<EvalAltResult>> {
+        borrow_mut(file).unlock().map_err(|e| e.to_string().into())
+    }
+
     /// Returns the size of the file, in bytes.
     #[rhai_fn(global, pure, return_raw)]
     pub fn bytes(file: &mut SharedFile) -> Result<rhai::INT, Box<EvalAltResult>> {
@@ -206,6 +638,37 @@ pub mod file_functions {
         }
     }
 
+    /// Copies up to `len` bytes from the current stream position of the file to the current stream position of `other`, returning the number of bytes copied.
+    ///
+    /// The data never passes through a `Blob`, and kernel fast paths such as `copy_file_range` are used where available.
+    /// Fewer bytes are only copied when EOF is reached first.
+    ///
+    /// Throws an exception when:
+    /// - `len` is negative.
+    /// - Both handles are the same.
+    /// - The copy encounters an I/O error.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn copy_to(
+        file: &mut SharedFile,
+        other: SharedFile,
+        len: rhai::INT,
+    ) -> Result<rhai::INT, Box<EvalAltResult>> {
+        let len = u64::try_from(len).map_err(|_| format!("Invalid length {len}"))?;
+        copy_between(file, &other, Some(len))
+    }
+
+    /// Copies everything from the current stream position of `src` until EOF to the current stream position of `dst`, returning the number of bytes copied.
+    ///
+    /// The data never passes through a `Blob`, and kernel fast paths such as `copy_file_range` are used where available.
+    ///
+    /// Throws an exception when:
+    /// - Both handles are the same.
+    /// - The copy encounters an I/O error.
+    #[rhai_fn(return_raw)]
+    pub fn copy_stream(src: SharedFile, dst: SharedFile) -> Result<rhai::INT, Box<EvalAltResult>> {
+        copy_between(&src, &dst, None)
+    }
+
     #[cfg(not(feature = "no_index"))]
     pub mod blob_functions {
         use rhai::Blob;
@@ -220,44 +683,57 @@ pub mod file_functions {
         }
 
         /// Reads from the current stream position up to the passed `len` and returns it as a `Blob`, respects the engine's `max_array_size`.
+        ///
+        /// Fewer bytes are only returned when EOF is reached first, see `read_some` for a single read call.
         #[rhai_fn(global, pure, return_raw, name = "read_blob")]
         pub fn read_to_blob_with_len(
             ctx: NativeCallContext,
             file: &mut SharedFile,
             len: rhai::INT,
         ) -> Result<Blob, Box<EvalAltResult>> {
-            let mut buf: Vec<u8> = Vec::new();
+            let limit = read_limit(len, ctx.engine().max_array_size());
+            read_limited(borrow_mut(file).deref_mut(), limit)
+        }
 
+        /// Reads at most `len` bytes from the current stream position with a single read call and returns them as a `Blob`.
+        ///
+        /// Unlike `read_blob`, this may return fewer bytes than requested even before EOF, e.g. when reading from a pipe.
+        /// An empty `Blob` is returned at EOF.
+        ///
+        /// Throws an exception when:
+        /// - `len` is negative or exceeds the engine's `max_array_size`.
+        /// - The read function encounters an I/O error.
+        #[rhai_fn(global, pure, return_raw)]
+        pub fn read_some(
+            ctx: NativeCallContext,
+            file: &mut SharedFile,
+            len: rhai::INT,
+        ) -> Result<Blob, Box<EvalAltResult>> {
             let max_len = ctx.engine().max_array_size();
-            let res = match max_len {
-                0 if len == 0 => borrow_mut(file).read_to_end(&mut buf),
-                0 if len > 0 => {
-                    buf.resize(len as usize, 0);
-                    borrow_mut(file).read(&mut buf)
-                }
-                _ if len == 0 => {
-                    buf.resize(max_len, 0);
-                    borrow_mut(file).read(&mut buf)
-                }
-                _ => {
-                    buf.resize(max_len.min(len as usize), 0);
-                    borrow_mut(file).read(&mut buf)
+            let len = usize::try_from(len)
+                .ok()
+                .filter(|&len| max_len == 0 || len <= max_len)
+                .ok_or_else(|| format!("Invalid length {len}"))?;
+
+            let mut buf = vec![0; len.min(READ_SOME_MAX_LEN)];
+            let read_len = loop {
+                match borrow_mut(file).read(&mut buf) {
+                    Ok(read_len) => break read_len,
+                    Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e.to_string().into()),
                 }
             };
-
-            match res {
-                Ok(_) => Ok(buf),
-                Err(e) => Err(format!("{}", &e).into()),
-            }
+            buf.truncate(read_len);
+            Ok(buf)
         }
 
         /// Reads from the current stream position into the provided `Blob` with the read length being returned.
         #[rhai_fn(global, return_raw)]
         pub fn read_from_file(
             blob: &mut Blob,
-            mut file: SharedFile,
+            file: SharedFile,
         ) -> Result<rhai::INT, Box<EvalAltResult>> {
-            match borrow_mut(&mut file).read(blob) {
+            match borrow_mut(&file).read(blob) {
                 Ok(len) => convert_to_int(len),
                 Err(e) => Err(format!("{}", &e).into()),
             }
@@ -267,9 +743,9 @@ pub mod file_functions {
         #[rhai_fn(global, pure, return_raw)]
         pub fn write_to_file(
             blob: &mut Blob,
-            mut file: SharedFile,
+            file: SharedFile,
         ) -> Result<rhai::INT, Box<EvalAltResult>> {
-            match borrow_mut(&mut file).write(blob) {
+            match borrow_mut(&file).write(blob) {
                 Ok(len) => convert_to_int(len),
                 Err(e) => Err(format!("{}", &e).into()),
             }
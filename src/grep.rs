@@ -0,0 +1,667 @@
+#[allow(unused_imports)]
+use rhai::plugin::*;
+use rhai::{Array, Map};
+
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use super::config::{resolve, tick};
+use super::diff::Glob;
+#[cfg(feature = "diff")]
+use super::diff::{unified_diff, DEFAULT_CONTEXT};
+use super::file::{create_temp_beside, read_text, read_text_bytes};
+
+/// How many bytes at the start of a file are checked for a NUL byte to tell binary files apart.
+const BINARY_CHECK_LEN: usize = 8 * 1024;
+
+/// A pattern searched for in lines of text.
+pub(crate) enum Matcher {
+    /// A literal string.
+    Plain {
+        /// The string to find.
+        pattern: String,
+        /// Whether letters match regardless of their case.
+        ignore_case: bool,
+    },
+    /// A regular expression.
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+}
+
+impl Matcher {
+    /// Builds a matcher, parsing the pattern as a regular expression when `regex` is set.
+    ///
+    /// Throws when `regex` is set but the `regex` feature is disabled, or the expression is invalid.
+    pub(crate) fn new(
+        pattern: &str,
+        regex: bool,
+        ignore_case: bool,
+    ) -> Result<Self, Box<EvalAltResult>> {
+        if !regex {
+            return Ok(Matcher::Plain {
+                pattern: pattern.to_string(),
+                ignore_case,
+            });
+        }
+
+        #[cfg(feature = "regex")]
+        return regex::RegexBuilder::new(pattern)
+            .case_insensitive(ignore_case)
+            .build()
+            .map(Matcher::Regex)
+            .map_err(|e| e.to_string().into());
+        #[cfg(not(feature = "regex"))]
+        Err("Regex patterns are not enabled, they require the `regex` feature".into())
+    }
+
+    /// Returns the byte range of the first match in the text.
+    pub(crate) fn find(&self, text: &str) -> Option<(usize, usize)> {
+        match self {
+            Matcher::Plain {
+                pattern,
+                ignore_case: false,
+            } => text
+                .find(pattern.as_str())
+                .map(|start| (start, start + pattern.len())),
+            Matcher::Plain {
+                pattern,
+                ignore_case: true,
+            } => find_ignore_case(text, pattern),
+            #[cfg(feature = "regex")]
+            Matcher::Regex(regex) => regex.find(text).map(|m| (m.start(), m.end())),
+        }
+    }
+
+    /// Replaces every match in the text, returning the new text and the number of replacements.
+    ///
+    /// Capture references such as `$1` or `${name}` in the replacement are expanded for regular expressions, plain patterns insert it as is.
+    pub(crate) fn replace_all<'t>(
+        &self,
+        text: &'t str,
+        replacement: &str,
+    ) -> (Cow<'t, str>, usize) {
+        match self {
+            Matcher::Plain { .. } => {
+                let mut result = String::new();
+                let mut count = 0;
+                let mut pos = 0;
+                while let Some((start, end)) = self.find(&text[pos..]) {
+                    result.push_str(&text[pos..pos + start]);
+                    result.push_str(replacement);
+                    count += 1;
+                    pos += end;
+                }
+                if count == 0 {
+                    return (Cow::Borrowed(text), 0);
+                }
+                result.push_str(&text[pos..]);
+                (Cow::Owned(result), count)
+            }
+            #[cfg(feature = "regex")]
+            Matcher::Regex(regex) => {
+                let count = regex.find_iter(text).count();
+                (regex.replace_all(text, replacement), count)
+            }
+        }
+    }
+}
+
+/// Returns `true` if both characters are equal regardless of their case.
+fn eq_ignore_case(a: char, b: char) -> bool {
+    a == b || a.to_lowercase().eq(b.to_lowercase())
+}
+
+/// Returns the byte range of the first occurrence of the pattern in the text, ignoring case.
+///
+/// Characters are compared one by one, so the range always matches the original text.
+fn find_ignore_case(text: &str, pattern: &str) -> Option<(usize, usize)> {
+    text.char_indices()
+        .map(|(start, _)| start)
+        .chain(std::iter::once(text.len()))
+        .find_map(|start| {
+            let mut rest = text[start..].char_indices();
+            let mut end = start;
+            for p in pattern.chars() {
+                match rest.next() {
+                    Some((idx, c)) if eq_ignore_case(c, p) => end = start + idx + c.len_utf8(),
+                    _ => return None,
+                }
+            }
+            Some((start, end))
+        })
+}
+
+/// Options of `grep` and `replace_in_files`.
+struct GrepOptions {
+    /// Whether the pattern is a regular expression.
+    regex: bool,
+    /// Whether letters match regardless of their case.
+    ignore_case: bool,
+    /// The number of matches after which the search stops.
+    max_matches: Option<usize>,
+    /// Whether files that look binary are searched too.
+    binary: bool,
+    /// Whether subdirectories are searched.
+    recursive: bool,
+    /// Patterns of entries to skip while walking directories.
+    exclude: Vec<Glob>,
+    /// Whether replacements are only reported rather than written.
+    dry_run: bool,
+}
+
+impl GrepOptions {
+    /// Reads the options from a script map, throwing on unknown keys and values of the wrong type.
+    ///
+    /// `dry_run` is only accepted when `replace` is set, `max_matches` only when it isn't.
+    fn from_map(map: &Map, replace: bool) -> Result<Self, Box<EvalAltResult>> {
+        let mut options = GrepOptions {
+            regex: false,
+            ignore_case: false,
+            max_matches: None,
+            binary: false,
+            recursive: true,
+            exclude: Vec::new(),
+            dry_run: false,
+        };
+
+        for (key, value) in map {
+            let flag = || {
+                value
+                    .as_bool()
+                    .map_err(|_| format!("Option {key} must be a bool"))
+            };
+            match key.as_str() {
+                "regex" => options.regex = flag()?,
+                "ignore_case" => options.ignore_case = flag()?,
+                "binary" => options.binary = flag()?,
+                "recursive" => options.recursive = flag()?,
+                "exclude" => options.exclude = Glob::from_array(key, value)?,
+                "dry_run" if replace => options.dry_run = flag()?,
+                "max_matches" if !replace => {
+                    let max = value
+                        .as_int()
+                        .ok()
+                        .and_then(|max| usize::try_from(max).ok())
+                        .ok_or_else(|| format!("Option {key} must be a non-negative integer"))?;
+                    options.max_matches = Some(max).filter(|&max| max > 0);
+                }
+                _ => return Err(format!("Unknown option {key}").into()),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// Splits a path at its first component holding a glob wildcard, returning the directory to walk and the pattern to match below it.
+fn split_glob(raw: &str) -> Option<(&str, &str)> {
+    let wildcard = raw.find(['*', '?'])?;
+    match raw[..wildcard].rfind(['/', std::path::MAIN_SEPARATOR]) {
+        Some(0) => Some(("/", &raw[1..])),
+        Some(sep) => Some((&raw[..sep], &raw[sep + 1..])),
+        None => Some((".", raw)),
+    }
+}
+
+/// Adds the regular files below `dir` to `files` in name order, as paths relative to the root walked.
+///
+/// Symlinks found while walking are skipped, excluded entries and everything below excluded directories too.
+/// With a glob, only files whose relative path matches it are added.
+fn walk_files(
+    ctx: &NativeCallContext,
+    dir: &Path,
+    prefix: &Path,
+    glob: Option<&Glob>,
+    options: &GrepOptions,
+    files: &mut Vec<PathBuf>,
+) -> Result<(), Box<EvalAltResult>> {
+    tick(ctx)?;
+    let display = dir.to_string_lossy();
+    let mut children = std::fs::read_dir(dir)
+        .and_then(|dir| dir.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("{display}: {e}"))?;
+    children.sort_by_key(|child| child.file_name());
+
+    for child in children {
+        let rel = prefix.join(child.file_name());
+        let rel_str = rel
+            .to_string_lossy()
+            .replace(std::path::MAIN_SEPARATOR, "/");
+        if options.exclude.iter().any(|glob| glob.matches(&rel_str)) {
+            continue;
+        }
+        let kind = child
+            .file_type()
+            .map_err(|e| format!("{}: {e}", child.path().to_string_lossy()))?;
+        if kind.is_dir() && (options.recursive || glob.is_some()) {
+            walk_files(ctx, &child.path(), &rel, glob, options, files)?;
+        } else if kind.is_file() && glob.is_none_or(|glob| glob.matches(&rel_str)) {
+            files.push(rel);
+        }
+    }
+    Ok(())
+}
+
+/// Searches the file line by line, adding a map for each matching line to `matches`.
+///
+/// Returns `false` once `max_matches` is reached.
+fn grep_file(
+    path: &Path,
+    display_path: PathBuf,
+    matcher: &Matcher,
+    options: &GrepOptions,
+    matches: &mut Array,
+) -> Result<bool, Box<EvalAltResult>> {
+    let err = |e: std::io::Error| format!("{}: {e}", path.to_string_lossy());
+    let mut reader = BufReader::new(File::open(path).map_err(err)?);
+    if !options.binary {
+        let start = reader.fill_buf().map_err(err)?;
+        if start[..start.len().min(BINARY_CHECK_LEN)].contains(&0) {
+            return Ok(true);
+        }
+    }
+
+    let mut buf = Vec::new();
+    let mut line_no: rhai::INT = 0;
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf).map_err(err)? == 0 {
+            return Ok(true);
+        }
+        line_no += 1;
+        if buf.ends_with(b"\n") {
+            buf.pop();
+            if buf.ends_with(b"\r") {
+                buf.pop();
+            }
+        }
+
+        let line = String::from_utf8_lossy(&buf);
+        if let Some((start, end)) = matcher.find(&line) {
+            let mut map = Map::new();
+            map.insert("path".into(), Dynamic::from(display_path.clone()));
+            map.insert("line_no".into(), line_no.into());
+            map.insert("match".into(), line[start..end].into());
+            map.insert("line".into(), line.into_owned().into());
+            matches.push(map.into());
+
+            if options.max_matches == Some(matches.len()) {
+                return Ok(false);
+            }
+        }
+    }
+}
+
+/// Returns the files to search at `path`: the file itself, or the files below the directory matching the glob.
+///
+/// Each file comes with the path to report it by, joined to `path`.
+fn find_files(
+    ctx: &NativeCallContext,
+    path: &Path,
+    glob: Option<&Glob>,
+    options: &GrepOptions,
+) -> Result<Vec<(PathBuf, PathBuf)>, Box<EvalAltResult>> {
+    let root = resolve(ctx, path)?;
+    if glob.is_none() && !root.is_dir() {
+        return Ok(vec![(root, path.to_path_buf())]);
+    }
+
+    let mut files = Vec::new();
+    walk_files(ctx, &root, Path::new(""), glob, options, &mut files)?;
+    Ok(files
+        .into_iter()
+        .map(|rel| match path == Path::new(".") {
+            // Files found below a glob without a directory read like the glob.
+            true => (root.join(&rel), rel),
+            false => (root.join(&rel), path.join(&rel)),
+        })
+        .collect())
+}
+
+/// Searches the file or directory at `path`, or the files below it matching the glob.
+fn grep_paths(
+    ctx: &NativeCallContext,
+    path: PathBuf,
+    glob: Option<Glob>,
+    pattern: &str,
+    options: Map,
+) -> Result<Array, Box<EvalAltResult>> {
+    let options = GrepOptions::from_map(&options, false)?;
+    let matcher = Matcher::new(pattern, options.regex, options.ignore_case)?;
+
+    let mut matches = Array::new();
+    for (file, display_path) in find_files(ctx, &path, glob.as_ref(), &options)? {
+        tick(ctx)?;
+        if !grep_file(&file, display_path, &matcher, &options, &mut matches)? {
+            break;
+        }
+    }
+    Ok(matches)
+}
+
+/// Replaces the file's content by writing a temporary file next to it and renaming it over the file, so readers never see a partial write.
+///
+/// The permissions of the file are kept, and a symlink is followed so that the file it points to is replaced.
+fn write_atomic(path: &Path, content: &[u8]) -> Result<(), Box<EvalAltResult>> {
+    let display = path.to_string_lossy();
+    let err = |e: std::io::Error| format!("{display}: {e}");
+    let path = std::fs::canonicalize(path).map_err(err)?;
+    let permissions = std::fs::metadata(&path).map_err(err)?.permissions();
+    let (tmp, mut file) = create_temp_beside(&path).map_err(err)?;
+
+    let res = file
+        .write_all(content)
+        .and_then(|_| file.set_permissions(permissions))
+        .and_then(|_| file.sync_all())
+        .and_then(|_| std::fs::rename(&tmp, &path));
+    if let Err(e) = res {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(err(e).into());
+    }
+    Ok(())
+}
+
+/// Replaces every match in the file's text, returning the number of replacements and a report map.
+fn replace_file(
+    path: &Path,
+    text: &str,
+    display_path: PathBuf,
+    matcher: &Matcher,
+    replacement: &str,
+    options: &GrepOptions,
+) -> Result<(usize, Map), Box<EvalAltResult>> {
+    let (replaced, count) = matcher.replace_all(text, replacement);
+
+    let mut report = Map::new();
+    if options.dry_run {
+        #[cfg(feature = "diff")]
+        {
+            let name = display_path.to_string_lossy();
+            let diff = unified_diff(text, &replaced, (&name, &name), DEFAULT_CONTEXT);
+            report.insert("diff".into(), diff.into());
+        }
+    } else if count > 0 {
+        write_atomic(path, replaced.as_bytes())?;
+    }
+    report.insert("path".into(), Dynamic::from(display_path));
+    report.insert("replacements".into(), (count as rhai::INT).into());
+    Ok((count, report))
+}
+
+/// Replaces every match in the file or directory at `path`, or the files below it matching the glob.
+///
+/// Returns a report map for each file that changed.
+fn replace_paths(
+    ctx: &NativeCallContext,
+    path: PathBuf,
+    glob: Option<Glob>,
+    pattern: &str,
+    replacement: &str,
+    options: Map,
+) -> Result<Array, Box<EvalAltResult>> {
+    let options = GrepOptions::from_map(&options, true)?;
+    let matcher = Matcher::new(pattern, options.regex, options.ignore_case)?;
+    if pattern.is_empty() {
+        return Err("Pattern to replace must not be empty".into());
+    }
+
+    let mut reports = Array::new();
+    for (file, display_path) in find_files(ctx, &path, glob.as_ref(), &options)? {
+        tick(ctx)?;
+        if !options.binary && is_binary(&file)? {
+            continue;
+        }
+        // Like binary files, files that aren't UTF-8 are skipped rather than stopping halfway through.
+        let Ok(text) = String::from_utf8(read_text_bytes(ctx, &file)?) else {
+            continue;
+        };
+        let (count, report) =
+            replace_file(&file, &text, display_path, &matcher, replacement, &options)?;
+        if count > 0 {
+            reports.push(report.into());
+        }
+    }
+    Ok(reports)
+}
+
+/// Returns `true` if the file has a NUL byte near its start.
+fn is_binary(path: &Path) -> Result<bool, Box<EvalAltResult>> {
+    let mut reader = BufReader::with_capacity(
+        BINARY_CHECK_LEN,
+        File::open(path).map_err(|e| format!("{}: {e}", path.to_string_lossy()))?,
+    );
+    let start = reader.fill_buf().map_err(|e| e.to_string())?;
+    Ok(start.contains(&0))
+}
+
+#[export_module]
+pub mod grep_functions {
+    /// Searches a file, or the files below a directory, for lines containing the plain string `pattern`.
+    ///
+    /// See `grep(path, pattern, options)` for details.
+    #[rhai_fn(return_raw)]
+    pub fn grep(
+        ctx: NativeCallContext,
+        path: PathBuf,
+        pattern: &str,
+    ) -> Result<Array, Box<EvalAltResult>> {
+        grep_with_options(ctx, path, pattern, Map::new())
+    }
+
+    /// Searches files matching a path or glob for lines containing the plain string `pattern`.
+    ///
+    /// See `grep(path, pattern, options)` for details.
+    #[rhai_fn(return_raw, name = "grep")]
+    pub fn grep_str(
+        ctx: NativeCallContext,
+        path_raw: ImmutableString,
+        pattern: &str,
+    ) -> Result<Array, Box<EvalAltResult>> {
+        grep_with_options_str(ctx, path_raw, pattern, Map::new())
+    }
+
+    /// Searches a file, or the files below a directory, line by line for `pattern`.
+    ///
+    /// Returns an array with a map for each matching line, in path order:
+    /// - `path`: the path of the file, joined to the passed path.
+    /// - `line_no`: the number of the line, starting at 1.
+    /// - `line`: the line without its line ending. Invalid UTF-8 is replaced with `�`.
+    /// - `match`: the text of the first match in the line.
+    ///
+    /// Files are read as a stream, so they are never loaded whole.
+    /// Symlinks found while walking directories are skipped.
+    ///
+    /// Options:
+    /// - `regex`: parse `pattern` as a regular expression, defaults to `false`. Requires the `regex` feature.
+    /// - `ignore_case`: match letters regardless of their case, defaults to `false`.
+    /// - `max_matches`: stop after this many matching lines, defaults to 0 for no limit.
+    /// - `binary`: also search files with a NUL byte near their start, defaults to `false` which skips them.
+    /// - `recursive`: search subdirectories, defaults to `true`. Globs are always matched at any depth.
+    /// - `exclude`: an array of glob patterns of entries to skip while walking, as in `diff_dirs`.
+    ///
+    /// Throws an exception when:
+    /// - The path doesn't exist, or a file can't be read.
+    /// - `pattern` isn't a valid regular expression.
+    /// - An option is unknown or has the wrong type.
+    #[rhai_fn(return_raw, name = "grep")]
+    pub fn grep_with_options(
+        ctx: NativeCallContext,
+        path: PathBuf,
+        pattern: &str,
+        options: Map,
+    ) -> Result<Array, Box<EvalAltResult>> {
+        grep_paths(&ctx, path, None, pattern, options)
+    }
+
+    /// Searches files matching a path or glob line by line for `pattern`.
+    ///
+    /// When the string contains `*` or `?` it is a glob, where `*` and `?` stay within a directory and `**` matches across directories,
+    /// e.g. `"src/**/*.rs"`. Otherwise it is a path, see `grep(path, pattern, options)` for the rest.
+    #[rhai_fn(return_raw, name = "grep")]
+    pub fn grep_with_options_str(
+        ctx: NativeCallContext,
+        path_raw: ImmutableString,
+        pattern: &str,
+        options: Map,
+    ) -> Result<Array, Box<EvalAltResult>> {
+        match split_glob(&path_raw) {
+            Some((base, glob)) => {
+                let path = ctx.call_native_fn::<PathBuf>("path", (ImmutableString::from(base),))?;
+                grep_paths(&ctx, path, Some(Glob::anchored(glob)), pattern, options)
+            }
+            None => {
+                let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+                grep_with_options(ctx, path, pattern, options)
+            }
+        }
+    }
+
+    /// Replaces every occurrence of the plain string `pattern` in the file with `replacement`.
+    ///
+    /// See `replace_in_file(path, pattern, replacement, options)` for details.
+    #[rhai_fn(return_raw)]
+    pub fn replace_in_file(
+        ctx: NativeCallContext,
+        path: PathBuf,
+        pattern: &str,
+        replacement: &str,
+    ) -> Result<Map, Box<EvalAltResult>> {
+        replace_in_file_with_options(ctx, path, pattern, replacement, Map::new())
+    }
+
+    /// Helper function for `replace_in_file` that takes a string instead of `PathBuf`.
+    #[rhai_fn(return_raw, name = "replace_in_file")]
+    pub fn replace_in_file_str(
+        ctx: NativeCallContext,
+        path_raw: ImmutableString,
+        pattern: &str,
+        replacement: &str,
+    ) -> Result<Map, Box<EvalAltResult>> {
+        let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+        replace_in_file(ctx, path, pattern, replacement)
+    }
+
+    /// Replaces every match of `pattern` in the UTF-8 text file with `replacement`, and returns a report map:
+    /// - `path`: the passed path.
+    /// - `replacements`: the number of replacements.
+    /// - `diff`: in a dry run, the unified diff of the change, empty when nothing matched. Requires the `diff` feature.
+    ///
+    /// The new content is written to a temporary file that then replaces the file, so it is never left half written.
+    /// The file keeps its permissions, and a symlink is followed so that the file it points to is replaced.
+    /// The file isn't touched when nothing matched.
+    ///
+    /// Options:
+    /// - `regex`: parse `pattern` as a regular expression, defaults to `false`. Requires the `regex` feature.
+    ///   Capture references such as `$1` or `${name}` in `replacement` are then expanded, and `$$` inserts a `$`.
+    /// - `ignore_case`: match letters regardless of their case, defaults to `false`.
+    /// - `dry_run`: only report what would be replaced, defaults to `false`.
+    ///
+    /// Throws an exception when:
+    /// - The file can't be read, isn't valid UTF-8 or is larger than the engine's `max_string_size`.
+    /// - The file can't be written.
+    /// - `pattern` is empty or isn't a valid regular expression.
+    /// - An option is unknown or has the wrong type.
+    #[rhai_fn(return_raw, name = "replace_in_file")]
+    pub fn replace_in_file_with_options(
+        ctx: NativeCallContext,
+        path: PathBuf,
+        pattern: &str,
+        replacement: &str,
+        options: Map,
+    ) -> Result<Map, Box<EvalAltResult>> {
+        let options = GrepOptions::from_map(&options, true)?;
+        let matcher = Matcher::new(pattern, options.regex, options.ignore_case)?;
+        if pattern.is_empty() {
+            return Err("Pattern to replace must not be empty".into());
+        }
+        let file = resolve(&ctx, &path)?;
+        let text = read_text(&ctx, &file)?;
+        replace_file(&file, &text, path, &matcher, replacement, &options).map(|(_, report)| report)
+    }
+
+    /// Helper function for `replace_in_file` that takes a string instead of `PathBuf`.
+    #[rhai_fn(return_raw, name = "replace_in_file")]
+    pub fn replace_in_file_with_options_str(
+        ctx: NativeCallContext,
+        path_raw: ImmutableString,
+        pattern: &str,
+        replacement: &str,
+        options: Map,
+    ) -> Result<Map, Box<EvalAltResult>> {
+        let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+        replace_in_file_with_options(ctx, path, pattern, replacement, options)
+    }
+
+    /// Replaces every occurrence of the plain string `pattern` with `replacement` in a file, or the files below a directory.
+    ///
+    /// See `replace_in_files(path, pattern, replacement, options)` for details.
+    #[rhai_fn(return_raw)]
+    pub fn replace_in_files(
+        ctx: NativeCallContext,
+        path: PathBuf,
+        pattern: &str,
+        replacement: &str,
+    ) -> Result<Array, Box<EvalAltResult>> {
+        replace_in_files_with_options(ctx, path, pattern, replacement, Map::new())
+    }
+
+    /// Replaces every occurrence of the plain string `pattern` with `replacement` in files matching a path or glob.
+    ///
+    /// See `replace_in_files(path, pattern, replacement, options)` for details.
+    #[rhai_fn(return_raw, name = "replace_in_files")]
+    pub fn replace_in_files_str(
+        ctx: NativeCallContext,
+        path_raw: ImmutableString,
+        pattern: &str,
+        replacement: &str,
+    ) -> Result<Array, Box<EvalAltResult>> {
+        replace_in_files_with_options_str(ctx, path_raw, pattern, replacement, Map::new())
+    }
+
+    /// Replaces every match of `pattern` with `replacement` in a file, or the files below a directory, as `replace_in_file` does.
+    ///
+    /// Returns an array with the report map of each file that changed, in path order, see `replace_in_file(path, pattern, replacement, options)`.
+    /// Files are replaced one at a time, so an error leaves the files before it changed.
+    /// Files that aren't valid UTF-8 are skipped.
+    ///
+    /// Options, besides those of `replace_in_file`:
+    /// - `binary`: also replace in files with a NUL byte near their start, defaults to `false` which skips them.
+    /// - `recursive`: search subdirectories, defaults to `true`.
+    /// - `exclude`: an array of glob patterns of entries to skip while walking, as in `diff_dirs`.
+    #[rhai_fn(return_raw, name = "replace_in_files")]
+    pub fn replace_in_files_with_options(
+        ctx: NativeCallContext,
+        path: PathBuf,
+        pattern: &str,
+        replacement: &str,
+        options: Map,
+    ) -> Result<Array, Box<EvalAltResult>> {
+        replace_paths(&ctx, path, None, pattern, replacement, options)
+    }
+
+    /// Replaces every match of `pattern` with `replacement` in files matching a path or glob.
+    ///
+    /// Globs are expanded as in `grep`, see `replace_in_files(path, pattern, replacement, options)` for the rest.
+    #[rhai_fn(return_raw, name = "replace_in_files")]
+    pub fn replace_in_files_with_options_str(
+        ctx: NativeCallContext,
+        path_raw: ImmutableString,
+        pattern: &str,
+        replacement: &str,
+        options: Map,
+    ) -> Result<Array, Box<EvalAltResult>> {
+        match split_glob(&path_raw) {
+            Some((base, glob)) => {
+                let path = ctx.call_native_fn::<PathBuf>("path", (ImmutableString::from(base),))?;
+                let glob = Some(Glob::anchored(glob));
+                replace_paths(&ctx, path, glob, pattern, replacement, options)
+            }
+            None => {
+                let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+                replace_in_files_with_options(ctx, path, pattern, replacement, options)
+            }
+        }
+    }
+}
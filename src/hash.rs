@@ -0,0 +1,322 @@
+#[allow(unused_imports)]
+use rhai::plugin::*;
+
+use std::fmt::Write as _;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+use super::config::{resolve, tick};
+use super::file::{borrow_mut, SharedFile};
+
+/// Size of the chunks content is streamed through the hasher in.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// A running hash of one of the algorithms enabled through cargo features.
+enum Hasher {
+    /// SHA-256.
+    #[cfg(feature = "sha256")]
+    Sha256(sha2::Sha256),
+    /// SHA-1.
+    #[cfg(feature = "sha1")]
+    Sha1(sha1::Sha1),
+    /// MD5.
+    #[cfg(feature = "md5")]
+    Md5(md5::Md5),
+    /// BLAKE3.
+    #[cfg(feature = "blake3")]
+    Blake3(Box<blake3::Hasher>),
+    /// CRC32 (IEEE).
+    #[cfg(feature = "crc32")]
+    Crc32(crc32fast::Hasher),
+}
+
+impl Hasher {
+    /// Creates a hasher for the named algorithm, ignoring case.
+    ///
+    /// Throws an exception when the algorithm is unknown or its cargo feature isn't enabled.
+    fn new(algo: &str) -> Result<Self, Box<EvalAltResult>> {
+        // The second set of arms only matches algorithms whose feature is disabled.
+        #[allow(unreachable_patterns)]
+        let feature = match algo.to_ascii_lowercase().as_str() {
+            #[cfg(feature = "sha256")]
+            "sha256" | "sha-256" => return Ok(Hasher::Sha256(sha2::Digest::new())),
+            #[cfg(feature = "sha1")]
+            "sha1" | "sha-1" => return Ok(Hasher::Sha1(sha1::Digest::new())),
+            #[cfg(feature = "md5")]
+            "md5" => return Ok(Hasher::Md5(md5::Digest::new())),
+            #[cfg(feature = "blake3")]
+            "blake3" => return Ok(Hasher::Blake3(Box::default())),
+            #[cfg(feature = "crc32")]
+            "crc32" => return Ok(Hasher::Crc32(crc32fast::Hasher::new())),
+            "sha256" | "sha-256" => "sha256",
+            "sha1" | "sha-1" => "sha1",
+            "md5" => "md5",
+            "blake3" => "blake3",
+            "crc32" => "crc32",
+            _ => return Err(format!("Unknown hash algorithm {algo}").into()),
+        };
+        Err(
+            format!("Hash algorithm {algo} is not enabled, it requires the `{feature}` feature")
+                .into(),
+        )
+    }
+
+    /// Feeds bytes into the hash.
+    fn update(&mut self, bytes: &[u8]) {
+        match self {
+            #[cfg(feature = "sha256")]
+            Hasher::Sha256(h) => sha2::Digest::update(h, bytes),
+            #[cfg(feature = "sha1")]
+            Hasher::Sha1(h) => sha1::Digest::update(h, bytes),
+            #[cfg(feature = "md5")]
+            Hasher::Md5(h) => md5::Digest::update(h, bytes),
+            #[cfg(feature = "blake3")]
+            Hasher::Blake3(h) => {
+                h.update(bytes);
+            }
+            #[cfg(feature = "crc32")]
+            Hasher::Crc32(h) => h.update(bytes),
+        }
+    }
+
+    /// Returns the hash as a lowercase hex string.
+    fn finish(self) -> String {
+        let bytes: Vec<u8> = match self {
+            #[cfg(feature = "sha256")]
+            Hasher::Sha256(h) => sha2::Digest::finalize(h).to_vec(),
+            #[cfg(feature = "sha1")]
+            Hasher::Sha1(h) => sha1::Digest::finalize(h).to_vec(),
+            #[cfg(feature = "md5")]
+            Hasher::Md5(h) => md5::Digest::finalize(h).to_vec(),
+            #[cfg(feature = "blake3")]
+            Hasher::Blake3(h) => h.finalize().as_bytes().to_vec(),
+            #[cfg(feature = "crc32")]
+            Hasher::Crc32(h) => h.finalize().to_be_bytes().to_vec(),
+        };
+        bytes.iter().fold(String::new(), |mut hex, b| {
+            let _ = write!(hex, "{b:02x}");
+            hex
+        })
+    }
+}
+
+/// Streams everything from the reader through a hasher of the named algorithm and returns the hex digest.
+fn hash_reader(
+    ctx: &NativeCallContext,
+    reader: &mut impl Read,
+    algo: &str,
+) -> Result<String, Box<EvalAltResult>> {
+    let mut hasher = Hasher::new(algo)?;
+    let mut buf = vec![0; CHUNK_SIZE];
+    loop {
+        tick(ctx)?;
+        match reader.read(&mut buf) {
+            Ok(0) => return Ok(hasher.finish()),
+            Ok(len) => hasher.update(&buf[..len]),
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.to_string().into()),
+        }
+    }
+}
+
+/// Hashes the file at an already resolved path.
+fn hash_path(
+    ctx: &NativeCallContext,
+    path: &Path,
+    algo: &str,
+) -> Result<String, Box<EvalAltResult>> {
+    let mut file =
+        std::fs::File::open(path).map_err(|e| format!("{}: {e}", path.to_string_lossy()))?;
+    hash_reader(ctx, &mut file, algo)
+}
+
+/// Parses a line of a `sha256sum` style manifest into the expected digest and the path.
+///
+/// Paths are in binary (`*path`) or text (` path`) mode, and a leading `\` marks a path with escaped newlines and backslashes.
+fn parse_manifest_line(line: &str) -> Option<(String, String)> {
+    let (escaped, line) = match line.strip_prefix('\\') {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+    let (digest, rest) = line.split_once(' ')?;
+    let path = rest.strip_prefix(['*', ' '])?;
+    if digest.is_empty() || path.is_empty() || !digest.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let path = if escaped {
+        let mut unescaped = String::with_capacity(path.len());
+        let mut chars = path.chars();
+        while let Some(c) = chars.next() {
+            match (c, c == '\\') {
+                (_, true) => match chars.next()? {
+                    'n' => unescaped.push('\n'),
+                    '\\' => unescaped.push('\\'),
+                    _ => return None,
+                },
+                (c, false) => unescaped.push(c),
+            }
+        }
+        unescaped
+    } else {
+        path.to_string()
+    };
+
+    Some((digest.to_ascii_lowercase(), path))
+}
+
+#[export_module]
+pub mod hash_functions {
+    use rhai::{Array, Map};
+
+    /// Hashes the content of the file at the path and returns the digest as a lowercase hex string.
+    ///
+    /// Supported algorithms are `"sha256"`, `"sha1"`, `"md5"`, `"blake3"` and `"crc32"`, each behind the cargo feature of the same name.
+    /// The content is streamed through the hash, so files of any size can be hashed.
+    ///
+    /// Throws an exception when:
+    /// - The algorithm is unknown or its feature isn't enabled.
+    /// - The file can't be opened or read.
+    #[rhai_fn(return_raw)]
+    pub fn hash_file(
+        ctx: NativeCallContext,
+        path: PathBuf,
+        algo: &str,
+    ) -> Result<String, Box<EvalAltResult>> {
+        hash_path(&ctx, &resolve(&ctx, &path)?, algo)
+    }
+
+    /// Helper function for `hash_file` that takes a string instead of `PathBuf`.
+    #[rhai_fn(return_raw, name = "hash_file")]
+    pub fn hash_file_str(
+        ctx: NativeCallContext,
+        path_raw: ImmutableString,
+        algo: &str,
+    ) -> Result<String, Box<EvalAltResult>> {
+        let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+        hash_file(ctx, path, algo)
+    }
+
+    /// Hashes the file from the current stream position until EOF and returns the digest as a lowercase hex string.
+    ///
+    /// See `hash_file` for the supported algorithms.
+    ///
+    /// Throws an exception when:
+    /// - The algorithm is unknown or its feature isn't enabled.
+    /// - The read function encounters an I/O error.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn hash(
+        ctx: NativeCallContext,
+        file: &mut SharedFile,
+        algo: &str,
+    ) -> Result<String, Box<EvalAltResult>> {
+        hash_reader(&ctx, &mut *borrow_mut(file), algo)
+    }
+
+    /// Checks the files listed in a `sha256sum` format manifest against their SHA-256 digests.
+    ///
+    /// See `verify_checksum_file(path, algo)` for the returned map.
+    ///
+    /// Throws an exception when:
+    /// - The `sha256` feature isn't enabled.
+    /// - The manifest can't be read or contains a malformed line.
+    #[rhai_fn(return_raw)]
+    pub fn verify_checksum_file(
+        ctx: NativeCallContext,
+        path: PathBuf,
+    ) -> Result<Map, Box<EvalAltResult>> {
+        verify_checksum_file_with_algo(ctx, path, "sha256")
+    }
+
+    /// Helper function for `verify_checksum_file` that takes a string instead of `PathBuf`.
+    #[rhai_fn(return_raw, name = "verify_checksum_file")]
+    pub fn verify_checksum_file_str(
+        ctx: NativeCallContext,
+        path_raw: ImmutableString,
+    ) -> Result<Map, Box<EvalAltResult>> {
+        let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+        verify_checksum_file(ctx, path)
+    }
+
+    /// Checks the files listed in a checksum manifest, in the format written by `sha256sum` and similar tools, against digests of the passed algorithm.
+    ///
+    /// Paths in the manifest are resolved against the directory containing it.
+    /// Returns a map with:
+    /// - `ok`: `true` if every listed file exists and matches.
+    /// - `checked`: the number of files listed.
+    /// - `failed`: the paths, as listed, of the files whose digest doesn't match.
+    /// - `missing`: the paths, as listed, of the files that couldn't be read.
+    ///
+    /// Throws an exception when:
+    /// - The algorithm is unknown or its feature isn't enabled.
+    /// - The manifest can't be read or contains a malformed line.
+    /// - A listed path is absolute or contains `..`, so it could lead outside the manifest's directory.
+    #[rhai_fn(return_raw, name = "verify_checksum_file")]
+    pub fn verify_checksum_file_with_algo(
+        ctx: NativeCallContext,
+        path: PathBuf,
+        algo: &str,
+    ) -> Result<Map, Box<EvalAltResult>> {
+        // Fail early on unsupported algorithms, even for empty manifests.
+        Hasher::new(algo)?;
+
+        let manifest_path = resolve(&ctx, &path)?;
+        let manifest = std::fs::read_to_string(&manifest_path)
+            .map_err(|e| format!("{}: {e}", manifest_path.to_string_lossy()))?;
+        let base = manifest_path.parent().unwrap_or(Path::new(""));
+
+        let mut checked: rhai::INT = 0;
+        let mut failed = Array::new();
+        let mut missing = Array::new();
+        for (idx, line) in manifest.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (expected, listed) = parse_manifest_line(line)
+                .ok_or_else(|| format!("Malformed checksum line {}: {line}", idx + 1))?;
+
+            if Path::new(&listed)
+                .components()
+                .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+            {
+                return Err(format!(
+                    "Checksum line {} lists a path outside the manifest's directory: {listed}",
+                    idx + 1
+                )
+                .into());
+            }
+
+            checked += 1;
+            let path = base.join(&listed);
+            match std::fs::File::open(&path) {
+                Ok(mut file) => {
+                    if hash_reader(&ctx, &mut file, algo)? != expected {
+                        failed.push(listed.into());
+                    }
+                }
+                Err(_) => missing.push(listed.into()),
+            }
+        }
+
+        let mut result = Map::new();
+        result.insert(
+            "ok".into(),
+            (failed.is_empty() && missing.is_empty()).into(),
+        );
+        result.insert("checked".into(), checked.into());
+        result.insert("failed".into(), failed.into());
+        result.insert("missing".into(), missing.into());
+        Ok(result)
+    }
+
+    /// Helper function for `verify_checksum_file` that takes a string instead of `PathBuf`.
+    #[rhai_fn(return_raw, name = "verify_checksum_file")]
+    pub fn verify_checksum_file_with_algo_str(
+        ctx: NativeCallContext,
+        path_raw: ImmutableString,
+        algo: &str,
+    ) -> Result<Map, Box<EvalAltResult>> {
+        let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+        verify_checksum_file_with_algo(ctx, path, algo)
+    }
+}
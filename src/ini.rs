@@ -0,0 +1,459 @@
+#[allow(unused_imports)]
+use rhai::plugin::*;
+use rhai::Map;
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use super::config::resolve;
+use super::file::read_text;
+
+/// A line of an INI file.
+enum IniLine<'a> {
+    /// A `[section]` header.
+    Section {
+        /// The name of the section.
+        name: &'a str,
+        /// The line as it is in the file.
+        raw: &'a str,
+    },
+    /// A `key = value` entry.
+    Entry {
+        /// The key, without surrounding whitespace.
+        key: &'a str,
+        /// The value, without surrounding whitespace and quotes.
+        value: &'a str,
+        /// The line up to where the value starts.
+        prefix: &'a str,
+        /// The line as it is in the file.
+        raw: &'a str,
+    },
+    /// A blank line or a comment.
+    Other(&'a str),
+}
+
+/// Parses a line of an INI file.
+///
+/// Lines starting with `;` or `#` are comments, keys are separated from values by the first `=` or `:`,
+/// and a line without one is a key with an empty value.
+fn parse_ini_line(raw: &str) -> IniLine<'_> {
+    let line = raw.trim();
+    if line.is_empty() || line.starts_with([';', '#']) {
+        return IniLine::Other(raw);
+    }
+    if let Some(name) = line
+        .strip_prefix('[')
+        .and_then(|line| line.strip_suffix(']'))
+    {
+        return IniLine::Section {
+            name: name.trim(),
+            raw,
+        };
+    }
+
+    let (key, prefix, value) = match raw.find(['=', ':']) {
+        Some(sep) => {
+            let value = &raw[sep + 1..];
+            let start = raw.len() - value.trim_start().len();
+            (&raw[..sep], &raw[..start], &raw[start..])
+        }
+        None => (raw, raw, ""),
+    };
+    IniLine::Entry {
+        key: key.trim(),
+        value: unquote(value.trim_end()),
+        prefix,
+        raw,
+    }
+}
+
+/// Strips a pair of matching single or double quotes around the value.
+fn unquote(value: &str) -> &str {
+    ['"', '\'']
+        .into_iter()
+        .find_map(|quote| value.strip_prefix(quote)?.strip_suffix(quote))
+        .unwrap_or(value)
+}
+
+/// Formats an INI entry from the line up to its value, quoting the value when reading it back would otherwise change it.
+fn format_ini_entry(prefix: &str, value: &str) -> String {
+    if value.is_empty() {
+        prefix.trim_end().to_string()
+    } else if value.trim() != value || unquote(value) != value {
+        format!("{prefix}\"{value}\"")
+    } else {
+        format!("{prefix}{value}")
+    }
+}
+
+/// Returns the entries of a section as strings, `()` being an empty value.
+///
+/// Names and keys that would read back differently, or not at all, are rejected.
+fn section_entries(
+    name: &str,
+    section: &Dynamic,
+) -> Result<Vec<(String, String)>, Box<EvalAltResult>> {
+    if name.contains([']', '\n', '\r']) || name.trim() != name {
+        return Err(format!("Invalid INI section name {name:?}").into());
+    }
+    let section = section
+        .read_lock::<Map>()
+        .ok_or_else(|| format!("Section {name} must be a map"))?;
+    section
+        .iter()
+        .map(|(key, value)| {
+            let value = if value.is_unit() {
+                String::new()
+            } else {
+                value.to_string()
+            };
+            // Keys starting like a comment or a section header would read back as one.
+            if key.contains(['\n', '\r', '=', ':'])
+                || key.starts_with([';', '#', '['])
+                || key.trim() != key.as_str()
+                || key.is_empty()
+            {
+                return Err(format!("Invalid INI key {key:?} in section {name}").into());
+            }
+            if value.contains(['\n', '\r']) {
+                return Err(
+                    format!("Value of {key} in section {name} can't span several lines").into(),
+                );
+            }
+            Ok((key.to_string(), value))
+        })
+        .collect()
+}
+
+/// Writes the sections into the lines of an existing INI file, returning the new content.
+///
+/// Entries whose value didn't change keep their line, changed entries keep their key and separator,
+/// and comments and order are kept. Entries and sections missing from `sections` are removed,
+/// new entries are added after the last entry of their section and new sections at the end.
+fn update_ini(existing: &str, sections: &Map, newline: &str) -> Result<String, Box<EvalAltResult>> {
+    let mut output: Vec<String> = Vec::new();
+    let mut written_sections = HashSet::new();
+
+    // Lines before the first header belong to the section with an empty name.
+    let mut blocks: Vec<(&str, Vec<IniLine>)> = vec![("", Vec::new())];
+    for raw in existing.lines() {
+        let line = parse_ini_line(raw);
+        match line {
+            IniLine::Section { name, .. } => blocks.push((name, vec![line])),
+            _ => blocks.last_mut().unwrap().1.push(line),
+        }
+    }
+
+    for (name, lines) in blocks {
+        let Some(section) = sections.get(name) else {
+            // Comments before the first section are kept even when it has no entries.
+            if name.is_empty() {
+                output.extend(lines.iter().filter_map(|line| match line {
+                    IniLine::Other(raw) => Some(raw.to_string()),
+                    _ => None,
+                }));
+            }
+            continue;
+        };
+        // A section repeated in the file was written with its first occurrence.
+        if !written_sections.insert(name) {
+            continue;
+        }
+
+        let entries = section_entries(name, section)?;
+        let mut written = HashSet::new();
+        let mut block = Vec::new();
+        let mut insert_at = 0;
+        for line in &lines {
+            match line {
+                IniLine::Section { raw, .. } => {
+                    block.push(raw.to_string());
+                    insert_at = block.len();
+                }
+                IniLine::Entry {
+                    key,
+                    value,
+                    prefix,
+                    raw,
+                } => {
+                    let Some((_, new)) = entries.iter().find(|(k, _)| k == key) else {
+                        continue;
+                    };
+                    if !written.insert(*key) {
+                        continue;
+                    }
+                    if new == value {
+                        block.push(raw.to_string());
+                    } else if prefix == raw {
+                        block.push(format_ini_entry(&format!("{key} = "), new));
+                    } else {
+                        block.push(format_ini_entry(prefix, new));
+                    }
+                    insert_at = block.len();
+                }
+                IniLine::Other(raw) => block.push(raw.to_string()),
+            }
+        }
+
+        let new_entries = entries
+            .iter()
+            .filter(|(key, _)| !written.contains(key.as_str()))
+            .map(|(key, value)| format_ini_entry(&format!("{key} = "), value));
+        block.splice(insert_at..insert_at, new_entries);
+        output.extend(block);
+    }
+
+    // Sections that aren't in the file yet go at the end, the one without a name first.
+    let mut new_sections: Vec<_> = sections
+        .iter()
+        .filter(|(name, _)| !written_sections.contains(name.as_str()))
+        .collect();
+    new_sections.sort_by_key(|(name, _)| !name.is_empty());
+    for (name, section) in new_sections {
+        let entries = section_entries(name, section)?;
+        if name.is_empty() {
+            let new_entries = entries
+                .iter()
+                .map(|(key, value)| format_ini_entry(&format!("{key} = "), value));
+            output.splice(0..0, new_entries);
+            continue;
+        }
+        if output.last().is_some_and(|line| !line.trim().is_empty()) {
+            output.push(String::new());
+        }
+        output.push(format!("[{name}]"));
+        output.extend(
+            entries
+                .iter()
+                .map(|(key, value)| format_ini_entry(&format!("{key} = "), value)),
+        );
+    }
+
+    let mut text = output.join(newline);
+    if !text.is_empty() {
+        text.push_str(newline);
+    }
+    Ok(text)
+}
+
+/// Parses the content of a `.env` file into its variables, in order.
+fn parse_dotenv(text: &str) -> Result<Vec<(String, String)>, String> {
+    let mut vars = Vec::new();
+    let mut lines = text.lines().enumerate();
+
+    while let Some((idx, line)) = lines.next() {
+        let line_no = idx + 1;
+        let line = line.trim_start();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line
+            .strip_prefix("export")
+            .filter(|rest| rest.starts_with([' ', '\t']))
+            .map_or(line, str::trim_start);
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("line {line_no}: expected KEY=VALUE"))?;
+        let key = key.trim_end();
+        let valid_key = key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'));
+        if !valid_key {
+            return Err(format!("line {line_no}: invalid variable name {key:?}"));
+        }
+
+        let value = value.trim_start();
+        let value = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let mut parsed = String::new();
+                let mut rest = &value[1..];
+                // Quoted values may span several lines.
+                let rest = loop {
+                    if let Some(rest) = parse_quoted(rest, quote, &mut parsed) {
+                        break rest;
+                    }
+                    let (_, next) = lines
+                        .next()
+                        .ok_or_else(|| format!("line {line_no}: unterminated quoted value"))?;
+                    parsed.push('\n');
+                    rest = next;
+                };
+                let rest = rest.trim_start();
+                if !rest.is_empty() && !rest.starts_with('#') {
+                    return Err(format!(
+                        "line {line_no}: unexpected characters after quoted value"
+                    ));
+                }
+                parsed
+            }
+            // A `#` after whitespace starts a comment.
+            _ => match value.find(" #").or_else(|| value.find("\t#")) {
+                Some(comment) => value[..comment].trim_end(),
+                None => value.trim_end(),
+            }
+            .to_string(),
+        };
+        vars.push((key.to_string(), value));
+    }
+
+    Ok(vars)
+}
+
+/// Parses the quoted part of a value up to the closing quote, returning what follows it,
+/// or `None` when the line ends first.
+///
+/// Double quotes support the escapes `\n`, `\r`, `\t`, `\"`, `\\` and `\$`, single quotes take everything literally.
+fn parse_quoted<'a>(text: &'a str, quote: char, parsed: &mut String) -> Option<&'a str> {
+    let mut chars = text.char_indices();
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            c if c == quote => return Some(&text[idx + 1..]),
+            '\\' if quote == '"' => match chars.next() {
+                Some((_, 'n')) => parsed.push('\n'),
+                Some((_, 'r')) => parsed.push('\r'),
+                Some((_, 't')) => parsed.push('\t'),
+                Some((_, c @ ('"' | '\\' | '$'))) => parsed.push(c),
+                Some((_, c)) => {
+                    parsed.push('\\');
+                    parsed.push(c);
+                }
+                None => parsed.push('\\'),
+            },
+            c => parsed.push(c),
+        }
+    }
+    None
+}
+
+/// Formats an error raised while parsing the file at the path.
+fn parse_error(path: &Path, e: impl std::fmt::Display) -> Box<EvalAltResult> {
+    format!("{}: {e}", path.to_string_lossy()).into()
+}
+
+#[export_module]
+pub mod ini_functions {
+    use std::path::PathBuf;
+
+    /// Reads an INI file and returns a map from section names to maps of their entries.
+    ///
+    /// Entries before the first section are in the section named `""`, and values are strings without surrounding whitespace or quotes.
+    /// Lines starting with `;` or `#` are comments, keys are separated from values by `=` or `:`, and a repeated key keeps its last value.
+    /// The whole file is read, so it must not be larger than the engine's `max_string_size`.
+    ///
+    /// Throws an exception when the file can't be read, isn't valid UTF-8 or is too large.
+    #[rhai_fn(return_raw)]
+    pub fn read_ini(ctx: NativeCallContext, path: PathBuf) -> Result<Map, Box<EvalAltResult>> {
+        let text = read_text(&ctx, &resolve(&ctx, &path)?)?;
+        let mut sections = Map::new();
+        let mut current = "";
+        for raw in text.lines() {
+            match parse_ini_line(raw) {
+                IniLine::Section { name, .. } => {
+                    current = name;
+                    sections
+                        .entry(name.into())
+                        .or_insert_with(|| Map::new().into());
+                }
+                IniLine::Entry { key, value, .. } => {
+                    let section = sections
+                        .entry(current.into())
+                        .or_insert_with(|| Map::new().into());
+                    if let Some(mut section) = section.write_lock::<Map>() {
+                        section.insert(key.into(), value.into());
+                    }
+                }
+                IniLine::Other(_) => (),
+            }
+        }
+        Ok(sections)
+    }
+
+    /// Helper function for `read_ini` that takes a string instead of `PathBuf`.
+    #[rhai_fn(return_raw, name = "read_ini")]
+    pub fn read_ini_str(
+        ctx: NativeCallContext,
+        path_raw: ImmutableString,
+    ) -> Result<Map, Box<EvalAltResult>> {
+        let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+        read_ini(ctx, path)
+    }
+
+    /// Writes a map from section names to maps of their entries to an INI file, as returned by `read_ini`.
+    ///
+    /// When the file exists it is updated rather than replaced: comments, blank lines and the order of entries are kept,
+    /// entries whose value didn't change keep their line, and entries and sections missing from the map are removed.
+    /// New entries are added at the end of their section and new sections at the end of the file.
+    /// Values are written as strings, `()` being an empty value, and quoted when they have surrounding whitespace or quotes.
+    ///
+    /// Throws an exception when:
+    /// - A section isn't a map, or its name holds `]` or a line break.
+    /// - A key is empty, starts with `;`, `#` or `[`, or holds `=`, `:` or a line break.
+    /// - A value holds a line break.
+    /// - The file can't be read or written.
+    #[rhai_fn(return_raw)]
+    pub fn write_ini(
+        ctx: NativeCallContext,
+        path: PathBuf,
+        sections: Map,
+    ) -> Result<(), Box<EvalAltResult>> {
+        let path = resolve(&ctx, &path)?;
+        let existing = match path.exists() {
+            true => read_text(&ctx, &path)?,
+            false => String::new(),
+        };
+        let newline = if existing.contains("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        };
+        let text = update_ini(&existing, &sections, newline)?;
+        std::fs::write(&path, text).map_err(|e| parse_error(&path, e))
+    }
+
+    /// Helper function for `write_ini` that takes a string instead of `PathBuf`.
+    #[rhai_fn(return_raw, name = "write_ini")]
+    pub fn write_ini_str(
+        ctx: NativeCallContext,
+        path_raw: ImmutableString,
+        sections: Map,
+    ) -> Result<(), Box<EvalAltResult>> {
+        let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+        write_ini(ctx, path, sections)
+    }
+
+    /// Reads a `.env` file and returns a map of its variables, as strings.
+    ///
+    /// Each line is `KEY=VALUE`, optionally prefixed with `export`. Blank lines and lines starting with `#` are skipped.
+    /// Values may be:
+    /// - Unquoted, without surrounding whitespace and ending at a `#` that follows whitespace.
+    /// - In double quotes, where `\n`, `\r`, `\t`, `\"`, `\\` and `\$` are unescaped.
+    /// - In single quotes, taken literally.
+    ///
+    /// Quoted values may span several lines. Variables aren't expanded, and a repeated variable keeps its last value.
+    ///
+    /// Throws an exception when:
+    /// - The file can't be read, isn't valid UTF-8 or is larger than the engine's `max_string_size`.
+    /// - A line isn't a valid assignment, or a quote isn't closed.
+    #[rhai_fn(return_raw)]
+    pub fn read_dotenv(ctx: NativeCallContext, path: PathBuf) -> Result<Map, Box<EvalAltResult>> {
+        let path = resolve(&ctx, &path)?;
+        let text = read_text(&ctx, &path)?;
+        let vars = parse_dotenv(&text).map_err(|e| parse_error(&path, e))?;
+        Ok(vars
+            .into_iter()
+            .map(|(key, value)| (key.into(), value.into()))
+            .collect())
+    }
+
+    /// Helper function for `read_dotenv` that takes a string instead of `PathBuf`.
+    #[rhai_fn(return_raw, name = "read_dotenv")]
+    pub fn read_dotenv_str(
+        ctx: NativeCallContext,
+        path_raw: ImmutableString,
+    ) -> Result<Map, Box<EvalAltResult>> {
+        let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+        read_dotenv(ctx, path)
+    }
+}
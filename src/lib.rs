@@ -11,17 +11,106 @@
 #![doc = include_str!("../docs/highlight.html")]
 
 use rhai::def_package;
+use rhai::packages::Package;
 use rhai::plugin::*;
 
+/// Archive functions.
+#[cfg(any(feature = "tar", feature = "zip"))]
+pub(crate) mod archive;
+/// Typed binary file functions.
+pub(crate) mod binary;
+/// Compressed file functions.
+#[cfg(any(feature = "gzip", feature = "zstd", feature = "bzip2"))]
+pub(crate) mod compress;
+/// Package configuration.
+pub(crate) mod config;
+/// Structured data file functions.
+#[cfg(any(feature = "json", feature = "toml", feature = "yaml", feature = "csv"))]
+pub(crate) mod data;
+/// File and directory comparison functions.
+pub(crate) mod diff;
+/// Directory functions.
 pub(crate) mod dir;
+/// Text encodings for reading and writing strings.
+pub(crate) mod encoding;
+/// Environment-aware path functions.
+pub(crate) mod env;
+/// File functions.
 pub(crate) mod file;
+/// File content search and replace functions.
+pub(crate) mod grep;
+/// File hashing and checksum functions.
+#[cfg(any(
+    feature = "sha256",
+    feature = "sha1",
+    feature = "md5",
+    feature = "blake3",
+    feature = "crc32"
+))]
+pub(crate) mod hash;
+/// INI and dotenv file functions.
+pub(crate) mod ini;
+/// Lock file functions.
+pub(crate) mod lockfile;
+/// Memory-mapped file functions.
+#[cfg(feature = "mmap")]
+pub(crate) mod mmap;
+/// Path functions.
 pub(crate) mod path;
+/// Filesystem change watching functions.
+#[cfg(feature = "watch")]
+pub(crate) mod watch;
 
 def_package! {
     /// Package for filesystem manipulation operations.
     pub FilesystemPackage(lib) {
         combine_with_exported_module!(lib, "rhai_fs_path", path::path_functions);
         combine_with_exported_module!(lib, "rhai_fs_file", file::file_functions);
+        combine_with_exported_module!(lib, "rhai_fs_binary", binary::binary_functions);
         combine_with_exported_module!(lib, "rhai_fs_dir", dir::dir_functions);
+        combine_with_exported_module!(lib, "rhai_fs_diff", diff::diff_functions);
+        combine_with_exported_module!(lib, "rhai_fs_grep", grep::grep_functions);
+        combine_with_exported_module!(lib, "rhai_fs_ini", ini::ini_functions);
+        combine_with_exported_module!(lib, "rhai_fs_env", env::env_functions);
+        combine_with_exported_module!(lib, "rhai_fs_lockfile", lockfile::lockfile_functions);
+        #[cfg(any(feature = "sha256", feature = "sha1", feature = "md5", feature = "blake3", feature = "crc32"))]
+        combine_with_exported_module!(lib, "rhai_fs_hash", hash::hash_functions);
+        #[cfg(any(feature = "gzip", feature = "zstd", feature = "bzip2"))]
+        combine_with_exported_module!(lib, "rhai_fs_compress", compress::compress_functions);
+        #[cfg(any(feature = "tar", feature = "zip"))]
+        combine_with_exported_module!(lib, "rhai_fs_archive", archive::archive_functions);
+        #[cfg(any(feature = "json", feature = "toml", feature = "yaml", feature = "csv"))]
+        combine_with_exported_module!(lib, "rhai_fs_data", data::data_functions);
+        #[cfg(feature = "mmap")]
+        combine_with_exported_module!(lib, "rhai_fs_mmap", mmap::mmap_functions);
+        #[cfg(feature = "watch")]
+        combine_with_exported_module!(lib, "rhai_fs_watch", watch::watch_functions);
+
+        // Friendly type names that don't depend on the `sync` feature.
+        lib.set_custom_type::<std::path::PathBuf>("Path");
+        lib.set_custom_type::<file::SharedFile>("File");
+        lib.set_custom_type::<lockfile::SharedLockFile>("LockFile");
+        #[cfg(any(feature = "gzip", feature = "zstd", feature = "bzip2"))]
+        lib.set_custom_type::<compress::SharedCompressedFile>("CompressedFile");
+        #[cfg(feature = "mmap")]
+        lib.set_custom_type::<mmap::SharedMmap>("MappedFile");
+        #[cfg(feature = "watch")]
+        lib.set_custom_type::<watch::SharedWatcher>("Watcher");
+
+        config::set_config(lib, FilesystemConfig::default());
+    }
+}
+
+pub use config::{EnvAccess, FilesystemConfig};
+
+impl FilesystemPackage {
+    /// Create a new `FilesystemPackage` using the provided configuration.
+    #[must_use]
+    pub fn with_config(config: FilesystemConfig) -> Self {
+        let mut module = Module::new();
+        <Self as Package>::init(&mut module);
+        config::set_config(&mut module, config);
+        module.build_index();
+        Self(module.into())
     }
 }
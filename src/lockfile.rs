@@ -0,0 +1,196 @@
+#[allow(unused_imports)]
+use rhai::plugin::*;
+use rhai::{Locked, Shared};
+
+use std::fs::{File, TryLockError};
+use std::io::{ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::config::resolve;
+use super::file::{open_options, would_block_error};
+
+/// How many times to retry when the lock file changes underneath us while acquiring it.
+const ACQUIRE_ATTEMPTS: usize = 5;
+
+/// A held lock file, removed when released or dropped.
+#[derive(Debug)]
+pub struct LockFile {
+    /// The path and the locked handle of the lock file, `None` once released.
+    held: Locked<Option<(PathBuf, File)>>,
+}
+
+impl LockFile {
+    /// Returns the path of the lock file, if it is still held.
+    fn path(&self) -> Option<PathBuf> {
+        #[cfg(not(feature = "sync"))]
+        let held = self.held.borrow();
+
+        #[cfg(feature = "sync")]
+        let held = self.held.read().unwrap();
+
+        held.as_ref().map(|(path, _)| path.clone())
+    }
+
+    /// Removes the lock file and then releases the lock on it, returning `false` if it was already released.
+    fn release(&self) -> bool {
+        #[cfg(not(feature = "sync"))]
+        let held = self.held.borrow_mut().take();
+
+        #[cfg(feature = "sync")]
+        let held = self.held.write().unwrap().take();
+
+        match held {
+            Some((path, file)) => {
+                // Remove before unlocking, so a waiting process never takes over a file that's about to go away.
+                let _ = std::fs::remove_file(path);
+                drop(file);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Drop for LockFile {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+/// A lock file shared between Rhai values, released when the last of them is dropped.
+pub type SharedLockFile = Shared<LockFile>;
+
+/// Returns `true` if the open file is still the one found at the path, i.e. it wasn't removed or replaced.
+#[cfg(unix)]
+fn is_same_file(file: &File, path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match (file.metadata(), std::fs::metadata(path)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+/// Returns `true` if the open file is still the one found at the path, i.e. it wasn't removed or replaced.
+///
+/// Open files can't be removed on this platform, so only the path is checked.
+#[cfg(not(unix))]
+fn is_same_file(_file: &File, path: &Path) -> bool {
+    path.exists()
+}
+
+/// Replaces the content of a locked lock file with the PID of this process and the current UNIX timestamp.
+fn write_owner(file: &mut File) -> std::io::Result<()> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    file.set_len(0)?;
+    write!(file, "{}\n{}\n", std::process::id(), timestamp)?;
+    file.flush()
+}
+
+/// Returns the PID recorded in a lock file, if it can be read.
+fn read_owner(file: &mut File) -> Option<u32> {
+    let mut content = String::new();
+    file.read_to_string(&mut content).ok()?;
+    content.lines().next()?.trim().parse().ok()
+}
+
+/// Creates and locks the lock file, taking over stale lock files left behind by processes that have exited.
+///
+/// A lock file's owner holds an exclusive advisory lock on it for as long as it runs, so the OS releases it when the process exits.
+fn acquire(path: PathBuf) -> Result<SharedLockFile, Box<EvalAltResult>> {
+    for _ in 0..ACQUIRE_ATTEMPTS {
+        let mut file = match open_options("wx").open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                match open_options("r+").open(&path) {
+                    Ok(file) => file,
+                    // Released in the meantime, try to create it again.
+                    Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                    Err(e) => return Err(e.to_string().into()),
+                }
+            }
+            Err(e) => return Err(e.to_string().into()),
+        };
+
+        match file.try_lock() {
+            Ok(()) if is_same_file(&file, &path) => {
+                write_owner(&mut file).map_err(|e| e.to_string())?;
+                let held = Locked::new(Some((path, file)));
+                return Ok(Shared::new(LockFile { held }));
+            }
+            // Released and removed before we got the lock, try again.
+            Ok(()) => continue,
+            Err(TryLockError::WouldBlock) => {
+                let owner = read_owner(&mut file)
+                    .map(|pid| format!(" by process {pid}"))
+                    .unwrap_or_default();
+                return Err(would_block_error(&format!(
+                    "Lock file {} is held{owner}",
+                    path.to_string_lossy()
+                )));
+            }
+            Err(TryLockError::Error(e)) => return Err(e.to_string().into()),
+        }
+    }
+
+    Err(would_block_error(&format!(
+        "Lock file {} keeps changing",
+        path.to_string_lossy()
+    )))
+}
+
+#[export_module]
+pub mod lockfile_functions {
+    /// Creates a lock file at the path, guarding against other processes doing the same, and returns a guard for it.
+    ///
+    /// The file is created exclusively (like `open_file(path, "wx")`) and contains the PID of this process and a UNIX timestamp.
+    /// It is removed when `release` is called on the guard or the guard is dropped.
+    /// Lock files left behind by processes that have exited are detected and taken over.
+    ///
+    /// Throws an exception when:
+    /// - The lock file is held by a running process, the thrown value is a map with `kind` set to `"would_block"`.
+    /// - The lock file can't be created, e.g. because the directory doesn't exist.
+    #[rhai_fn(return_raw)]
+    pub fn acquire_lockfile(
+        ctx: NativeCallContext,
+        path: PathBuf,
+    ) -> Result<SharedLockFile, Box<EvalAltResult>> {
+        acquire(resolve(&ctx, &path)?)
+    }
+
+    /// Helper function for `acquire_lockfile` that takes a string instead of `PathBuf`.
+    #[rhai_fn(return_raw, name = "acquire_lockfile")]
+    pub fn acquire_lockfile_str(
+        ctx: NativeCallContext,
+        path_raw: ImmutableString,
+    ) -> Result<SharedLockFile, Box<EvalAltResult>> {
+        let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+        acquire_lockfile(ctx, path)
+    }
+
+    /// Removes the lock file, returning `false` if it was already released.
+    #[rhai_fn(global, pure)]
+    pub fn release(lock: &mut SharedLockFile) -> bool {
+        lock.release()
+    }
+
+    /// Returns `true` while the lock file is held.
+    #[rhai_fn(global, pure, get = "is_held")]
+    pub fn is_held(lock: &mut SharedLockFile) -> bool {
+        lock.path().is_some()
+    }
+
+    /// Returns the path of the lock file.
+    ///
+    /// Throws an exception when:
+    /// - The lock file was released.
+    #[rhai_fn(global, pure, return_raw, get = "path")]
+    pub fn get_path(lock: &mut SharedLockFile) -> Result<PathBuf, Box<EvalAltResult>> {
+        lock.path().ok_or_else(|| "Lock file was released".into())
+    }
+}
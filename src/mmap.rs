@@ -0,0 +1,199 @@
+#[allow(unused_imports)]
+use rhai::plugin::*;
+use rhai::Shared;
+
+use memmap2::Mmap;
+use std::fs::File;
+use std::path::PathBuf;
+
+use super::config::resolve;
+use super::file::convert_to_int;
+
+/// A read-only memory-mapped file shared between Rhai values.
+pub type SharedMmap = Shared<Mmap>;
+
+/// Returns the byte range starting at `start` spanning up to `len` bytes, clamped to the mapping like `Blob::extract`.
+///
+/// A negative `start` counts from the end.
+#[cfg(not(feature = "no_index"))]
+fn clamp_range(map: &Mmap, start: rhai::INT, len: rhai::INT) -> std::ops::Range<usize> {
+    let size = map.len();
+    let start = if start < 0 {
+        size.saturating_sub(start.unsigned_abs() as usize)
+    } else {
+        (start as usize).min(size)
+    };
+    let len = (len.max(0) as usize).min(size - start);
+    start..start + len
+}
+
+#[export_module]
+pub mod mmap_functions {
+    /// Maps the file at the path into memory for reading, without reading it up front.
+    ///
+    /// Only the pages that are accessed are loaded, which makes this suitable for random access to large files.
+    /// The file must not be truncated by another process while it is mapped, the result of reading it is undefined.
+    ///
+    /// Throws an exception when:
+    /// - The file doesn't exist or can't be opened for reading.
+    /// - The file can't be mapped, e.g. because it isn't a regular file.
+    #[rhai_fn(return_raw)]
+    pub fn mmap_file(
+        ctx: NativeCallContext,
+        path: PathBuf,
+    ) -> Result<SharedMmap, Box<EvalAltResult>> {
+        let file = File::open(resolve(&ctx, &path)?).map_err(|e| e.to_string())?;
+        // SAFETY: the mapping is only ever read, and the documentation warns about concurrent truncation.
+        let map = unsafe { Mmap::map(&file) }.map_err(|e| e.to_string())?;
+        Ok(Shared::new(map))
+    }
+
+    /// Helper function for `mmap_file` that takes a string instead of `PathBuf`.
+    #[rhai_fn(return_raw, name = "mmap_file")]
+    pub fn mmap_file_str(
+        ctx: NativeCallContext,
+        path_raw: ImmutableString,
+    ) -> Result<SharedMmap, Box<EvalAltResult>> {
+        let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+        mmap_file(ctx, path)
+    }
+
+    /// Returns the size of the mapped file, in bytes.
+    #[rhai_fn(global, pure, return_raw, name = "len", get = "len")]
+    pub fn len(map: &mut SharedMmap) -> Result<rhai::INT, Box<EvalAltResult>> {
+        convert_to_int(map.len())
+    }
+
+    /// Returns `true` if the mapped file is empty.
+    #[rhai_fn(global, pure, name = "is_empty", get = "is_empty")]
+    pub fn is_empty(map: &mut SharedMmap) -> bool {
+        map.is_empty()
+    }
+
+    /// Returns the byte at the index, a negative index counts from the end.
+    ///
+    /// Throws an exception when:
+    /// - The index is out of bounds.
+    #[rhai_fn(global, pure, return_raw, index_get)]
+    pub fn get(map: &mut SharedMmap, index: rhai::INT) -> Result<rhai::INT, Box<EvalAltResult>> {
+        let idx = if index < 0 {
+            map.len().checked_sub(index.unsigned_abs() as usize)
+        } else {
+            Some(index as usize).filter(|&idx| idx < map.len())
+        };
+        match idx {
+            Some(idx) => Ok(map[idx] as rhai::INT),
+            None => Err(format!("Index {index} is out of bounds for {} bytes", map.len()).into()),
+        }
+    }
+
+    /// Returns `"MappedFile"`.
+    #[rhai_fn(global, pure)]
+    pub fn to_string(_map: &mut SharedMmap) -> String {
+        "MappedFile".into()
+    }
+
+    /// Returns the size of the mapping for debugging.
+    #[rhai_fn(global, pure)]
+    pub fn to_debug(map: &mut SharedMmap) -> String {
+        format!("MappedFile({} bytes)", map.len())
+    }
+
+    #[cfg(not(feature = "no_index"))]
+    pub mod blob_functions {
+        use rhai::Blob;
+        use std::ops::{Range, RangeInclusive};
+
+        /// Copies the bytes starting at `start` spanning up to `len` bytes into a `Blob`, a negative `start` counts from the end.
+        ///
+        /// Out of bounds ranges are clamped to the file like `Blob::extract`.
+        ///
+        /// Throws an exception when:
+        /// - The slice exceeds the engine's `max_array_size`.
+        #[rhai_fn(global, pure, return_raw, name = "extract")]
+        pub fn extract(
+            ctx: NativeCallContext,
+            map: &mut SharedMmap,
+            start: rhai::INT,
+            len: rhai::INT,
+        ) -> Result<Blob, Box<EvalAltResult>> {
+            let range = clamp_range(map, start, len);
+            let max_len = ctx.engine().max_array_size();
+            if max_len > 0 && range.len() > max_len {
+                return Err(format!(
+                    "Slice of {} bytes exceeds the maximum array size",
+                    range.len()
+                )
+                .into());
+            }
+            Ok(map[range].to_vec())
+        }
+
+        /// Copies the bytes in the exclusive range into a `Blob`.
+        ///
+        /// Out of bounds ranges are clamped to the file like `Blob::extract`.
+        ///
+        /// Throws an exception when:
+        /// - The slice exceeds the engine's `max_array_size`.
+        #[rhai_fn(global, pure, return_raw, name = "extract")]
+        pub fn extract_range(
+            ctx: NativeCallContext,
+            map: &mut SharedMmap,
+            range: Range<rhai::INT>,
+        ) -> Result<Blob, Box<EvalAltResult>> {
+            let start = range.start.max(0);
+            extract(ctx, map, start, range.end.saturating_sub(start))
+        }
+
+        /// Copies the bytes in the inclusive range into a `Blob`.
+        ///
+        /// Out of bounds ranges are clamped to the file like `Blob::extract`.
+        ///
+        /// Throws an exception when:
+        /// - The slice exceeds the engine's `max_array_size`.
+        #[rhai_fn(global, pure, return_raw, name = "extract")]
+        pub fn extract_inclusive_range(
+            ctx: NativeCallContext,
+            map: &mut SharedMmap,
+            range: RangeInclusive<rhai::INT>,
+        ) -> Result<Blob, Box<EvalAltResult>> {
+            let start = (*range.start()).max(0);
+            extract(
+                ctx,
+                map,
+                start,
+                range.end().saturating_sub(start).saturating_add(1),
+            )
+        }
+
+        /// Returns the index of the first occurrence of the pattern in the mapped file, or -1 if it isn't found.
+        #[rhai_fn(global, pure, return_raw)]
+        pub fn find(map: &mut SharedMmap, pattern: Blob) -> Result<rhai::INT, Box<EvalAltResult>> {
+            find_from(map, pattern, 0)
+        }
+
+        /// Returns the index of the first occurrence of the pattern at or after `start`, or -1 if it isn't found.
+        ///
+        /// A negative `start` counts from the end.
+        #[rhai_fn(global, pure, return_raw, name = "find")]
+        pub fn find_from(
+            map: &mut SharedMmap,
+            pattern: Blob,
+            start: rhai::INT,
+        ) -> Result<rhai::INT, Box<EvalAltResult>> {
+            let range = clamp_range(map, start, rhai::INT::MAX);
+            let haystack = &map[range.clone()];
+            let found = if pattern.is_empty() {
+                Some(0)
+            } else {
+                haystack
+                    .windows(pattern.len())
+                    .position(|w| w == pattern.as_slice())
+            };
+            match found {
+                Some(idx) => convert_to_int(range.start + idx),
+                None => Ok(-1),
+            }
+        }
+    }
+}
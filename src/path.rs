@@ -1,10 +1,114 @@
 use rhai::plugin::*;
+use rhai::FnPtr;
+
+use std::path::{Component, Path, PathBuf};
+
+use super::config::{resolve, state};
+
+/// Lexically normalizes a path, removing `.` components and resolving `..` against preceding components without touching the filesystem.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                // `..` at the root is the root itself.
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => normalized.push(component),
+            },
+            _ => normalized.push(component),
+        }
+    }
+
+    if normalized.as_os_str().is_empty() {
+        normalized.push(".");
+    }
+
+    normalized
+}
+
+/// Returns the working directory of the package, falling back to the process' current directory.
+fn working_dir(ctx: &NativeCallContext) -> Result<PathBuf, Box<EvalAltResult>> {
+    match state(ctx)?.cwd() {
+        Some(cwd) => Ok(cwd),
+        None => std::env::current_dir().map_err(|e| e.to_string().into()),
+    }
+}
+
+/// Makes a path absolute by joining it onto the working directory, without resolving symbolic links.
+fn absolute_path(ctx: &NativeCallContext, path: &Path) -> Result<PathBuf, Box<EvalAltResult>> {
+    if path.is_absolute() {
+        Ok(normalize_path(path))
+    } else {
+        Ok(normalize_path(&working_dir(ctx)?.join(path)))
+    }
+}
+
+/// Returns the absolute form of a path that must point to a directory, to be used as the working directory.
+fn checked_working_dir(
+    ctx: &NativeCallContext,
+    path: &Path,
+) -> Result<PathBuf, Box<EvalAltResult>> {
+    let cwd = absolute_path(ctx, path)?;
+    if cwd.is_dir() {
+        Ok(cwd)
+    } else {
+        Err(format!("Not a directory: {}", cwd.to_string_lossy()).into())
+    }
+}
+
+/// Computes a lexical path from `base` to `path`, using `..` components to leave `base` where required.
+fn relative_path(
+    ctx: &NativeCallContext,
+    path: &Path,
+    base: &Path,
+) -> Result<PathBuf, Box<EvalAltResult>> {
+    let (path, base) = if path.is_absolute() == base.is_absolute() {
+        (normalize_path(path), normalize_path(base))
+    } else {
+        (absolute_path(ctx, path)?, absolute_path(ctx, base)?)
+    };
+
+    let mut path_iter = path.components().peekable();
+    let mut base_iter = base.components().peekable();
+
+    // Skip the common prefix.
+    while let (Some(a), Some(b)) = (path_iter.peek(), base_iter.peek()) {
+        if a != b {
+            break;
+        }
+        path_iter.next();
+        base_iter.next();
+    }
+
+    let mut relative = PathBuf::new();
+    for component in base_iter {
+        match component {
+            Component::CurDir => {}
+            Component::Normal(_) => relative.push(".."),
+            _ => {
+                return Err(
+                    format!("Cannot compute path relative to {}", base.to_string_lossy()).into(),
+                )
+            }
+        }
+    }
+    relative.extend(path_iter.filter(|c| *c != Component::CurDir));
+
+    if relative.as_os_str().is_empty() {
+        relative.push(".");
+    }
+
+    Ok(relative)
+}
 
 #[export_module]
 #[allow(clippy::ptr_arg)]
 pub mod path_functions {
-    use std::path::{Path, PathBuf};
-
     /// Creates a path from the passed string.
     #[rhai_fn(global)]
     pub fn path(path: &str) -> PathBuf {
@@ -13,24 +117,150 @@ pub mod path_functions {
 
     /// Returns path to current working directory.
     ///
+    /// This is the directory set by `set_cwd`, or the process' current working directory if it was never set.
+    ///
     /// Throws an exception when:
     /// - The current working directory does not exist.
     /// - The process lacks the permissions to access the current working directory.
     #[rhai_fn(return_raw)]
-    pub fn cwd() -> Result<PathBuf, Box<EvalAltResult>> {
-        std::env::current_dir().map_err(|e| e.to_string().into())
+    pub fn cwd(ctx: NativeCallContext) -> Result<PathBuf, Box<EvalAltResult>> {
+        working_dir(&ctx)
+    }
+
+    /// Sets the working directory that relative paths are resolved against, without changing the process' current directory.
+    ///
+    /// Throws an exception when:
+    /// - The path doesn't point to a directory.
+    #[rhai_fn(return_raw)]
+    pub fn set_cwd(ctx: NativeCallContext, path: PathBuf) -> Result<(), Box<EvalAltResult>> {
+        let cwd = checked_working_dir(&ctx, &path)?;
+        state(&ctx)?.replace_cwd(Some(cwd));
+        Ok(())
+    }
+
+    /// Helper function for `set_cwd` that takes a string instead of `PathBuf`.
+    #[rhai_fn(return_raw, name = "set_cwd")]
+    pub fn set_cwd_str(
+        ctx: NativeCallContext,
+        path_raw: ImmutableString,
+    ) -> Result<(), Box<EvalAltResult>> {
+        let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+        set_cwd(ctx, path)
+    }
+
+    /// Calls the function with the working directory set to the path, restoring the previous working directory afterwards.
+    ///
+    /// Returns the result of the function.
+    ///
+    /// Throws an exception when:
+    /// - The path doesn't point to a directory.
+    /// - The function throws an exception.
+    #[rhai_fn(return_raw)]
+    pub fn with_cwd(
+        ctx: NativeCallContext,
+        path: PathBuf,
+        func: FnPtr,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let cwd = checked_working_dir(&ctx, &path)?;
+        let state = state(&ctx)?;
+        let previous = state.replace_cwd(Some(cwd));
+        let result = func.call_within_context(&ctx, ());
+        state.replace_cwd(previous);
+        result
+    }
+
+    /// Helper function for `with_cwd` that takes a string instead of `PathBuf`.
+    #[rhai_fn(return_raw, name = "with_cwd")]
+    pub fn with_cwd_str(
+        ctx: NativeCallContext,
+        path_raw: ImmutableString,
+        func: FnPtr,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+        with_cwd(ctx, path, func)
     }
 
     /// Returns `true` if path points to something in the filesystem (a file or directory) so long as the current process can access it.
-    #[rhai_fn(global, pure, get = "exists")]
-    pub fn exists(path: &mut PathBuf) -> bool {
-        path.exists()
+    #[rhai_fn(global, pure, return_raw, get = "exists")]
+    pub fn exists(ctx: NativeCallContext, path: &mut PathBuf) -> Result<bool, Box<EvalAltResult>> {
+        Ok(resolve(&ctx, path)?.exists())
     }
 
     /// Returns the canonical, absolute form of the path with all intermediate components normalized and symbolic links resolved.
     #[rhai_fn(global, pure, return_raw)]
-    pub fn canonicalize(path: &mut PathBuf) -> Result<PathBuf, Box<EvalAltResult>> {
-        path.canonicalize().map_err(|e| e.to_string().into())
+    pub fn canonicalize(
+        ctx: NativeCallContext,
+        path: &mut PathBuf,
+    ) -> Result<PathBuf, Box<EvalAltResult>> {
+        resolve(&ctx, path)?
+            .canonicalize()
+            .map_err(|e| e.to_string().into())
+    }
+
+    /// Returns the path with all `.` components removed and `..` components resolved lexically.
+    ///
+    /// Unlike `canonicalize`, the path doesn't need to exist and symbolic links are not resolved.
+    #[rhai_fn(global, pure)]
+    pub fn normalize(path: &mut PathBuf) -> PathBuf {
+        normalize_path(path)
+    }
+
+    /// Returns the path joined onto the current working directory and normalized, without resolving symbolic links.
+    ///
+    /// Throws an exception when:
+    /// - The path is relative and the current working directory cannot be accessed.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn absolute(
+        ctx: NativeCallContext,
+        path: &mut PathBuf,
+    ) -> Result<PathBuf, Box<EvalAltResult>> {
+        absolute_path(&ctx, path)
+    }
+
+    /// Returns the path with the `base` prefix removed.
+    ///
+    /// Throws an exception when:
+    /// - `base` is not a prefix of the path.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn strip_prefix(path: &mut PathBuf, base: PathBuf) -> Result<PathBuf, Box<EvalAltResult>> {
+        path.strip_prefix(base)
+            .map(Path::to_path_buf)
+            .map_err(|e| e.to_string().into())
+    }
+
+    /// Helper function for `strip_prefix` that takes a string instead of `PathBuf`.
+    #[rhai_fn(global, pure, return_raw, name = "strip_prefix")]
+    pub fn strip_prefix_string(
+        path: &mut PathBuf,
+        base: &str,
+    ) -> Result<PathBuf, Box<EvalAltResult>> {
+        strip_prefix(path, PathBuf::from(base))
+    }
+
+    /// Returns a relative path that leads from `base` to the path, e.g. `path("a/c").relative_to("a/b")` is `../c`.
+    ///
+    /// Both paths are normalized lexically first. When only one of them is absolute, the other is joined onto the current working directory.
+    ///
+    /// Throws an exception when:
+    /// - `base` contains `..` components that can't be resolved lexically.
+    /// - The current working directory is needed but cannot be accessed.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn relative_to(
+        ctx: NativeCallContext,
+        path: &mut PathBuf,
+        base: PathBuf,
+    ) -> Result<PathBuf, Box<EvalAltResult>> {
+        relative_path(&ctx, path, &base)
+    }
+
+    /// Helper function for `relative_to` that takes a string instead of `PathBuf`.
+    #[rhai_fn(global, pure, return_raw, name = "relative_to")]
+    pub fn relative_to_string(
+        ctx: NativeCallContext,
+        path: &mut PathBuf,
+        base: &str,
+    ) -> Result<PathBuf, Box<EvalAltResult>> {
+        relative_path(&ctx, path, Path::new(base))
     }
 
     /// Returns true if the Path is absolute, i.e., if it is independent of the current directory.
@@ -40,15 +270,15 @@ pub mod path_functions {
     }
 
     /// Returns true if the path exists on disk and is pointing at a directory.
-    #[rhai_fn(global, pure, get = "is_dir")]
-    pub fn is_dir(path: &mut PathBuf) -> bool {
-        path.is_dir()
+    #[rhai_fn(global, pure, return_raw, get = "is_dir")]
+    pub fn is_dir(ctx: NativeCallContext, path: &mut PathBuf) -> Result<bool, Box<EvalAltResult>> {
+        Ok(resolve(&ctx, path)?.is_dir())
     }
 
     /// Returns true if the path exists on disk and is pointing at a regular file.
-    #[rhai_fn(global, pure, get = "is_file")]
-    pub fn is_file(path: &mut PathBuf) -> bool {
-        path.is_file()
+    #[rhai_fn(global, pure, return_raw, get = "is_file")]
+    pub fn is_file(ctx: NativeCallContext, path: &mut PathBuf) -> Result<bool, Box<EvalAltResult>> {
+        Ok(resolve(&ctx, path)?.is_file())
     }
 
     /// Returns true if the Path is relative, i.e., not absolute.
@@ -58,28 +288,170 @@ pub mod path_functions {
     }
 
     /// Returns true if the Path is relative, i.e., not absolute.
-    #[rhai_fn(global, pure, get = "is_symlink")]
-    pub fn is_symlink(path: &mut PathBuf) -> bool {
-        path.is_symlink()
+    #[rhai_fn(global, pure, return_raw, get = "is_symlink")]
+    pub fn is_symlink(
+        ctx: NativeCallContext,
+        path: &mut PathBuf,
+    ) -> Result<bool, Box<EvalAltResult>> {
+        Ok(resolve(&ctx, path)?.is_symlink())
     }
 
+    /// Joins two paths together.
     #[rhai_fn(global, name = "+", pure)]
     pub fn add(path1: &mut PathBuf, path2: PathBuf) -> PathBuf {
         path1.join(path2)
     }
 
+    /// Joins a string onto the end of a path.
     #[rhai_fn(global, name = "+", pure)]
     pub fn add_string(path: &mut PathBuf, str: &str) -> PathBuf {
         path.join(Path::new(str))
     }
 
+    /// Extends the path in place with another path.
     #[rhai_fn(global, name = "+=", name = "append", name = "push")]
     pub fn append(path1: &mut PathBuf, path2: PathBuf) {
         path1.push(path2);
     }
 
-    #[rhai_fn(global, name = "to_string", name = "to_debug", pure)]
+    /// Returns `true` if both paths are equal, comparing component-wise.
+    #[rhai_fn(global, name = "==", pure)]
+    pub fn eq(path1: &mut PathBuf, path2: PathBuf) -> bool {
+        *path1 == path2
+    }
+
+    /// Returns `true` if the path is equal to the path described by the string, comparing component-wise.
+    #[rhai_fn(global, name = "==", pure)]
+    pub fn eq_string(path: &mut PathBuf, str: &str) -> bool {
+        path.as_path() == Path::new(str)
+    }
+
+    /// Returns `true` if the paths are not equal, comparing component-wise.
+    #[rhai_fn(global, name = "!=", pure)]
+    pub fn ne(path1: &mut PathBuf, path2: PathBuf) -> bool {
+        *path1 != path2
+    }
+
+    /// Returns `true` if the path is not equal to the path described by the string, comparing component-wise.
+    #[rhai_fn(global, name = "!=", pure)]
+    pub fn ne_string(path: &mut PathBuf, str: &str) -> bool {
+        path.as_path() != Path::new(str)
+    }
+
+    /// Returns `true` if the first path sorts before the second.
+    ///
+    /// Can be used to sort an array of paths: `paths.sort(|a, b| a < b)`.
+    #[rhai_fn(global, name = "<", pure)]
+    pub fn lt(path1: &mut PathBuf, path2: PathBuf) -> bool {
+        *path1 < path2
+    }
+
+    /// Returns `true` if the path sorts before the path described by the string.
+    #[rhai_fn(global, name = "<", pure)]
+    pub fn lt_string(path: &mut PathBuf, str: &str) -> bool {
+        path.as_path() < Path::new(str)
+    }
+
+    /// Returns `true` if the first path sorts before or is equal to the second.
+    #[rhai_fn(global, name = "<=", pure)]
+    pub fn le(path1: &mut PathBuf, path2: PathBuf) -> bool {
+        *path1 <= path2
+    }
+
+    /// Returns `true` if the path sorts before or is equal to the path described by the string.
+    #[rhai_fn(global, name = "<=", pure)]
+    pub fn le_string(path: &mut PathBuf, str: &str) -> bool {
+        path.as_path() <= Path::new(str)
+    }
+
+    /// Returns `true` if the first path sorts after the second.
+    #[rhai_fn(global, name = ">", pure)]
+    pub fn gt(path1: &mut PathBuf, path2: PathBuf) -> bool {
+        *path1 > path2
+    }
+
+    /// Returns `true` if the path sorts after the path described by the string.
+    #[rhai_fn(global, name = ">", pure)]
+    pub fn gt_string(path: &mut PathBuf, str: &str) -> bool {
+        path.as_path() > Path::new(str)
+    }
+
+    /// Returns `true` if the first path sorts after or is equal to the second.
+    #[rhai_fn(global, name = ">=", pure)]
+    pub fn ge(path1: &mut PathBuf, path2: PathBuf) -> bool {
+        *path1 >= path2
+    }
+
+    /// Returns `true` if the path sorts after or is equal to the path described by the string.
+    #[rhai_fn(global, name = ">=", pure)]
+    pub fn ge_string(path: &mut PathBuf, str: &str) -> bool {
+        path.as_path() >= Path::new(str)
+    }
+
+    /// Returns `true` if `base` is a prefix of the path, only considering whole path components.
+    #[rhai_fn(global, pure)]
+    pub fn starts_with(path: &mut PathBuf, base: PathBuf) -> bool {
+        path.starts_with(base)
+    }
+
+    /// Helper function for `starts_with` that takes a string instead of `PathBuf`.
+    #[rhai_fn(global, pure, name = "starts_with")]
+    pub fn starts_with_string(path: &mut PathBuf, base: &str) -> bool {
+        path.starts_with(base)
+    }
+
+    /// Returns `true` if `child` is a suffix of the path, only considering whole path components.
+    #[rhai_fn(global, pure)]
+    pub fn ends_with(path: &mut PathBuf, child: PathBuf) -> bool {
+        path.ends_with(child)
+    }
+
+    /// Helper function for `ends_with` that takes a string instead of `PathBuf`.
+    #[rhai_fn(global, pure, name = "ends_with")]
+    pub fn ends_with_string(path: &mut PathBuf, child: &str) -> bool {
+        path.ends_with(child)
+    }
+
+    /// Returns the path as a string, replacing any non-UTF-8 sequences with `U+FFFD REPLACEMENT CHARACTER`.
+    #[rhai_fn(global, name = "to_string", name = "to_string_lossy", pure)]
     pub fn to_string(path: &mut PathBuf) -> String {
-        path.to_str().unwrap_or_default().into()
+        path.to_string_lossy().into()
+    }
+
+    /// Returns the quoted debug representation of the path, with any non-UTF-8 bytes escaped.
+    #[rhai_fn(global, name = "to_debug", pure)]
+    pub fn to_debug(path: &mut PathBuf) -> String {
+        format!("{path:?}")
+    }
+
+    #[cfg(not(feature = "no_index"))]
+    pub mod blob_functions {
+        use rhai::Blob;
+
+        /// Returns the raw bytes of the path as a `Blob`.
+        ///
+        /// On Unix these are the bytes the OS uses for the path, on other platforms they are the path's internal encoding.
+        #[rhai_fn(global, pure)]
+        pub fn to_bytes(path: &mut PathBuf) -> Blob {
+            path.as_os_str().as_encoded_bytes().to_vec()
+        }
+
+        /// Creates a path from the raw bytes in the `Blob`, the reverse of `to_bytes`.
+        ///
+        /// Throws an exception when:
+        /// - The bytes aren't valid UTF-8 on a platform where paths aren't arbitrary bytes (i.e. not Unix).
+        #[rhai_fn(return_raw)]
+        pub fn path_from_bytes(bytes: Blob) -> Result<PathBuf, Box<EvalAltResult>> {
+            #[cfg(unix)]
+            {
+                use std::os::unix::ffi::OsStringExt;
+                Ok(std::ffi::OsString::from_vec(bytes).into())
+            }
+
+            #[cfg(not(unix))]
+            String::from_utf8(bytes)
+                .map(PathBuf::from)
+                .map_err(|e| e.to_string().into())
+        }
     }
 }
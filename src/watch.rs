@@ -0,0 +1,322 @@
+#[allow(unused_imports)]
+use rhai::plugin::*;
+use rhai::{Dynamic, Map, Shared};
+
+use notify::event::{EventKind, ModifyKind, RenameMode};
+use notify::{Config, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher as _};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::config::{resolve, tick};
+
+/// How often a waiting `poll` gives the engine's progress callback a chance to terminate it.
+const TICK_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Default quiet period that ends a batch of events, in milliseconds.
+const DEFAULT_DEBOUNCE_MS: rhai::INT = 50;
+
+/// Default interval between scans of the polling backend, in milliseconds.
+const DEFAULT_POLL_INTERVAL_MS: rhai::INT = 500;
+
+/// Events reported by the backend, or the errors it ran into.
+type EventReceiver = Receiver<notify::Result<notify::Event>>;
+
+/// A watch on a path, delivering change events until it's dropped.
+pub struct Watcher {
+    /// The backend, kept alive for as long as the watch should last.
+    _backend: Mutex<Box<dyn notify::Watcher + Send>>,
+    /// Name of the backend, `"native"` or `"poll"`.
+    backend_name: &'static str,
+    /// Events reported by the backend.
+    events: Mutex<EventReceiver>,
+    /// Quiet period that ends a batch of events.
+    debounce: Duration,
+}
+
+/// A watcher shared between Rhai values.
+pub type SharedWatcher = Shared<Watcher>;
+
+/// Options accepted by `watch`.
+struct WatchOptions {
+    /// Whether subdirectories are watched too.
+    recursive: bool,
+    /// Quiet period that ends a batch of events.
+    debounce: Duration,
+    /// Whether to use the polling backend even when a native one is available.
+    poll: bool,
+    /// Interval between scans of the polling backend.
+    poll_interval: Duration,
+}
+
+impl WatchOptions {
+    /// Reads the options from a script map, throwing on unknown keys and values of the wrong type.
+    fn from_map(map: &Map) -> Result<Self, Box<EvalAltResult>> {
+        let mut options = WatchOptions {
+            recursive: false,
+            debounce: millis(DEFAULT_DEBOUNCE_MS),
+            poll: false,
+            poll_interval: millis(DEFAULT_POLL_INTERVAL_MS),
+        };
+
+        for (key, value) in map {
+            let invalid = |kind: &str| format!("Watch option {key} must be {kind}");
+            match key.as_str() {
+                "recursive" => {
+                    options.recursive = value.as_bool().map_err(|_| invalid("a bool"))?
+                }
+                "poll" => options.poll = value.as_bool().map_err(|_| invalid("a bool"))?,
+                "debounce" => {
+                    options.debounce = millis(value.as_int().map_err(|_| invalid("an integer"))?)
+                }
+                "poll_interval" => {
+                    options.poll_interval =
+                        millis(value.as_int().map_err(|_| invalid("an integer"))?)
+                }
+                _ => return Err(format!("Unknown watch option {key}").into()),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// Converts a number of milliseconds into a duration, treating negative numbers as zero.
+fn millis(ms: rhai::INT) -> Duration {
+    Duration::from_millis(ms.max(0) as u64)
+}
+
+/// Starts watching the path with the native backend, or the polling backend if that isn't available or was asked for.
+fn start(path: PathBuf, options: WatchOptions) -> Result<SharedWatcher, Box<EvalAltResult>> {
+    let mode = if options.recursive {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    };
+
+    if !options.poll {
+        let (tx, rx) = mpsc::channel();
+        let native = RecommendedWatcher::new(tx, Config::default())
+            .and_then(|mut backend| backend.watch(&path, mode).map(|()| backend));
+        match native {
+            Ok(backend) => return Ok(Watcher::shared(Box::new(backend), "native", rx, &options)),
+            // Only fall back when the native backend itself is the problem, e.g. when the inotify watch limit is reached.
+            Err(e) if matches!(e.kind, notify::ErrorKind::PathNotFound) => {
+                return Err(format!("{}: {e}", path.to_string_lossy()).into())
+            }
+            Err(_) => (),
+        }
+    }
+
+    let (tx, rx) = mpsc::channel();
+    let config = Config::default().with_poll_interval(options.poll_interval);
+    let mut backend = PollWatcher::new(tx, config).map_err(|e| e.to_string())?;
+    backend
+        .watch(&path, mode)
+        .map_err(|e| format!("{}: {e}", path.to_string_lossy()))?;
+    Ok(Watcher::shared(Box::new(backend), "poll", rx, &options))
+}
+
+impl Watcher {
+    /// Wraps a running backend into a shared watcher.
+    fn shared(
+        backend: Box<dyn notify::Watcher + Send>,
+        backend_name: &'static str,
+        events: EventReceiver,
+        options: &WatchOptions,
+    ) -> SharedWatcher {
+        Shared::new(Watcher {
+            _backend: Mutex::new(backend),
+            backend_name,
+            events: Mutex::new(events),
+            debounce: options.debounce,
+        })
+    }
+}
+
+/// A change reported to scripts.
+#[derive(PartialEq)]
+struct Change {
+    /// One of `"created"`, `"modified"`, `"removed"` or `"renamed"`.
+    kind: &'static str,
+    /// The affected path.
+    path: PathBuf,
+    /// The previous path of a rename, when known.
+    from: Option<PathBuf>,
+}
+
+impl Change {
+    /// Converts the change into the map handed to scripts.
+    fn into_map(self) -> Map {
+        let mut map = Map::new();
+        map.insert("kind".into(), self.kind.into());
+        map.insert("path".into(), Dynamic::from(self.path));
+        if let Some(from) = self.from {
+            map.insert("from".into(), Dynamic::from(from));
+        }
+        map
+    }
+}
+
+/// Appends the changes of a backend event to the batch, skipping those already in it, and returns whether any were added.
+///
+/// Access events and others that don't change anything are dropped.
+fn push_changes(batch: &mut Vec<Change>, event: notify::Event) -> bool {
+    let kind = match event.kind {
+        EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(RenameMode::To)) => "created",
+        EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(RenameMode::From)) => "removed",
+        EventKind::Modify(ModifyKind::Name(_)) => "renamed",
+        EventKind::Modify(_) => "modified",
+        EventKind::Access(_) | EventKind::Any | EventKind::Other => return false,
+    };
+
+    let changes: Vec<Change> = match event.kind {
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+            let [from, path]: [PathBuf; 2] = event.paths.try_into().unwrap_or_default();
+            vec![Change {
+                kind,
+                path,
+                from: Some(from),
+            }]
+        }
+        _ => event
+            .paths
+            .into_iter()
+            .map(|path| Change {
+                kind,
+                path,
+                from: None,
+            })
+            .collect(),
+    };
+
+    let len = batch.len();
+    for change in changes {
+        if !batch.contains(&change) {
+            batch.push(change);
+        }
+    }
+    batch.len() > len
+}
+
+#[export_module]
+pub mod watch_functions {
+    use rhai::Array;
+
+    /// Starts watching the file or directory at the path for changes, with the default options.
+    ///
+    /// See `watch(path, options)` for details.
+    #[rhai_fn(return_raw)]
+    pub fn watch(
+        ctx: NativeCallContext,
+        path: PathBuf,
+    ) -> Result<SharedWatcher, Box<EvalAltResult>> {
+        watch_with_options(ctx, path, Map::new())
+    }
+
+    /// Helper function for `watch` that takes a string instead of `PathBuf`.
+    #[rhai_fn(return_raw, name = "watch")]
+    pub fn watch_str(
+        ctx: NativeCallContext,
+        path_raw: ImmutableString,
+    ) -> Result<SharedWatcher, Box<EvalAltResult>> {
+        let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+        watch(ctx, path)
+    }
+
+    /// Starts watching the file or directory at the path for changes, returning a watcher to `poll` for events.
+    ///
+    /// Changes are reported by the OS where possible (inotify on Linux), with a fallback to periodically scanning the path.
+    /// The watch lasts until the watcher is dropped.
+    ///
+    /// Options:
+    /// - `recursive`: also watch subdirectories, defaults to `false`.
+    /// - `debounce`: milliseconds without new events that end a batch returned by `poll`, defaults to `50`.
+    /// - `poll`: always use the scanning fallback, defaults to `false`.
+    /// - `poll_interval`: milliseconds between scans of the fallback, defaults to `500`.
+    ///
+    /// Throws an exception when:
+    /// - The path doesn't exist.
+    /// - An option is unknown or has the wrong type.
+    #[rhai_fn(return_raw, name = "watch")]
+    pub fn watch_with_options(
+        ctx: NativeCallContext,
+        path: PathBuf,
+        options: Map,
+    ) -> Result<SharedWatcher, Box<EvalAltResult>> {
+        let options = WatchOptions::from_map(&options)?;
+        start(resolve(&ctx, &path)?, options)
+    }
+
+    /// Helper function for `watch` that takes a string instead of `PathBuf`.
+    #[rhai_fn(return_raw, name = "watch")]
+    pub fn watch_with_options_str(
+        ctx: NativeCallContext,
+        path_raw: ImmutableString,
+        options: Map,
+    ) -> Result<SharedWatcher, Box<EvalAltResult>> {
+        let path = ctx.call_native_fn::<PathBuf>("path", (path_raw,))?;
+        watch_with_options(ctx, path, options)
+    }
+
+    /// Waits up to `timeout` milliseconds for changes and returns them as an array of event maps, empty if nothing changed.
+    ///
+    /// Once a change arrives, events are collected until none arrive for the `debounce` interval or the timeout elapses, and repeated events are merged.
+    /// Each event has a `kind` of `"created"`, `"modified"`, `"removed"` or `"renamed"` and the affected `path`.
+    /// Renames seen from both sides also carry the previous path as `from`, otherwise they may be reported as a removal and a creation.
+    ///
+    /// The wait can be interrupted by the engine's progress callback.
+    ///
+    /// Throws an exception when:
+    /// - The backend reports an error, e.g. because the watched path was removed.
+    /// - The progress callback terminates the script.
+    #[rhai_fn(global, pure, return_raw)]
+    pub fn poll(
+        ctx: NativeCallContext,
+        watcher: &mut SharedWatcher,
+        timeout: rhai::INT,
+    ) -> Result<Array, Box<EvalAltResult>> {
+        let events = watcher.events.lock().unwrap();
+        let mut batch = Vec::new();
+        let timeout_deadline = Instant::now() + millis(timeout);
+        let mut deadline = timeout_deadline;
+
+        loop {
+            tick(&ctx)?;
+
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            match events.recv_timeout((deadline - now).min(TICK_INTERVAL)) {
+                // Keep collecting until things quiet down, but no longer than the timeout.
+                Ok(Ok(event)) => {
+                    if push_changes(&mut batch, event) {
+                        deadline = (Instant::now() + watcher.debounce).min(timeout_deadline);
+                    }
+                }
+                Ok(Err(e)) => return Err(e.to_string().into()),
+                Err(RecvTimeoutError::Timeout) => (),
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+
+        Ok(batch
+            .into_iter()
+            .map(|change| change.into_map().into())
+            .collect())
+    }
+
+    /// Returns the backend used by the watcher, `"native"` for OS notifications or `"poll"` for the scanning fallback.
+    #[rhai_fn(global, pure, get = "backend")]
+    pub fn backend(watcher: &mut SharedWatcher) -> String {
+        watcher.backend_name.into()
+    }
+
+    /// Returns `"Watcher"`.
+    #[rhai_fn(global, pure)]
+    pub fn to_string(_watcher: &mut SharedWatcher) -> String {
+        "Watcher".into()
+    }
+}
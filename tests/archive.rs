@@ -0,0 +1,390 @@
+#![cfg(any(feature = "tar", feature = "zip"))]
+
+use std::path::Path;
+
+use rhai::{EvalAltResult, Scope};
+
+mod common;
+use common::engine_in;
+
+/// A few files to archive.
+const FILES: &[(&str, &[u8])] = &[
+    ("src/a.txt", b"alpha"),
+    ("src/sub/b.txt", b"beta"),
+    ("top.txt", b"top"),
+];
+
+/// Formats enabled in this build, with the extension to use.
+const FORMATS: &[(&str, &str)] = &[
+    #[cfg(feature = "tar")]
+    ("tar", "tar"),
+    #[cfg(all(feature = "tar", feature = "gzip"))]
+    ("tar.gz", "tgz"),
+    #[cfg(feature = "zip")]
+    ("zip", "zip"),
+];
+
+/// Writes a tar archive with one file entry whose name is written as is, bypassing the builder's checks.
+#[cfg(feature = "tar")]
+fn write_raw_tar(path: &Path, entries: &[(&str, tar::EntryType, &str)]) {
+    let mut builder = tar::Builder::new(std::fs::File::create(path).unwrap());
+    for &(name, kind, content) in entries {
+        let mut header = tar::Header::new_gnu();
+        header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+        header.set_entry_type(kind);
+        header.set_mode(0o644);
+        if kind == tar::EntryType::Symlink {
+            header.as_old_mut().linkname[..content.len()].copy_from_slice(content.as_bytes());
+            header.set_size(0);
+            header.set_cksum();
+            builder.append(&header, std::io::empty()).unwrap();
+        } else {
+            header.set_size(content.len() as u64);
+            header.set_cksum();
+            builder.append(&header, content.as_bytes()).unwrap();
+        }
+    }
+    builder.finish().unwrap();
+}
+
+#[test]
+fn test_archive_round_trip() -> Result<(), Box<EvalAltResult>> {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = engine_in(&dir, FILES);
+    #[cfg(unix)]
+    std::os::unix::fs::symlink("a.txt", dir.path().join("src").join("link")).unwrap();
+    let links = if cfg!(unix) { 1 } else { 0 };
+
+    for &(format, ext) in FORMATS {
+        let mut scope = Scope::new();
+        scope.push_constant("FORMAT", format);
+        scope.push_constant("ARCHIVE", format!("out.{ext}"));
+        scope.push_constant("DEST", format!("dest-{ext}"));
+
+        // src, src/a.txt, src/link, src/sub, src/sub/b.txt and top.txt.
+        assert_eq!(
+            engine.eval_with_scope::<rhai::INT>(
+                &mut scope,
+                r#"archive_create(ARCHIVE, ["src", path("top.txt")], FORMAT)"#
+            )?,
+            5 + links
+        );
+
+        let listed: Vec<String> = engine
+            .eval_with_scope::<rhai::Array>(&mut scope, r#"archive_list(ARCHIVE)"#)?
+            .into_iter()
+            .map(|entry| {
+                let entry = entry.cast::<rhai::Map>();
+                let kind = entry["kind"].clone().into_string().unwrap();
+                let path = entry["path"].clone().into_string().unwrap();
+                format!("{kind}:{}", path.trim_end_matches('/'))
+            })
+            .filter(|entry| entry != "symlink:src/link")
+            .collect();
+        assert_eq!(
+            listed,
+            [
+                "dir:src",
+                "file:src/a.txt",
+                "dir:src/sub",
+                "file:src/sub/b.txt",
+                "file:top.txt"
+            ],
+            "{format}"
+        );
+
+        let extracted = engine
+            .eval_with_scope::<rhai::Array>(&mut scope, r#"archive_extract(ARCHIVE, DEST)"#)?;
+        assert_eq!(extracted.len() as rhai::INT, 5 + links);
+        let dest = dir.path().join(format!("dest-{ext}"));
+        assert_eq!(
+            std::fs::read_to_string(dest.join("src/sub/b.txt")).unwrap(),
+            "beta"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dest.join("top.txt")).unwrap(),
+            "top"
+        );
+        #[cfg(unix)]
+        assert_eq!(
+            std::fs::read_to_string(dest.join("src/link")).unwrap(),
+            "alpha"
+        );
+
+        // Existing files are only replaced when asked to.
+        assert!(engine
+            .run_with_scope(&mut scope, r#"archive_extract(ARCHIVE, DEST)"#)
+            .is_err());
+        engine.run_with_scope(
+            &mut scope,
+            r#"archive_extract(ARCHIVE, DEST, #{overwrite: true})"#,
+        )?;
+
+        // Stripping removes leading components and skips what's left empty.
+        assert_eq!(
+            engine.eval_with_scope::<rhai::INT>(
+                &mut scope,
+                r#"archive_extract(ARCHIVE, DEST + "-strip", #{strip_components: 1}).len()"#
+            )?,
+            3 + links
+        );
+        let stripped = dir.path().join(format!("dest-{ext}-strip"));
+        assert_eq!(
+            std::fs::read_to_string(stripped.join("sub/b.txt")).unwrap(),
+            "beta"
+        );
+        assert!(!stripped.join("top.txt").exists());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_archive_current_dir() -> Result<(), Box<EvalAltResult>> {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = engine_in(&dir, FILES);
+    let (format, ext) = FORMATS[0];
+    let mut scope = Scope::new();
+    scope.push_constant("FORMAT", format);
+    scope.push_constant("ARCHIVE", format!("all.{ext}"));
+
+    // The archive being written isn't added to itself, neither its temporary file nor the one it replaces.
+    let listed = engine.eval_with_scope::<rhai::Array>(
+        &mut scope,
+        r#"
+            archive_create(ARCHIVE, ["."], FORMAT);
+            archive_create(ARCHIVE, ["."], FORMAT);
+            archive_list(ARCHIVE).map(|e| e.path)
+        "#,
+    )?;
+    let listed: Vec<String> = listed
+        .into_iter()
+        .map(|p| p.into_string().unwrap())
+        .collect();
+    assert!(listed.iter().any(|p| p == "top.txt"));
+    assert!(listed.iter().all(|p| !p.contains("all.")));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "tar")]
+fn test_archive_traversal() {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = engine_in(&dir, FILES);
+    let outside = dir.path().join("outside");
+    std::fs::create_dir(&outside).unwrap();
+
+    let malicious: &[&[(&str, tar::EntryType, &str)]] = &[
+        &[
+            ("fine.txt", tar::EntryType::Regular, "ok"),
+            ("../outside/evil.txt", tar::EntryType::Regular, "evil"),
+        ],
+        &[("/tmp/evil.txt", tar::EntryType::Regular, "evil")],
+        &[("a/../../outside/evil.txt", tar::EntryType::Regular, "evil")],
+        &[
+            ("link", tar::EntryType::Symlink, "../outside"),
+            ("link/evil.txt", tar::EntryType::Regular, "evil"),
+        ],
+        &[("deep/link", tar::EntryType::Symlink, "/etc")],
+        &[("hard", tar::EntryType::Link, "../outside/x")],
+    ];
+
+    for (idx, entries) in malicious.iter().enumerate() {
+        let archive = dir.path().join(format!("evil{idx}.tar"));
+        write_raw_tar(&archive, entries);
+        let mut scope = Scope::new();
+        scope.push_constant("ARCHIVE", archive.to_string_lossy().into_owned());
+        scope.push_constant("DEST", format!("dest{idx}"));
+
+        assert!(
+            engine
+                .run_with_scope(&mut scope, r#"archive_extract(ARCHIVE, DEST)"#)
+                .is_err(),
+            "{entries:?}"
+        );
+        // Nothing at all is extracted from a malicious archive.
+        assert!(
+            !dir.path().join(format!("dest{idx}")).exists(),
+            "{entries:?}"
+        );
+        assert_eq!(std::fs::read_dir(&outside).unwrap().count(), 0);
+    }
+
+    // Links that stay inside the destination are fine.
+    let archive = dir.path().join("inside.tar");
+    write_raw_tar(
+        &archive,
+        &[
+            ("dir/file.txt", tar::EntryType::Regular, "content"),
+            ("dir/link", tar::EntryType::Symlink, "../dir/./file.txt"),
+        ],
+    );
+    assert!(engine
+        .run(r#"archive_extract("inside.tar", "inside")"#)
+        .is_ok());
+}
+
+#[test]
+#[cfg(all(feature = "tar", unix))]
+fn test_archive_existing_symlink() {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = engine_in(&dir, FILES);
+    let outside = dir.path().join("outside");
+    std::fs::create_dir(&outside).unwrap();
+
+    // A symlink already in the destination isn't written through when it leads outside.
+    std::fs::create_dir(dir.path().join("dest")).unwrap();
+    std::os::unix::fs::symlink(&outside, dir.path().join("dest").join("escape")).unwrap();
+    write_raw_tar(
+        &dir.path().join("plain.tar"),
+        &[("escape/evil.txt", tar::EntryType::Regular, "evil")],
+    );
+    assert!(engine
+        .run(r#"archive_extract("plain.tar", "dest")"#)
+        .is_err());
+    assert_eq!(std::fs::read_dir(&outside).unwrap().count(), 0);
+
+    // Overwriting replaces a symlink rather than the file it points to.
+    std::fs::write(outside.join("target.txt"), "keep").unwrap();
+    std::os::unix::fs::symlink(
+        outside.join("target.txt"),
+        dir.path().join("dest").join("f.txt"),
+    )
+    .unwrap();
+    write_raw_tar(
+        &dir.path().join("file.tar"),
+        &[("f.txt", tar::EntryType::Regular, "new")],
+    );
+    assert!(engine
+        .run(r#"archive_extract("file.tar", "dest", #{overwrite: true})"#)
+        .is_ok());
+    assert_eq!(
+        std::fs::read_to_string(outside.join("target.txt")).unwrap(),
+        "keep"
+    );
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("dest").join("f.txt")).unwrap(),
+        "new"
+    );
+}
+
+#[test]
+#[cfg(all(feature = "tar", unix))]
+fn test_archive_chained_symlinks() {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = engine_in(&dir, FILES);
+
+    // Each link stays inside on its own, but not when reached through the ones before it.
+    let malicious: &[&[(&str, tar::EntryType, &str)]] = &[
+        &[
+            ("sub/", tar::EntryType::Directory, ""),
+            ("sub/up", tar::EntryType::Symlink, ".."),
+            ("sub/up/esc", tar::EntryType::Symlink, ".."),
+        ],
+        &[
+            ("here", tar::EntryType::Symlink, "."),
+            ("esc", tar::EntryType::Symlink, "here/.."),
+        ],
+    ];
+    for (idx, entries) in malicious.iter().enumerate() {
+        let archive = dir.path().join(format!("chain{idx}.tar"));
+        write_raw_tar(&archive, entries);
+        let mut scope = Scope::new();
+        scope.push_constant("ARCHIVE", archive.to_string_lossy().into_owned());
+        scope.push_constant("DEST", format!("dest{idx}"));
+
+        assert!(
+            engine
+                .run_with_scope(&mut scope, r#"archive_extract(ARCHIVE, DEST)"#)
+                .is_err(),
+            "{entries:?}"
+        );
+        let esc = dir.path().join(format!("dest{idx}")).join("esc");
+        assert!(std::fs::symlink_metadata(esc).is_err(), "{entries:?}");
+    }
+
+    // Chains that stay inside are fine.
+    write_raw_tar(
+        &dir.path().join("libs.tar"),
+        &[
+            ("lib/libfoo.so.1", tar::EntryType::Regular, "foo"),
+            ("lib/libfoo.so", tar::EntryType::Symlink, "libfoo.so.1"),
+            ("bin/foo", tar::EntryType::Symlink, "../lib/libfoo.so"),
+        ],
+    );
+    assert!(engine.run(r#"archive_extract("libs.tar", "libs")"#).is_ok());
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("libs").join("bin").join("foo")).unwrap(),
+        "foo"
+    );
+}
+
+#[test]
+#[cfg(feature = "zip")]
+fn test_zip_traversal() {
+    use std::io::Write;
+
+    let dir = tempfile::tempdir().unwrap();
+    let engine = engine_in(&dir, FILES);
+
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(dir.path().join("evil.zip")).unwrap());
+    zip.start_file("../evil.txt", zip::write::SimpleFileOptions::default())
+        .unwrap();
+    zip.write_all(b"evil").unwrap();
+    zip.finish().unwrap();
+
+    assert_eq!(
+        engine
+            .eval::<String>(r#"archive_list("evil.zip")[0].path"#)
+            .unwrap(),
+        "../evil.txt"
+    );
+    assert!(engine
+        .run(r#"archive_extract("evil.zip", "dest")"#)
+        .is_err());
+    assert!(!dir.path().join("evil.txt").exists());
+}
+
+#[test]
+fn test_archive_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = engine_in(&dir, FILES);
+
+    for script in [
+        r#"archive_create("x.rar", ["src"], "rar")"#,
+        r#"archive_create("x.zip", ["../src"], "zip")"#,
+        r#"archive_create("x.tar", ["missing"], "tar")"#,
+        r#"archive_create("x.tar", [42], "tar")"#,
+        r#"archive_extract("missing.tar", "dest")"#,
+        r#"archive_extract("top.txt", "dest", #{strip: 1})"#,
+    ] {
+        assert!(engine.run(script).is_err(), "{script} should throw");
+    }
+    // Failed archives aren't left behind.
+    assert!(!dir.path().join("x.tar").exists());
+    assert!(!dir.path().join("x.zip").exists());
+
+    // A failure leaves an existing file at the path untouched.
+    let (format, ext) = FORMATS[0];
+    let existing = dir.path().join(format!("existing.{ext}"));
+    std::fs::write(&existing, "keep").unwrap();
+    let mut scope = Scope::new();
+    scope.push_constant("FORMAT", format);
+    scope.push_constant("ARCHIVE", existing.to_string_lossy().into_owned());
+    assert!(engine
+        .run_with_scope(
+            &mut scope,
+            r#"archive_create(ARCHIVE, ["src", "missing"], FORMAT)"#
+        )
+        .is_err());
+    assert_eq!(std::fs::read_to_string(&existing).unwrap(), "keep");
+    let names: Vec<_> = std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    assert!(
+        names.iter().all(|name| !name.ends_with(".tmp")),
+        "{names:?}"
+    );
+}
@@ -0,0 +1,113 @@
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::SeekFrom;
+use std::ops::DerefMut;
+
+use rhai::{packages::Package, Engine, EvalAltResult, Locked, Scope, Shared};
+use rhai_fs::FilesystemPackage;
+
+#[inline(always)]
+fn borrow_mut(file: &Shared<Locked<File>>) -> impl DerefMut<Target = File> + '_ {
+    #[cfg(not(feature = "sync"))]
+    return file.borrow_mut();
+
+    #[cfg(feature = "sync")]
+    return file.write().unwrap();
+}
+
+#[test]
+fn test_binary_read() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    // Register our filesystem package.
+    let package = FilesystemPackage::new();
+    package.register_into_engine(&mut engine);
+
+    // A small header.
+    let shared_file = Shared::new(Locked::new(tempfile::tempfile().unwrap()));
+    let _ = borrow_mut(&shared_file)
+        .write(&[0xFF, 0x01, 0x02, 0x01, 0x02, 0x00, 0x00, 0x80, 0x3F])
+        .unwrap();
+    borrow_mut(&shared_file).seek(SeekFrom::Start(0)).unwrap();
+    let mut scope = Scope::new();
+    scope.push_constant("FILE", shared_file);
+
+    assert_eq!(
+        engine
+            .eval_with_scope::<rhai::Array>(
+                &mut scope,
+                r#"[FILE.read_i8(), FILE.read_u16_le(), FILE.read_u16_be()]"#
+            )?
+            .into_iter()
+            .map(|v| v.as_int().unwrap())
+            .collect::<Vec<_>>(),
+        vec![-1, 0x0201, 0x0102]
+    );
+    #[cfg(not(feature = "no_float"))]
+    assert_eq!(
+        engine.eval_with_scope::<rhai::FLOAT>(&mut scope, r#"FILE.read_f32_le()"#)?,
+        1.0
+    );
+
+    // Short reads throw and leave the position unchanged.
+    assert!(engine
+        .eval_with_scope::<rhai::INT>(&mut scope, r#"FILE.seek(7); FILE.read_u32_le()"#)
+        .is_err());
+    assert_eq!(
+        engine.eval_with_scope::<rhai::INT>(&mut scope, r#"FILE.position()"#)?,
+        7
+    );
+    #[cfg(not(feature = "no_index"))]
+    {
+        assert!(engine
+            .eval_with_scope::<rhai::Blob>(&mut scope, r#"FILE.read_exact(3)"#)
+            .is_err());
+        // A huge length isn't allocated up front.
+        assert!(engine
+            .eval_with_scope::<rhai::Blob>(&mut scope, r#"FILE.read_exact(1 << 60)"#)
+            .is_err());
+        assert_eq!(
+            engine.eval_with_scope::<rhai::INT>(&mut scope, r#"FILE.position()"#)?,
+            7
+        );
+        assert_eq!(
+            engine.eval_with_scope::<rhai::Blob>(&mut scope, r#"FILE.read_exact(2)"#)?,
+            &[0x80, 0x3F]
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_binary_write() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    // Register our filesystem package.
+    let package = FilesystemPackage::new();
+    package.register_into_engine(&mut engine);
+
+    let shared_file = Shared::new(Locked::new(tempfile::tempfile().unwrap()));
+    let mut scope = Scope::new();
+    scope.push_constant("FILE", shared_file.clone());
+
+    assert_eq!(
+        engine.eval_with_scope::<rhai::INT>(
+            &mut scope,
+            r#"FILE.write_u16_be(0x0102) + FILE.write_i32_le(-2) + FILE.write_u8(255)"#
+        )?,
+        7
+    );
+
+    // Out of range numbers throw.
+    assert!(engine
+        .eval_with_scope::<rhai::INT>(&mut scope, r#"FILE.write_u8(256)"#)
+        .is_err());
+
+    let mut buf = Vec::new();
+    borrow_mut(&shared_file).seek(SeekFrom::Start(0)).unwrap();
+    borrow_mut(&shared_file).read_to_end(&mut buf).unwrap();
+    assert_eq!(buf, &[0x01, 0x02, 0xFE, 0xFF, 0xFF, 0xFF, 0xFF]);
+
+    Ok(())
+}
@@ -0,0 +1,28 @@
+//! Helpers shared by the integration tests.
+
+use rhai::{packages::Package, Engine};
+use rhai_fs::{FilesystemConfig, FilesystemPackage};
+
+/// Returns an engine whose working directory is the passed directory, after writing each fixture
+/// file into it with any missing parent directories.
+pub fn engine_in(dir: &tempfile::TempDir, files: &[(&str, &[u8])]) -> Engine {
+    for (name, contents) in files {
+        let path = dir.path().join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    let mut engine = Engine::new();
+    let package = FilesystemPackage::with_config(FilesystemConfig {
+        cwd: Some(dir.path().to_path_buf()),
+        ..Default::default()
+    });
+    package.register_into_engine(&mut engine);
+    engine
+}
+
+/// Reads a file in the directory.
+#[allow(dead_code)]
+pub fn read(dir: &tempfile::TempDir, name: &str) -> String {
+    std::fs::read_to_string(dir.path().join(name)).unwrap()
+}
@@ -0,0 +1,197 @@
+#![cfg(any(feature = "gzip", feature = "zstd", feature = "bzip2"))]
+
+use rhai::{EvalAltResult, Scope};
+
+mod common;
+use common::engine_in;
+
+/// Codecs enabled in this build, with their extension and magic bytes.
+const CODECS: &[(&str, &str, &[u8])] = &[
+    #[cfg(feature = "gzip")]
+    ("gzip", "gz", &[0x1F, 0x8B]),
+    #[cfg(feature = "zstd")]
+    ("zstd", "zst", &[0x28, 0xB5, 0x2F, 0xFD]),
+    #[cfg(feature = "bzip2")]
+    ("bzip2", "bz2", b"BZh"),
+];
+
+#[test]
+fn test_compressed_round_trip() -> Result<(), Box<EvalAltResult>> {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = engine_in(&dir, &[]);
+
+    for &(codec, ext, magic) in CODECS {
+        let mut scope = Scope::new();
+        scope.push_constant("CODEC", codec);
+        scope.push_constant("NAME", format!("data.{codec}"));
+
+        // Named codecs don't depend on the extension.
+        engine.run_with_scope(
+            &mut scope,
+            r#"
+                let f = open_file(NAME, "w", #{compression: CODEC, level: 1});
+                f.write("héllo\n");
+                f.write("wörld\n");
+                f.close();
+            "#,
+        )?;
+        let raw = std::fs::read(dir.path().join(format!("data.{codec}"))).unwrap();
+        assert!(
+            raw.starts_with(magic),
+            "{codec} output lacks its magic bytes"
+        );
+
+        assert_eq!(
+            engine.eval_with_scope::<String>(
+                &mut scope,
+                r#"open_file(NAME, "r", #{compression: CODEC}).read_string()"#
+            )?,
+            "héllo\nwörld\n"
+        );
+
+        // Auto detection uses the extension for writing and the magic bytes for reading.
+        scope.push_constant("AUTO", format!("auto.{ext}"));
+        engine.run_with_scope(
+            &mut scope,
+            r#"let f = open_file(AUTO, "w", #{}); f.write("abc"); f.close();"#,
+        )?;
+        let raw = std::fs::read(dir.path().join(format!("auto.{ext}"))).unwrap();
+        assert!(
+            raw.starts_with(magic),
+            "{ext} extension didn't select {codec}"
+        );
+        std::fs::rename(
+            dir.path().join(format!("auto.{ext}")),
+            dir.path().join("renamed"),
+        )
+        .unwrap();
+        assert_eq!(
+            engine.eval_with_scope::<String>(
+                &mut scope,
+                r#"open_file("renamed", "r", #{compression: "auto"}).read_string()"#
+            )?,
+            "abc"
+        );
+
+        // Appending adds a stream that reads back as one, also when dropped without closing.
+        engine.run_with_scope(
+            &mut scope,
+            r#"open_file(NAME, "a", #{compression: CODEC}).write("again\n");"#,
+        )?;
+        assert_eq!(
+            engine.eval_with_scope::<String>(
+                &mut scope,
+                r#"
+                    let f = open_file(NAME, "r", #{});
+                    `${f.read_line()}|${f.read_string(3)}|${f.read_line()}|${f.read_line()}|${f.read_line()}`
+                "#
+            )?,
+            "héllo\n|wö|rld\n|again\n|"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_compressed_stream() -> Result<(), Box<EvalAltResult>> {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = engine_in(&dir, &[]);
+    let (codec, _, _) = CODECS[0];
+    let mut scope = Scope::new();
+    scope.push_constant("CODEC", codec);
+
+    // Bounded reads leave a character cut in two for the next read.
+    engine.run_with_scope(
+        &mut scope,
+        r#"let f = open_file("text", "w", #{compression: CODEC}); f.write("añb"); f.close();"#,
+    )?;
+    assert_eq!(
+        engine.eval_with_scope::<String>(
+            &mut scope,
+            r#"let f = open_file("text", "r", #{}); f.read_string(2) + "|" + f.read_string()"#
+        )?,
+        "a|ñb"
+    );
+
+    // Encodings are applied before compressing.
+    assert_eq!(
+        engine.eval_with_scope::<String>(
+            &mut scope,
+            r#"
+                let f = open_file("utf16", "w", #{compression: CODEC});
+                f.write("ünï", "utf-16");
+                f.close();
+                open_file("utf16", "r", #{}).read_string("utf-16")
+            "#
+        )?,
+        "ünï"
+    );
+    assert_eq!(
+        engine.eval_with_scope::<String>(
+            &mut scope,
+            r#"
+                let f = open_file("utf16be", "w", #{compression: CODEC});
+                f.write("\uFEFFünï", "utf-16be");
+                f.close();
+                let f = open_file("utf16be", "r", #{});
+                f.read_string(4, "utf-16") + "|" + f.read_string(2, "utf-16") + "|" + f.read_string("utf-16")
+            "#
+        )?,
+        "ü|n|ï"
+    );
+
+    #[cfg(not(feature = "no_index"))]
+    assert_eq!(
+        engine.eval_with_scope::<rhai::Blob>(
+            &mut scope,
+            r#"
+                let f = open_file("blob", "w", #{compression: CODEC});
+                f.write(blob(1000, 7));
+                f.close();
+                let f = open_file("blob", "r", #{});
+                f.read_blob(10);
+                f.read_blob()
+            "#
+        )?,
+        vec![7; 990]
+    );
+
+    // Files without known magic bytes are read as is.
+    std::fs::write(dir.path().join("plain.txt"), "just text").unwrap();
+    assert_eq!(
+        engine.eval::<String>(r#"open_file("plain.txt", "r", #{}).read_string()"#)?,
+        "just text"
+    );
+    assert_eq!(
+        engine.eval::<String>(
+            r#"let f = open_file("empty", "w", #{}); f.close(); open_file("empty", "r", #{}).read_line()"#
+        )?,
+        ""
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_compressed_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = engine_in(&dir, &[]);
+    std::fs::write(dir.path().join("plain.txt"), "just text").unwrap();
+
+    for script in [
+        // Read and write at once.
+        r#"open_file("x.gz", "w+", #{})"#,
+        // Unknown option, codec and level.
+        r#"open_file("x.gz", "w", #{format: "gzip"})"#,
+        r#"open_file("x.gz", "w", #{compression: "lz4"})"#,
+        r#"open_file("x", "w", #{compression: "zstd", level: 99})"#,
+        // Wrong direction, closed handles and corrupt data.
+        r#"open_file("plain.txt", "r", #{}).write("abc")"#,
+        r#"open_file("y", "w", #{}).read_string()"#,
+        r#"let f = open_file("plain.txt", "r", #{}); f.close(); f.read_line()"#,
+        r#"open_file("plain.txt", "r", #{compression: "bzip2"}).read_string()"#,
+    ] {
+        assert!(engine.run(script).is_err(), "{script} should throw");
+    }
+}
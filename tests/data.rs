@@ -0,0 +1,158 @@
+#![cfg(any(feature = "json", feature = "toml", feature = "yaml", feature = "csv"))]
+
+use rhai::EvalAltResult;
+
+mod common;
+use common::{engine_in, read};
+
+#[cfg(feature = "json")]
+#[test]
+fn test_json() -> Result<(), Box<EvalAltResult>> {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = engine_in(&dir, &[]);
+    std::fs::write(
+        dir.path().join("config.json"),
+        r#"{ "name": "app", "ports": [80, 443], "debug": false, "extra": null }"#,
+    )
+    .unwrap();
+
+    let summary = engine.eval::<String>(
+        r#"let c = read_json("config.json"); `${c.name} ${c.ports[1]} ${c["debug"]} ${type_of(c.extra)}`"#,
+    )?;
+    assert_eq!(summary, "app 443 false ()");
+
+    engine.run(r#"write_json("out.json", #{ a: 1, b: [true, "x"] })"#)?;
+    assert_eq!(
+        read(&dir, "out.json"),
+        "{\n  \"a\": 1,\n  \"b\": [\n    true,\n    \"x\"\n  ]\n}\n"
+    );
+    engine.run(r#"write_json(path("out.json"), [1, 2], #{ pretty: false })"#)?;
+    assert_eq!(read(&dir, "out.json"), "[1,2]\n");
+
+    std::fs::write(dir.path().join("bad.json"), "{ nope").unwrap();
+    assert!(engine.run(r#"read_json("bad.json")"#).is_err());
+    assert!(engine.run(r#"read_json("missing.json")"#).is_err());
+    assert!(engine
+        .run(r#"write_json("out.json", 1, #{ indent: 2 })"#)
+        .is_err());
+
+    Ok(())
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_json_max_string_size() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut engine = engine_in(&dir, &[]);
+    engine.set_max_string_size(10);
+    std::fs::write(dir.path().join("big.json"), r#"{ "key": "a long value" }"#).unwrap();
+
+    assert!(engine.run(r#"read_json("big.json")"#).is_err());
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn test_toml() -> Result<(), Box<EvalAltResult>> {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = engine_in(&dir, &[]);
+    std::fs::write(
+        dir.path().join("Cargo.toml"),
+        "[package]\nname = \"demo\"\nreleased = 1979-05-27T07:32:00Z\n\n[dependencies]\nrhai = \"1\"\n",
+    )
+    .unwrap();
+
+    let summary = engine.eval::<String>(
+        r#"let c = read_toml("Cargo.toml"); let p = c["package"]; `${p.name} ${p.released} ${c.dependencies.rhai}`"#,
+    )?;
+    assert_eq!(summary, "demo 1979-05-27T07:32:00Z 1");
+
+    engine.run(r#"write_toml("out.toml", #{ name: "x", server: #{ port: 8080 } })"#)?;
+    assert_eq!(
+        read(&dir, "out.toml"),
+        "name = \"x\"\n\n[server]\nport = 8080\n"
+    );
+
+    assert!(engine
+        .run(r#"write_toml("out.toml", #{ nothing: () })"#)
+        .is_err());
+    std::fs::write(dir.path().join("bad.toml"), "key = ").unwrap();
+    assert!(engine.run(r#"read_toml("bad.toml")"#).is_err());
+
+    Ok(())
+}
+
+#[cfg(feature = "yaml")]
+#[test]
+fn test_yaml() -> Result<(), Box<EvalAltResult>> {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = engine_in(&dir, &[]);
+    std::fs::write(
+        dir.path().join("config.yaml"),
+        "name: app\nhosts:\n  - a.example\n  - b.example\nretries: 3\n",
+    )
+    .unwrap();
+
+    let summary = engine.eval::<String>(
+        r#"let c = read_yaml("config.yaml"); `${c.name} ${c.hosts.len()} ${c.retries + 1}`"#,
+    )?;
+    assert_eq!(summary, "app 2 4");
+
+    engine.run(r#"write_yaml("out.yaml", #{ name: "x", list: [1, 2] })"#)?;
+    assert_eq!(read(&dir, "out.yaml"), "list:\n- 1\n- 2\nname: x\n");
+    engine.run(r#"write_yaml(path("out.yaml"), #{ name: "y" }, #{})"#)?;
+    assert_eq!(read(&dir, "out.yaml"), "name: y\n");
+    assert!(engine
+        .run(r#"write_yaml("out.yaml", #{ name: "z" }, #{ pretty: true })"#)
+        .is_err());
+    assert_eq!(read(&dir, "out.yaml"), "name: y\n");
+
+    std::fs::write(dir.path().join("bad.yaml"), "key: [").unwrap();
+    assert!(engine.run(r#"read_yaml("bad.yaml")"#).is_err());
+
+    Ok(())
+}
+
+#[cfg(feature = "csv")]
+#[test]
+fn test_csv() -> Result<(), Box<EvalAltResult>> {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = engine_in(&dir, &[]);
+    std::fs::write(
+        dir.path().join("data.csv"),
+        "name,size\nalpha,1\n\"b, c\",2\n",
+    )
+    .unwrap();
+
+    let summary = engine.eval::<String>(
+        r#"let rows = read_csv("data.csv"); `${rows.len()} ${rows[1].name} ${rows[1].size}`"#,
+    )?;
+    assert_eq!(summary, "2 b, c 2");
+    let summary = engine.eval::<String>(
+        r#"let rows = read_csv("data.csv", #{ headers: false }); `${rows.len()} ${rows[0][1]}`"#,
+    )?;
+    assert_eq!(summary, "3 size");
+
+    engine.run(r#"write_csv("out.csv", [#{ b: 2, a: "x,y" }, #{ a: () }])"#)?;
+    assert_eq!(read(&dir, "out.csv"), "a,b\n\"x,y\",2\n,\n");
+    engine.run(
+        r#"write_csv(path("out.csv"), [[1, "two"], [3]], #{ columns: ["n", "s"], delimiter: ";" })"#,
+    )?;
+    assert_eq!(read(&dir, "out.csv"), "n;s\n1;two\n3\n");
+
+    std::fs::write(dir.path().join("ragged.csv"), "a,b\n1\n").unwrap();
+    assert!(engine.run(r#"read_csv("ragged.csv")"#).is_err());
+    assert!(engine
+        .run(r#"read_csv("data.csv", #{ delimiter: ",," })"#)
+        .is_err());
+    assert!(engine
+        .run(r#"read_csv("data.csv", #{ columns: ["a"] })"#)
+        .is_err());
+    assert!(engine.run(r#"write_csv("out.csv", [1])"#).is_err());
+
+    // Arrays have no column names to write as headers.
+    assert!(engine.run(r#"write_csv("out.csv", [[1, 2]])"#).is_err());
+    engine.run(r#"write_csv("out.csv", [[1, 2]], #{ headers: false })"#)?;
+    assert_eq!(read(&dir, "out.csv"), "1,2\n");
+
+    Ok(())
+}
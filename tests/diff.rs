@@ -0,0 +1,248 @@
+use rhai::{Array, EvalAltResult, Map};
+
+mod common;
+use common::engine_in;
+
+/// A source tree to compare.
+const FILES: &[(&str, &[u8])] = &[
+    ("src/a.txt", b"alpha"),
+    ("src/sub/b.txt", b"beta"),
+    ("src/sub/c.log", b"log"),
+    ("src/target/out", b"built"),
+];
+
+/// Returns the strings in an array of the report map.
+fn paths(map: &Map, key: &str) -> Vec<String> {
+    map[key]
+        .clone()
+        .into_typed_array::<rhai::ImmutableString>()
+        .unwrap()
+        .into_iter()
+        .map(|path| path.to_string())
+        .collect()
+}
+
+#[test]
+fn test_sync_and_diff_dirs() -> Result<(), Box<EvalAltResult>> {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = engine_in(&dir, FILES);
+
+    let report = engine.eval::<Map>(r#"sync_dir("src", "dst")"#)?;
+    assert_eq!(
+        paths(&report, "created"),
+        [
+            "a.txt",
+            "sub",
+            "sub/b.txt",
+            "sub/c.log",
+            "target",
+            "target/out"
+        ]
+    );
+    assert!(paths(&report, "updated").is_empty());
+    assert_eq!(report["dry_run"].as_bool(), Ok(false));
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("dst").join("sub").join("b.txt")).unwrap(),
+        "beta"
+    );
+
+    // Copies keep their modification time, so nothing differs by metadata.
+    let diff = engine.eval::<Map>(r#"diff_dirs("src", "dst")"#)?;
+    for key in ["added", "removed", "modified"] {
+        assert!(paths(&diff, key).is_empty(), "{key}");
+    }
+    let report = engine.eval::<Map>(r#"sync_dir("src", "dst")"#)?;
+    assert!(paths(&report, "created").is_empty());
+    assert!(paths(&report, "updated").is_empty());
+
+    std::fs::write(dir.path().join("src").join("a.txt"), "changed").unwrap();
+    std::fs::write(dir.path().join("src").join("new.txt"), "new").unwrap();
+    std::fs::remove_file(dir.path().join("src").join("sub").join("b.txt")).unwrap();
+
+    let diff = engine.eval::<Map>(r#"diff_dirs("src", "dst", #{ compare: "content" })"#)?;
+    assert_eq!(paths(&diff, "added"), ["sub/b.txt"]);
+    assert_eq!(paths(&diff, "removed"), ["new.txt"]);
+    assert_eq!(paths(&diff, "modified"), ["a.txt"]);
+
+    let report =
+        engine.eval::<Map>(r#"sync_dir("src", "dst", #{ delete: true, dry_run: true })"#)?;
+    assert_eq!(paths(&report, "created"), ["new.txt"]);
+    assert_eq!(paths(&report, "updated"), ["a.txt"]);
+    assert_eq!(paths(&report, "deleted"), ["sub/b.txt"]);
+    assert_eq!(report["dry_run"].as_bool(), Ok(true));
+    assert!(!dir.path().join("dst").join("new.txt").exists());
+
+    engine.eval::<Map>(r#"sync_dir("src", "dst", #{ delete: true })"#)?;
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("dst").join("a.txt")).unwrap(),
+        "changed"
+    );
+    assert!(!dir.path().join("dst").join("sub").join("b.txt").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_sync_dir_exclude() -> Result<(), Box<EvalAltResult>> {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = engine_in(&dir, FILES);
+
+    let report =
+        engine.eval::<Map>(r#"sync_dir("src", "dst", #{ exclude: ["*.log", "/target"] })"#)?;
+    assert_eq!(paths(&report, "created"), ["a.txt", "sub", "sub/b.txt"]);
+
+    // Excluded entries in the destination are left alone, along with the directories holding them.
+    let kept = dir.path().join("dst").join("sub").join("keep.log");
+    std::fs::write(&kept, "kept").unwrap();
+    std::fs::remove_dir_all(dir.path().join("src").join("sub")).unwrap();
+    let report = engine
+        .eval::<Map>(r#"sync_dir("src", "dst", #{ exclude: ["**/*.log"], delete: true })"#)?;
+    assert_eq!(paths(&report, "deleted"), ["sub/b.txt"]);
+    assert!(kept.exists());
+
+    let diff = engine.eval::<Map>(r#"diff_dirs("src", "dst", #{ exclude: ["sub/**"] })"#)?;
+    assert!(paths(&diff, "removed").is_empty());
+    assert_eq!(paths(&diff, "added"), ["sub"]);
+
+    Ok(())
+}
+
+#[test]
+fn test_sync_dir_replace_dir() -> Result<(), Box<EvalAltResult>> {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = engine_in(&dir, FILES);
+    engine.eval::<Map>(r#"sync_dir("src", "dst")"#)?;
+
+    let dst_sub = dir.path().join("dst").join("sub");
+    let kept = dst_sub.join("keep.log");
+    std::fs::write(&kept, "kept").unwrap();
+    std::fs::remove_dir_all(dir.path().join("src").join("sub")).unwrap();
+    std::fs::write(dir.path().join("src").join("sub"), "now a file").unwrap();
+
+    // A directory replaced by a file takes its contents with it, so that needs `delete`.
+    assert!(engine
+        .eval::<Map>(r#"sync_dir("src", "dst", #{ dry_run: true })"#)
+        .is_err());
+    assert!(engine.eval::<Map>(r#"sync_dir("src", "dst")"#).is_err());
+    assert!(dst_sub.join("b.txt").exists());
+
+    // Excluded entries are never removed, so the directory can't be replaced.
+    assert!(engine
+        .eval::<Map>(r#"sync_dir("src", "dst", #{ exclude: ["**/*.log"], delete: true })"#)
+        .is_err());
+    assert!(kept.exists());
+    assert!(dst_sub.join("b.txt").exists());
+
+    let report = engine.eval::<Map>(r#"sync_dir("src", "dst", #{ delete: true })"#)?;
+    assert_eq!(paths(&report, "updated"), ["sub"]);
+    assert_eq!(
+        paths(&report, "deleted"),
+        ["sub/b.txt", "sub/c.log", "sub/keep.log"]
+    );
+    assert_eq!(std::fs::read_to_string(&dst_sub).unwrap(), "now a file");
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_sync_dir_symlinks() -> Result<(), Box<EvalAltResult>> {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = engine_in(&dir, FILES);
+    std::os::unix::fs::symlink("a.txt", dir.path().join("src").join("link")).unwrap();
+
+    // A symlink in the destination is replaced, never written through.
+    std::fs::create_dir(dir.path().join("dst")).unwrap();
+    std::fs::write(dir.path().join("outside.txt"), "outside").unwrap();
+    std::os::unix::fs::symlink("../outside.txt", dir.path().join("dst").join("a.txt")).unwrap();
+
+    let report = engine.eval::<Map>(r#"sync_dir("src", "dst")"#)?;
+    assert_eq!(paths(&report, "updated"), ["a.txt"]);
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("outside.txt")).unwrap(),
+        "outside"
+    );
+    assert_eq!(
+        std::fs::read_link(dir.path().join("dst").join("link")).unwrap(),
+        std::path::Path::new("a.txt")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_diff_dirs_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = engine_in(&dir, FILES);
+
+    assert!(engine
+        .eval::<Map>(r#"diff_dirs("src", "missing")"#)
+        .is_err());
+    assert!(engine
+        .eval::<Map>(r#"sync_dir("src/a.txt", "dst")"#)
+        .is_err());
+    assert!(engine
+        .eval::<Map>(r#"diff_dirs("src", "src", #{ delete: true })"#)
+        .is_err());
+    assert!(engine
+        .eval::<Map>(r#"diff_dirs("src", "src", #{ compare: "hash" })"#)
+        .is_err());
+    assert!(engine
+        .eval::<Array>(r#"sync_dir("src", "dst", #{ exclude: "*.log" })"#)
+        .is_err());
+}
+
+#[test]
+fn test_files_equal() -> Result<(), Box<EvalAltResult>> {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = engine_in(&dir, FILES);
+    std::fs::write(dir.path().join("same.txt"), "alpha").unwrap();
+    std::fs::write(dir.path().join("other.txt"), "alphb").unwrap();
+
+    assert!(engine.eval::<bool>(r#"files_equal("src/a.txt", "same.txt")"#)?);
+    assert!(!engine.eval::<bool>(r#"files_equal("src/a.txt", "other.txt")"#)?);
+    assert!(!engine.eval::<bool>(r#"files_equal(path("src/a.txt"), path("src/sub/b.txt"))"#)?);
+    assert!(engine
+        .eval::<bool>(r#"files_equal("src/a.txt", "missing.txt")"#)
+        .is_err());
+
+    Ok(())
+}
+
+#[cfg(feature = "diff")]
+#[test]
+fn test_diff_text() -> Result<(), Box<EvalAltResult>> {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = engine_in(&dir, FILES);
+    std::fs::write(dir.path().join("old.conf"), "a\nb\nc\nd\ne\n").unwrap();
+    std::fs::write(dir.path().join("new.conf"), "a\nb\nC\nd\ne\n").unwrap();
+
+    let diff = engine.eval::<String>(r#"diff_text(path("old.conf"), path("new.conf"))"#)?;
+    assert_eq!(
+        diff,
+        "--- old.conf\n+++ new.conf\n@@ -1,5 +1,5 @@\n a\n b\n-c\n+C\n d\n e\n"
+    );
+    let diff = engine
+        .eval::<String>(r#"diff_text(path("old.conf"), path("new.conf"), #{ context: 0 })"#)?;
+    assert_eq!(diff, "--- old.conf\n+++ new.conf\n@@ -3 +3 @@\n-c\n+C\n");
+    assert_eq!(
+        engine.eval::<String>(r#"diff_text(path("old.conf"), path("old.conf"))"#)?,
+        ""
+    );
+
+    // Strings are compared as text.
+    let diff = engine.eval::<String>(r#"diff_text("x\ny", "x\nz\n")"#)?;
+    assert_eq!(
+        diff,
+        "--- a\n+++ b\n@@ -1,2 +1,2 @@\n x\n-y\n\\ No newline at end of file\n+z\n"
+    );
+
+    assert!(engine
+        .eval::<String>(r#"diff_text("a", "b", #{ context: -1 })"#)
+        .is_err());
+    assert!(engine
+        .eval::<String>(r#"diff_text(path("old.conf"), path("missing.conf"))"#)
+        .is_err());
+
+    Ok(())
+}
@@ -1,21 +1,90 @@
 use std::path::Path;
 
 use rhai::{packages::Package, Engine, EvalAltResult};
-use rhai_fs::FilesystemPackage;
+use rhai_fs::{FilesystemConfig, FilesystemPackage};
+
+mod common;
+use common::engine_in;
 
 #[test]
 fn test_dir() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
 
-    // Register our filesystem package.
-    let package = FilesystemPackage::new();
+    // Register our filesystem package, working in the fixtures directory.
+    let package = FilesystemPackage::with_config(FilesystemConfig {
+        cwd: Some(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")),
+        ..Default::default()
+    });
     package.register_into_engine(&mut engine);
 
-    std::env::set_current_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures"))
-        .unwrap();
-
     // Retrieve number of paths from dir.
     assert_eq!(engine.eval::<rhai::INT>(r#"open_dir(cwd()).len"#)?, 2);
 
     Ok(())
 }
+
+/// A few files to measure.
+const FILES: &[(&str, &[u8])] = &[
+    ("cache/a.bin", &[0; 100]),
+    ("cache/sub/b.bin", &[0; 20]),
+    ("outside.bin", &[0; 1000]),
+];
+
+#[test]
+fn test_dir_size() -> Result<(), Box<EvalAltResult>> {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = engine_in(&dir, FILES);
+
+    assert_eq!(engine.eval::<rhai::INT>(r#"dir_size("cache")"#)?, 120);
+    assert_eq!(
+        engine.eval::<rhai::INT>(r#"dir_size(path("cache"), #{ same_device: true })"#)?,
+        120
+    );
+
+    #[cfg(unix)]
+    {
+        // Symlinks are skipped unless followed, and what they point to is still counted once.
+        let cache = dir.path().join("cache");
+        std::os::unix::fs::symlink("../outside.bin", cache.join("outside")).unwrap();
+        std::os::unix::fs::symlink("..", cache.join("sub").join("parent")).unwrap();
+        std::fs::hard_link(cache.join("a.bin"), cache.join("sub").join("a.bin")).unwrap();
+
+        assert_eq!(engine.eval::<rhai::INT>(r#"dir_size("cache")"#)?, 120);
+        assert_eq!(
+            engine.eval::<rhai::INT>(r#"dir_size("cache", #{ follow_symlinks: true })"#)?,
+            1120
+        );
+    }
+
+    assert!(engine
+        .eval::<rhai::INT>(r#"dir_size("outside.bin")"#)
+        .is_err());
+    assert!(engine.eval::<rhai::INT>(r#"dir_size("missing")"#).is_err());
+    assert!(engine
+        .eval::<rhai::INT>(r#"dir_size("cache", #{ follow: true })"#)
+        .is_err());
+    assert!(engine
+        .eval::<rhai::INT>(r#"dir_size("cache", #{ same_device: 1 })"#)
+        .is_err());
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_fs_stats() -> Result<(), Box<EvalAltResult>> {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = engine_in(&dir, FILES);
+
+    let stats = engine.eval::<rhai::Map>(r#"fs_stats("cache")"#)?;
+    let field = |name: &str| stats[name].as_int().unwrap();
+    assert!(field("total") > 0);
+    assert!(field("free") <= field("total"));
+    assert!(field("available") <= field("free"));
+    assert!(field("inodes_free") <= field("inodes"));
+    assert!(field("inodes_available") <= field("inodes_free"));
+
+    assert!(engine.eval::<rhai::Map>(r#"fs_stats("missing")"#).is_err());
+
+    Ok(())
+}
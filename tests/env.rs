@@ -0,0 +1,76 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use rhai::{packages::Package, Engine, EvalAltResult};
+use rhai_fs::{EnvAccess, FilesystemConfig, FilesystemPackage};
+
+#[test]
+fn test_env_dirs() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    // Register our filesystem package with a fixed set of variables.
+    let vars = BTreeMap::from([
+        ("HOME".to_string(), "/home/rhai".to_string()),
+        ("USERPROFILE".to_string(), "/home/rhai".to_string()),
+        ("XDG_CACHE_HOME".to_string(), "/var/cache/rhai".to_string()),
+        ("LOCALAPPDATA".to_string(), "/var/cache/rhai".to_string()),
+        ("PROJECT".to_string(), "rhai-fs".to_string()),
+    ]);
+    let package = FilesystemPackage::with_config(FilesystemConfig {
+        env: EnvAccess::Vars(vars),
+        ..Default::default()
+    });
+    package.register_into_engine(&mut engine);
+
+    // Standard locations.
+    assert_eq!(
+        engine.eval::<PathBuf>(r#"home_dir()"#)?,
+        PathBuf::from("/home/rhai")
+    );
+    assert_eq!(
+        engine.eval::<PathBuf>(r#"cache_dir()"#)?,
+        PathBuf::from("/var/cache/rhai")
+    );
+    #[cfg(not(windows))]
+    assert_eq!(
+        engine.eval::<PathBuf>(r#"config_dir()"#)?,
+        PathBuf::from("/home/rhai/.config")
+    );
+
+    // Expansion of home directories and variables.
+    assert_eq!(
+        engine.eval::<PathBuf>(r#"expand("~/src/${PROJECT}/$PROJECT.txt")"#)?,
+        PathBuf::from("/home/rhai/src/rhai-fs/rhai-fs.txt")
+    );
+    assert_eq!(
+        engine.eval::<PathBuf>(r#"path("~user/$/x").expand()"#)?,
+        PathBuf::from("~user/$/x")
+    );
+    assert!(engine.eval::<PathBuf>(r#"expand("$MISSING")"#).is_err());
+    assert!(engine.eval::<PathBuf>(r#"expand("${PROJECT")"#).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_env_disabled() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    // Register our filesystem package without environment access.
+    let package = FilesystemPackage::with_config(FilesystemConfig {
+        env: EnvAccess::Disabled,
+        ..Default::default()
+    });
+    package.register_into_engine(&mut engine);
+
+    assert!(engine.eval::<PathBuf>(r#"home_dir()"#).is_err());
+    assert!(engine.eval::<PathBuf>(r#"expand("~")"#).is_err());
+
+    // Paths without anything to expand still work.
+    assert_eq!(
+        engine.eval::<PathBuf>(r#"expand("foo.txt")"#)?,
+        PathBuf::from("foo.txt")
+    );
+
+    Ok(())
+}
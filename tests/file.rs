@@ -52,6 +52,37 @@ fn test_reading_file() -> Result<(), Box<EvalAltResult>> {
         15
     );
 
+    // Reads near EOF are truncated to what's left.
+    assert_eq!(
+        engine.eval_with_scope::<String>(&mut scope, r#"FILE.seek(10); FILE.read_string(10)"#)?,
+        "test!"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_file_type_name() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    // Register our filesystem package.
+    let package = FilesystemPackage::new();
+    package.register_into_engine(&mut engine);
+
+    // Files use a friendly type name regardless of the `sync` feature.
+    let shared_file = Shared::new(Locked::new(tempfile::tempfile().unwrap()));
+    let mut scope = Scope::new();
+    scope.push_constant("FILE", shared_file);
+
+    assert_eq!(
+        engine.eval_with_scope::<String>(&mut scope, r#"type_of(FILE)"#)?,
+        "File"
+    );
+    assert_eq!(
+        engine.eval_with_scope::<String>(&mut scope, r#"FILE.to_string()"#)?,
+        "File"
+    );
+
     Ok(())
 }
 
@@ -129,14 +160,36 @@ fn test_blob_file() -> Result<(), Box<EvalAltResult>> {
         &[1, 2, 3, 4]
     );
 
-    // Lengths under max string size are not respected.
+    // Reads stop at EOF when it comes before max array size.
     assert_eq!(
         engine
             .set_max_array_size(16)
             .eval_with_scope::<rhai::INT>(&mut scope, r#"FILE.seek(0); FILE.read_blob().len"#)?,
-        16
+        9
     );
 
+    // Reads near EOF are truncated to what's left.
+    assert_eq!(
+        engine.eval_with_scope::<rhai::Blob>(&mut scope, r#"FILE.seek(7); FILE.read_blob(4)"#)?,
+        &[8, 9]
+    );
+    assert_eq!(
+        engine.eval_with_scope::<rhai::Blob>(&mut scope, r#"FILE.seek(6); FILE.read_some(8)"#)?,
+        &[7, 8, 9]
+    );
+    assert!(engine
+        .eval_with_scope::<rhai::Blob>(&mut scope, r#"FILE.read_some(17)"#)
+        .is_err());
+    // A huge length without a `max_array_size` isn't allocated up front.
+    assert_eq!(
+        engine.set_max_array_size(0).eval_with_scope::<rhai::Blob>(
+            &mut scope,
+            r#"FILE.seek(6); FILE.read_some(1 << 60)"#
+        )?,
+        &[7, 8, 9]
+    );
+    engine.set_max_array_size(16);
+
     // Blob from rhai to rust.
     assert_eq!(
         engine.eval_with_scope::<rhai::Blob>(
@@ -157,3 +210,292 @@ fn test_blob_file() -> Result<(), Box<EvalAltResult>> {
 
     Ok(())
 }
+
+#[test]
+fn test_locking_file() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    // Register our filesystem package.
+    let package = FilesystemPackage::new();
+    package.register_into_engine(&mut engine);
+
+    // Two handles to the same file.
+    let temp = tempfile::NamedTempFile::new().unwrap();
+    let mut scope = Scope::new();
+    scope.push_constant("PATH", temp.path().to_path_buf());
+    scope.push_constant("FIRST", Shared::new(Locked::new(temp.reopen().unwrap())));
+    scope.push_constant("SECOND", Shared::new(Locked::new(temp.reopen().unwrap())));
+
+    // An exclusive lock blocks any other lock.
+    assert!(
+        engine.eval_with_scope::<bool>(&mut scope, r#"FIRST.lock(); !SECOND.try_lock_shared()"#)?
+    );
+    assert_eq!(
+        engine.eval_with_scope::<String>(
+            &mut scope,
+            r#"let kind = ""; try { SECOND.lock(20) } catch (e) { kind = e.kind } kind"#
+        )?,
+        "would_block"
+    );
+
+    // Shared locks can be held together.
+    assert!(engine.eval_with_scope::<bool>(
+        &mut scope,
+        r#"FIRST.unlock(); FIRST.lock_shared(); SECOND.try_lock_shared()"#
+    )?);
+    assert!(!engine.eval_with_scope::<bool>(&mut scope, r#"open_file(PATH).try_lock()"#)?);
+
+    // Locks are released when the handles go away.
+    scope.clear();
+    scope.push_constant("PATH", temp.path().to_path_buf());
+    assert!(engine.eval_with_scope::<bool>(&mut scope, r#"open_file(PATH).try_lock()"#)?);
+
+    Ok(())
+}
+
+#[test]
+fn test_encoding_file() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    // Register our filesystem package.
+    let package = FilesystemPackage::new();
+    package.register_into_engine(&mut engine);
+
+    let shared_file = Shared::new(Locked::new(tempfile::tempfile().unwrap()));
+    let mut scope = Scope::new();
+    scope.push_constant("FILE", shared_file.clone());
+
+    // UTF-16 with a BOM round trips, and the BOM picks the byte order.
+    assert_eq!(
+        engine.eval_with_scope::<String>(
+            &mut scope,
+            r#"FILE.write("héllo", "utf-16"); FILE.seek(0); FILE.read_string("utf-16")"#
+        )?,
+        "héllo"
+    );
+    let mut buf = Vec::new();
+    borrow_mut(&shared_file).seek(SeekFrom::Start(0)).unwrap();
+    borrow_mut(&shared_file).read_to_end(&mut buf).unwrap();
+    assert_eq!(&buf[..4], &[0xFF, 0xFE, b'h', 0]);
+
+    // Single byte code pages.
+    borrow_mut(&shared_file).set_len(0).unwrap();
+    borrow_mut(&shared_file).seek(SeekFrom::Start(0)).unwrap();
+    let _ = borrow_mut(&shared_file)
+        .write(&[0x80, b' ', 0xE9, 0xFF])
+        .unwrap();
+    assert_eq!(
+        engine
+            .eval_with_scope::<String>(&mut scope, r#"FILE.seek(0); FILE.read_string("cp1252")"#)?,
+        "€ éÿ"
+    );
+    assert_eq!(
+        engine.eval_with_scope::<String>(
+            &mut scope,
+            r#"FILE.seek(2); FILE.read_string(1, "latin1")"#
+        )?,
+        "é"
+    );
+    assert_eq!(
+        engine.eval_with_scope::<String>(
+            &mut scope,
+            r#"FILE.seek(0); FILE.read_string("utf-8-lossy")"#
+        )?,
+        "\u{FFFD} \u{FFFD}\u{FFFD}"
+    );
+    assert!(engine
+        .eval_with_scope::<String>(&mut scope, r#"FILE.seek(0); FILE.read_string("ascii")"#)
+        .is_err());
+    assert!(engine
+        .eval_with_scope::<rhai::INT>(&mut scope, r#"FILE.write("€", "latin1")"#)
+        .is_err());
+
+    // A UTF-8 BOM at the start of the file is skipped.
+    borrow_mut(&shared_file).set_len(0).unwrap();
+    borrow_mut(&shared_file).seek(SeekFrom::Start(0)).unwrap();
+    let _ = borrow_mut(&shared_file).write(b"\xEF\xBB\xBFtest").unwrap();
+    assert_eq!(
+        engine.eval_with_scope::<String>(&mut scope, r#"FILE.seek(0); FILE.read_string()"#)?,
+        "test"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_chunked_utf8_file() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    // Register our filesystem package.
+    let package = FilesystemPackage::new();
+    package.register_into_engine(&mut engine);
+
+    let shared_file = Shared::new(Locked::new(tempfile::tempfile().unwrap()));
+    let _ = borrow_mut(&shared_file).write("aé€😀b".as_bytes()).unwrap();
+    let mut scope = Scope::new();
+    scope.push_constant("FILE", shared_file.clone());
+
+    // Chunks stop before a character that doesn't fit and the next one starts with it.
+    assert_eq!(
+        engine
+            .eval_with_scope::<rhai::Array>(
+                &mut scope,
+                r#"
+                FILE.seek(0);
+                let chunks = [];
+                loop {
+                    let chunk = FILE.read_string(4);
+                    if chunk.is_empty() { break; }
+                    chunks.push(chunk);
+                }
+                chunks
+            "#
+            )?
+            .into_iter()
+            .map(|v| v.into_string().unwrap())
+            .collect::<Vec<_>>(),
+        vec!["aé", "€", "😀", "b"]
+    );
+
+    // The engine limit is treated the same way.
+    assert_eq!(
+        engine
+            .set_max_string_size(2)
+            .eval_with_scope::<String>(&mut scope, r#"FILE.seek(0); FILE.read_string()"#)?,
+        "a"
+    );
+    assert_eq!(
+        engine.eval_with_scope::<rhai::INT>(&mut scope, r#"FILE.position()"#)?,
+        1
+    );
+
+    // A length too short for a single character throws and leaves the position unchanged.
+    assert!(engine
+        .set_max_string_size(0)
+        .eval_with_scope::<String>(&mut scope, r#"FILE.seek(1); FILE.read_string(1)"#)
+        .is_err());
+    assert_eq!(
+        engine.eval_with_scope::<rhai::INT>(&mut scope, r#"FILE.position()"#)?,
+        1
+    );
+
+    // UTF-16 surrogate pairs aren't split either.
+    assert_eq!(
+        engine.eval_with_scope::<String>(
+            &mut scope,
+            r#"FILE.seek(0); FILE.write("a😀", "utf-16le"); FILE.seek(0); FILE.read_string(4, "utf-16le")"#
+        )?,
+        "a"
+    );
+
+    // Reads after the first keep the byte order of a big endian BOM.
+    borrow_mut(&shared_file).set_len(0).unwrap();
+    borrow_mut(&shared_file).seek(SeekFrom::Start(0)).unwrap();
+    let bytes: Vec<u8> = "\u{FEFF}héllo"
+        .encode_utf16()
+        .flat_map(u16::to_be_bytes)
+        .collect();
+    let _ = borrow_mut(&shared_file).write(&bytes).unwrap();
+    assert_eq!(
+        engine.eval_with_scope::<String>(
+            &mut scope,
+            r#"FILE.seek(0); FILE.read_string(4, "utf-16") + "|" + FILE.read_string(4, "utf-16") + "|" + FILE.read_string("utf-16")"#
+        )?,
+        "h|él|lo"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_copying_file() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    // Register our filesystem package.
+    let package = FilesystemPackage::new();
+    package.register_into_engine(&mut engine);
+
+    let src = Shared::new(Locked::new(tempfile::tempfile().unwrap()));
+    let _ = borrow_mut(&src).write(b"0123456789").unwrap();
+    let dst = Shared::new(Locked::new(tempfile::tempfile().unwrap()));
+    let mut scope = Scope::new();
+    scope.push_constant("SRC", src);
+    scope.push_constant("DST", dst.clone());
+
+    // Copies start at the stream positions and stop at the length or EOF.
+    assert_eq!(
+        engine.eval_with_scope::<rhai::INT>(&mut scope, r#"SRC.seek(2); SRC.copy_to(DST, 3)"#)?,
+        3
+    );
+    assert_eq!(
+        engine.eval_with_scope::<rhai::INT>(&mut scope, r#"SRC.seek(8); copy_stream(SRC, DST)"#)?,
+        2
+    );
+    assert_eq!(
+        engine.eval_with_scope::<rhai::INT>(&mut scope, r#"SRC.copy_to(DST, 5)"#)?,
+        0
+    );
+
+    let mut buf = String::new();
+    borrow_mut(&dst).seek(SeekFrom::Start(0)).unwrap();
+    borrow_mut(&dst).read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "23489");
+
+    // Copying a handle to itself throws rather than deadlocking.
+    assert!(engine
+        .eval_with_scope::<rhai::INT>(&mut scope, r#"copy_stream(SRC, SRC)"#)
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_reading_lines_file() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    // Register our filesystem package.
+    let package = FilesystemPackage::new();
+    package.register_into_engine(&mut engine);
+
+    let file = Shared::new(Locked::new(tempfile::tempfile().unwrap()));
+    let long = "x".repeat(1000);
+    write!(borrow_mut(&file), "first\n{long}\nlast").unwrap();
+    borrow_mut(&file).seek(SeekFrom::Start(0)).unwrap();
+    let mut scope = Scope::new();
+    scope.push_constant("FILE", file);
+
+    // Lines keep their newline, the last one may lack it, and EOF returns an empty string.
+    assert_eq!(
+        engine.eval_with_scope::<String>(&mut scope, r#"FILE.read_line()"#)?,
+        "first\n"
+    );
+    assert_eq!(
+        engine.eval_with_scope::<String>(&mut scope, r#"FILE.read_line()"#)?,
+        format!("{long}\n")
+    );
+    assert_eq!(
+        engine.eval_with_scope::<rhai::INT>(&mut scope, r#"FILE.position()"#)?,
+        1007
+    );
+    assert_eq!(
+        engine.eval_with_scope::<String>(&mut scope, r#"FILE.read_line()"#)?,
+        "last"
+    );
+    assert_eq!(
+        engine.eval_with_scope::<String>(&mut scope, r#"FILE.read_line()"#)?,
+        ""
+    );
+
+    // Lines longer than the maximum string size are returned in pieces.
+    engine.set_max_string_size(600);
+    assert_eq!(
+        engine.eval_with_scope::<String>(&mut scope, r#"FILE.seek(6); FILE.read_line()"#)?,
+        "x".repeat(600)
+    );
+    assert_eq!(
+        engine.eval_with_scope::<String>(&mut scope, r#"FILE.read_line()"#)?,
+        format!("{}\n", "x".repeat(400))
+    );
+
+    Ok(())
+}
@@ -0,0 +1,263 @@
+use rhai::{Array, EvalAltResult, Map};
+
+mod common;
+use common::engine_in;
+
+/// A small source tree to search.
+const FILES: &[(&str, &[u8])] = &[
+    (
+        "src/a.rs",
+        b"// TODO: first\nfn main() {}\n// todo: second\r\n",
+    ),
+    ("src/sub/b.rs", b"let x = 1; // TODO\n"),
+    ("src/sub/notes.txt", b"TODO later"),
+    ("src/data.bin", b"TODO\0binary"),
+];
+
+/// Formats the matches as `path:line_no:match`, joining paths with `/`.
+fn summary(matches: Array) -> Vec<String> {
+    matches
+        .into_iter()
+        .map(|m| {
+            let m = m.cast::<Map>();
+            let path = m["path"].clone().cast::<std::path::PathBuf>();
+            let path = path
+                .to_string_lossy()
+                .replace(std::path::MAIN_SEPARATOR, "/");
+            format!("{path}:{}:{}", m["line_no"], m["match"])
+        })
+        .collect()
+}
+
+#[test]
+fn test_grep_plain() -> Result<(), Box<EvalAltResult>> {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = engine_in(&dir, FILES);
+
+    assert_eq!(
+        summary(engine.eval::<Array>(r#"grep("src", "TODO")"#)?),
+        [
+            "src/a.rs:1:TODO",
+            "src/sub/b.rs:1:TODO",
+            "src/sub/notes.txt:1:TODO"
+        ]
+    );
+    assert_eq!(
+        summary(engine.eval::<Array>(r#"grep("src/a.rs", "todo", #{ ignore_case: true })"#)?),
+        ["src/a.rs:1:TODO", "src/a.rs:3:todo"]
+    );
+
+    // Lines are returned without their line ending.
+    let line = engine.eval::<String>(r#"grep("src/a.rs", "second")[0].line"#)?;
+    assert_eq!(line, "// todo: second");
+
+    assert_eq!(
+        summary(
+            engine.eval::<Array>(
+                r#"grep(path("src"), "TODO", #{ recursive: false, binary: true })"#
+            )?
+        ),
+        ["src/a.rs:1:TODO", "src/data.bin:1:TODO"]
+    );
+    assert_eq!(
+        summary(
+            engine
+                .eval::<Array>(r#"grep("src", "TODO", #{ exclude: ["sub"], max_matches: 1 })"#)?
+        ),
+        ["src/a.rs:1:TODO"]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_grep_glob() -> Result<(), Box<EvalAltResult>> {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = engine_in(&dir, FILES);
+
+    assert_eq!(
+        summary(engine.eval::<Array>(r#"grep("src/**/*.rs", "TODO")"#)?),
+        ["src/a.rs:1:TODO", "src/sub/b.rs:1:TODO"]
+    );
+    assert_eq!(
+        summary(engine.eval::<Array>(r#"grep("src/*.rs", "TODO")"#)?),
+        ["src/a.rs:1:TODO"]
+    );
+    assert_eq!(
+        summary(engine.eval::<Array>(r#"grep("*/sub/*.txt", "later")"#)?),
+        ["src/sub/notes.txt:1:later"]
+    );
+
+    Ok(())
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn test_grep_regex() -> Result<(), Box<EvalAltResult>> {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = engine_in(&dir, FILES);
+
+    assert_eq!(
+        summary(engine.eval::<Array>(
+            r#"grep("src/a.rs", "todo: \\w+", #{ regex: true, ignore_case: true })"#
+        )?),
+        ["src/a.rs:1:TODO: first", "src/a.rs:3:todo: second"]
+    );
+    assert!(engine
+        .eval::<Array>(r#"grep("src", "(", #{ regex: true })"#)
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_grep_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = engine_in(&dir, FILES);
+
+    assert!(engine.eval::<Array>(r#"grep("missing", "TODO")"#).is_err());
+    assert!(engine
+        .eval::<Array>(r#"grep("src", "TODO", #{ case: true })"#)
+        .is_err());
+    assert!(engine
+        .eval::<Array>(r#"grep("src", "TODO", #{ max_matches: -1 })"#)
+        .is_err());
+    #[cfg(not(feature = "regex"))]
+    assert!(engine
+        .eval::<Array>(r#"grep("src", "TODO", #{ regex: true })"#)
+        .is_err());
+}
+
+#[test]
+fn test_replace_in_file() -> Result<(), Box<EvalAltResult>> {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = engine_in(&dir, FILES);
+    let file = dir.path().join("src").join("a.rs");
+
+    let report = engine.eval::<Map>(
+        r#"replace_in_file("src/a.rs", "todo", "DONE", #{ ignore_case: true, dry_run: true })"#,
+    )?;
+    assert_eq!(report["replacements"].as_int(), Ok(2));
+    #[cfg(not(feature = "diff"))]
+    assert!(!report.contains_key("diff"));
+    #[cfg(feature = "diff")]
+    assert_eq!(
+        report["diff"].clone().into_string().unwrap(),
+        "--- src/a.rs\n+++ src/a.rs\n@@ -1,3 +1,3 @@\n-// TODO: first\n+// DONE: first\n fn main() {}\n-// todo: second\r\n+// DONE: second\r\n"
+    );
+    assert!(std::fs::read_to_string(&file)
+        .unwrap()
+        .starts_with("// TODO"));
+
+    let report = engine.eval::<Map>(r#"replace_in_file("src/a.rs", "TODO", "DONE")"#)?;
+    assert_eq!(report["replacements"].as_int(), Ok(1));
+    assert!(!report.contains_key("diff"));
+    assert_eq!(
+        std::fs::read_to_string(&file).unwrap(),
+        "// DONE: first\nfn main() {}\n// todo: second\r\n"
+    );
+
+    let report = engine.eval::<Map>(r#"replace_in_file(path("src/a.rs"), "missing", "x")"#)?;
+    assert_eq!(report["replacements"].as_int(), Ok(0));
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        // Permissions are kept and symlinks are followed.
+        std::fs::set_permissions(&file, std::fs::Permissions::from_mode(0o750)).unwrap();
+        std::os::unix::fs::symlink("a.rs", dir.path().join("src").join("link.rs")).unwrap();
+        engine.eval::<Map>(r#"replace_in_file("src/link.rs", "DONE", "TODO")"#)?;
+        let meta = std::fs::symlink_metadata(dir.path().join("src").join("link.rs")).unwrap();
+        assert!(meta.is_symlink());
+        assert!(std::fs::read_to_string(&file)
+            .unwrap()
+            .starts_with("// TODO"));
+        let mode = std::fs::metadata(&file).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o750);
+    }
+
+    // No temporary files are left behind.
+    assert_eq!(
+        std::fs::read_dir(dir.path().join("src")).unwrap().count(),
+        if cfg!(unix) { 4 } else { 3 }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_replace_in_files() -> Result<(), Box<EvalAltResult>> {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = engine_in(&dir, FILES);
+
+    let reports = engine.eval::<Array>(r#"replace_in_files("src/**/*.rs", "TODO", "FIXME")"#)?;
+    assert_eq!(summary_reports(reports), ["src/a.rs:1", "src/sub/b.rs:1"]);
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("src").join("sub").join("b.rs")).unwrap(),
+        "let x = 1; // FIXME\n"
+    );
+
+    // Binary files are skipped.
+    let reports =
+        engine.eval::<Array>(r#"replace_in_files("src", "TODO", "x", #{ recursive: false })"#)?;
+    assert!(reports.is_empty());
+    let reports = engine
+        .eval::<Array>(r#"replace_in_files(path("src"), "FIXME", "TODO", #{ dry_run: true })"#)?;
+    assert_eq!(summary_reports(reports), ["src/a.rs:1", "src/sub/b.rs:1"]);
+    assert!(std::fs::read_to_string(dir.path().join("src").join("a.rs"))
+        .unwrap()
+        .contains("FIXME"));
+
+    // So are files that aren't UTF-8, such as Latin-1 text, rather than stopping halfway through.
+    let latin1 = dir.path().join("src").join("b.rs");
+    std::fs::write(&latin1, b"// FIXME caf\xe9\n").unwrap();
+    let reports = engine.eval::<Array>(r#"replace_in_files("src/**/*.rs", "FIXME", "DONE")"#)?;
+    assert_eq!(summary_reports(reports), ["src/a.rs:1", "src/sub/b.rs:1"]);
+    assert_eq!(std::fs::read(&latin1).unwrap(), b"// FIXME caf\xe9\n");
+    assert!(engine
+        .eval::<Map>(r#"replace_in_file("src/b.rs", "FIXME", "DONE")"#)
+        .is_err());
+
+    assert!(engine
+        .eval::<Array>(r#"replace_in_files("src", "", "x")"#)
+        .is_err());
+    assert!(engine
+        .eval::<Array>(r#"replace_in_files("src", "x", "y", #{ max_matches: 1 })"#)
+        .is_err());
+
+    Ok(())
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn test_replace_in_file_regex() -> Result<(), Box<EvalAltResult>> {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = engine_in(&dir, FILES);
+
+    let report = engine.eval::<Map>(
+        r#"replace_in_file("src/a.rs", "(?i)todo: (?<what>\\w+)", "[${what}] $$1", #{ regex: true })"#,
+    )?;
+    assert_eq!(report["replacements"].as_int(), Ok(2));
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("src").join("a.rs")).unwrap(),
+        "// [first] $1\nfn main() {}\n// [second] $1\r\n"
+    );
+
+    Ok(())
+}
+
+/// Formats replacement reports as `path:replacements`, joining paths with `/`.
+fn summary_reports(reports: Array) -> Vec<String> {
+    reports
+        .into_iter()
+        .map(|r| {
+            let r = r.cast::<Map>();
+            let path = r["path"].clone().cast::<std::path::PathBuf>();
+            let path = path
+                .to_string_lossy()
+                .replace(std::path::MAIN_SEPARATOR, "/");
+            format!("{path}:{}", r["replacements"])
+        })
+        .collect()
+}
@@ -0,0 +1,137 @@
+#![cfg(any(
+    feature = "sha256",
+    feature = "sha1",
+    feature = "md5",
+    feature = "blake3",
+    feature = "crc32"
+))]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rhai::{EvalAltResult, Scope};
+
+mod common;
+use common::engine_in;
+
+/// Files hashed by the tests.
+const FILES: &[(&str, &[u8])] = &[("abc.txt", b"abc")];
+
+#[test]
+fn test_hash_file() -> Result<(), Box<EvalAltResult>> {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = engine_in(&dir, FILES);
+
+    let expected = [
+        #[cfg(feature = "sha256")]
+        (
+            "sha256",
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+        ),
+        #[cfg(feature = "sha1")]
+        ("sha1", "a9993e364706816aba3e25717850c26c9cd0d89d"),
+        #[cfg(feature = "md5")]
+        ("md5", "900150983cd24fb0d6963f7d28e17f72"),
+        #[cfg(feature = "blake3")]
+        (
+            "blake3",
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85",
+        ),
+        #[cfg(feature = "crc32")]
+        ("crc32", "352441c2"),
+    ];
+
+    for (algo, digest) in expected {
+        let mut scope = Scope::new();
+        scope.push_constant("ALGO", algo);
+        assert_eq!(
+            engine.eval_with_scope::<String>(&mut scope, r#"hash_file("abc.txt", ALGO)"#)?,
+            digest
+        );
+        assert_eq!(
+            engine.eval_with_scope::<String>(
+                &mut scope,
+                r#"let f = open_file("abc.txt", "r"); f.seek(1); f.hash(ALGO.to_upper())"#
+            )?,
+            engine.eval_with_scope::<String>(
+                &mut scope,
+                r#"open_file("bc.txt", "w+").write("bc"); hash_file("bc.txt", ALGO)"#
+            )?
+        );
+    }
+
+    assert!(engine
+        .eval::<String>(r#"hash_file("abc.txt", "sha512")"#)
+        .is_err());
+
+    // Hashing a large file gives the progress callback a chance to stop it between chunks.
+    let (algo, _) = expected[0];
+    std::fs::write(dir.path().join("big.bin"), vec![0; 4 * 1024 * 1024]).unwrap();
+    let calls = AtomicUsize::new(0);
+    let mut engine = engine;
+    engine.on_progress(move |_| {
+        (calls.fetch_add(1, Ordering::Relaxed) > 32).then(|| "stopped".into())
+    });
+    let result = engine.eval::<String>(&format!(r#"hash_file("big.bin", "{algo}")"#));
+    assert!(matches!(
+        *result.unwrap_err(),
+        EvalAltResult::ErrorTerminated(..)
+    ));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "sha256")]
+fn test_verify_checksum_file() -> Result<(), Box<EvalAltResult>> {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = engine_in(&dir, FILES);
+
+    let sub = dir.path().join("sub");
+    std::fs::create_dir(&sub).unwrap();
+    std::fs::write(sub.join("abc.txt"), "abc").unwrap();
+    std::fs::write(sub.join("other.txt"), "other").unwrap();
+    std::fs::write(
+        sub.join("SHA256SUMS"),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad  abc.txt\n\
+         ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad *other.txt\n\
+         ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad  gone.txt\n",
+    )
+    .unwrap();
+
+    // Listed paths are resolved against the manifest's directory.
+    assert_eq!(
+        engine.eval::<String>(
+            r#"
+                let result = verify_checksum_file("sub/SHA256SUMS");
+                `${result.ok} ${result.checked} ${result.failed} ${result.missing}`
+            "#
+        )?,
+        r#"false 3 ["other.txt"] ["gone.txt"]"#
+    );
+    std::fs::write(
+        sub.join("SHA256SUMS"),
+        "BA7816BF8F01CFEA414140DE5DAE2223B00361A396177A9CB410FF61F20015AD  abc.txt\n",
+    )
+    .unwrap();
+    assert!(engine.eval::<bool>(r#"verify_checksum_file("sub/SHA256SUMS").ok"#)?);
+
+    std::fs::write(dir.path().join("BAD"), "not a checksum line\n").unwrap();
+    assert!(engine
+        .eval::<rhai::Map>(r#"verify_checksum_file("BAD")"#)
+        .is_err());
+
+    // Listed paths can't lead outside the manifest's directory.
+    let digest = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+    let absolute = dir.path().join("abc.txt");
+    for listed in ["../abc.txt", "./../abc.txt", absolute.to_str().unwrap()] {
+        std::fs::write(sub.join("SHA256SUMS"), format!("{digest}  {listed}\n")).unwrap();
+        assert!(
+            engine
+                .eval::<rhai::Map>(r#"verify_checksum_file("sub/SHA256SUMS")"#)
+                .is_err(),
+            "{listed}"
+        );
+    }
+
+    Ok(())
+}
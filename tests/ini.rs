@@ -0,0 +1,132 @@
+use rhai::EvalAltResult;
+
+mod common;
+use common::{engine_in, read};
+
+const CONFIG: &str = "; global settings
+name = legacy
+
+[server]
+# where to listen
+host = localhost
+port: 8080
+motd = \"  hello  \"
+
+[paths]
+root=/srv
+";
+
+#[test]
+fn test_read_ini() -> Result<(), Box<EvalAltResult>> {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = engine_in(&dir, &[]);
+    std::fs::write(dir.path().join("app.ini"), CONFIG).unwrap();
+
+    let summary = engine.eval::<String>(
+        r#"let c = read_ini("app.ini"); `${c[""].name} ${c.server.host} ${c.server.port} [${c.server.motd}] ${c.paths.root} ${c.len()}`"#,
+    )?;
+    assert_eq!(summary, "legacy localhost 8080 [  hello  ] /srv 3");
+
+    assert!(engine.run(r#"read_ini(path("missing.ini"))"#).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_write_ini() -> Result<(), Box<EvalAltResult>> {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = engine_in(&dir, &[]);
+    std::fs::write(dir.path().join("app.ini"), CONFIG).unwrap();
+
+    // Writing back what was read leaves the file untouched.
+    engine.run(r#"write_ini("app.ini", read_ini("app.ini"))"#)?;
+    assert_eq!(read(&dir, "app.ini"), CONFIG);
+
+    engine.run(
+        r#"
+            let c = read_ini("app.ini");
+            c.server.port = 9090;
+            c.server.remove("motd");
+            c.server.timeout = 30;
+            c.remove("paths");
+            c.log = #{ level: "debug", file: () };
+            write_ini(path("app.ini"), c);
+        "#,
+    )?;
+    assert_eq!(
+        read(&dir, "app.ini"),
+        "; global settings
+name = legacy
+
+[server]
+# where to listen
+host = localhost
+port: 9090
+timeout = 30
+
+[log]
+file =
+level = debug
+"
+    );
+
+    engine.run(r#"write_ini("new.ini", #{ "": #{ a: " x" }, s: #{ b: 1 } })"#)?;
+    assert_eq!(read(&dir, "new.ini"), "a = \" x\"\n\n[s]\nb = 1\n");
+
+    assert!(engine.run(r#"write_ini("new.ini", #{ s: 1 })"#).is_err());
+    assert!(engine
+        .run(r#"write_ini("new.ini", #{ s: #{ a: "x\ny" } })"#)
+        .is_err());
+    assert!(engine
+        .run(r#"write_ini("new.ini", #{ s: #{ "a=b": 1 } })"#)
+        .is_err());
+
+    // Keys and section names that wouldn't read back are rejected rather than lost.
+    for key in ["#k", ";j", "[x]"] {
+        let script = format!(r#"write_ini("new.ini", #{{ s: #{{ "{key}": "1", ok: "3" }} }})"#);
+        assert!(engine.run(&script).is_err(), "{key}");
+    }
+    for name in ["x\\ny", "a]b", " s"] {
+        let script = format!(r#"write_ini("new.ini", #{{ "{name}": #{{ a: 1 }} }})"#);
+        assert!(engine.run(&script).is_err(), "{name}");
+    }
+    assert_eq!(read(&dir, "new.ini"), "a = \" x\"\n\n[s]\nb = 1\n");
+
+    Ok(())
+}
+
+#[test]
+fn test_read_dotenv() -> Result<(), Box<EvalAltResult>> {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = engine_in(&dir, &[]);
+    std::fs::write(
+        dir.path().join(".env"),
+        r#"# database
+export DB_HOST=localhost
+DB_PORT = 5432 # default port
+PASSWORD='p#ss $word\n'
+GREETING="hello\tworld \"quoted\"\n"
+MULTI="first
+second"
+EMPTY=
+"#,
+    )
+    .unwrap();
+
+    let env = engine.eval::<rhai::Map>(r#"read_dotenv(".env")"#)?;
+    let get = |key: &str| env[key].clone().into_string().unwrap();
+    assert_eq!(env.len(), 6);
+    assert_eq!(get("DB_HOST"), "localhost");
+    assert_eq!(get("DB_PORT"), "5432");
+    assert_eq!(get("PASSWORD"), "p#ss $word\\n");
+    assert_eq!(get("GREETING"), "hello\tworld \"quoted\"\n");
+    assert_eq!(get("MULTI"), "first\nsecond");
+    assert_eq!(get("EMPTY"), "");
+
+    for bad in ["NO_VALUE", "1KEY=x", "KEY=\"open", "KEY=\"x\" trailing"] {
+        std::fs::write(dir.path().join("bad.env"), bad).unwrap();
+        assert!(engine.run(r#"read_dotenv(path("bad.env"))"#).is_err());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,48 @@
+use rhai::{packages::Package, Engine, EvalAltResult, Scope};
+use rhai_fs::FilesystemPackage;
+
+#[test]
+fn test_lockfile() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    // Register our filesystem package.
+    let package = FilesystemPackage::new();
+    package.register_into_engine(&mut engine);
+
+    let dir = tempfile::tempdir().unwrap();
+    let lock_path = dir.path().join("job.lock");
+    let mut scope = Scope::new();
+    scope.push_constant("PATH", lock_path.clone());
+
+    // The lock file records our PID while held.
+    engine.run_with_scope(&mut scope, r#"let lock = acquire_lockfile(PATH);"#)?;
+    let content = std::fs::read_to_string(&lock_path).unwrap();
+    assert_eq!(
+        content.lines().next(),
+        Some(std::process::id().to_string().as_str())
+    );
+
+    // Only one guard can hold it.
+    assert_eq!(
+        engine.eval_with_scope::<String>(
+            &mut scope,
+            r#"let kind = ""; try { acquire_lockfile(PATH) } catch (e) { kind = e.kind } kind"#
+        )?,
+        "would_block"
+    );
+
+    // Releasing removes the file.
+    assert!(engine.eval_with_scope::<bool>(&mut scope, r#"lock.release() && !lock.is_held"#)?);
+    assert!(!lock_path.exists());
+
+    // Dropping the guard removes the file.
+    engine.run_with_scope(&mut scope, r#"{ let lock = acquire_lockfile(PATH); }"#)?;
+    assert!(!lock_path.exists());
+
+    // Stale lock files from exited processes are taken over.
+    std::fs::write(&lock_path, "999999\n0\n").unwrap();
+    assert!(engine.eval_with_scope::<bool>(&mut scope, r#"acquire_lockfile(PATH).is_held"#)?);
+    assert!(!lock_path.exists());
+
+    Ok(())
+}
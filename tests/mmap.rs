@@ -0,0 +1,109 @@
+#![cfg(feature = "mmap")]
+
+use std::io::Write;
+
+use rhai::{packages::Package, Engine, EvalAltResult, Scope};
+use rhai_fs::FilesystemPackage;
+
+#[test]
+fn test_mmap() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    // Register our filesystem package.
+    let package = FilesystemPackage::new();
+    package.register_into_engine(&mut engine);
+
+    let mut temp = tempfile::NamedTempFile::new().unwrap();
+    temp.write_all(b"header\0\x01\x02payload\x01\x02").unwrap();
+    let mut scope = Scope::new();
+    scope.push_constant("PATH", temp.path().to_path_buf());
+    engine.run_with_scope(&mut scope, r#"let map = mmap_file(PATH);"#)?;
+
+    assert_eq!(
+        engine.eval_with_scope::<rhai::INT>(&mut scope, r#"map.len"#)?,
+        18
+    );
+    assert_eq!(
+        engine.eval_with_scope::<rhai::INT>(&mut scope, r#"map[-1]"#)?,
+        2
+    );
+    assert!(engine
+        .eval_with_scope::<rhai::INT>(&mut scope, r#"map[18]"#)
+        .is_err());
+    assert_eq!(
+        engine.eval_with_scope::<String>(&mut scope, r#"type_of(map)"#)?,
+        "MappedFile"
+    );
+
+    // Slices are clamped like `Blob::extract`.
+    #[cfg(not(feature = "no_index"))]
+    {
+        assert_eq!(
+            engine.eval_with_scope::<rhai::Blob>(&mut scope, r#"map.extract(0..6)"#)?,
+            b"header"
+        );
+        assert_eq!(
+            engine.eval_with_scope::<rhai::Blob>(&mut scope, r#"map.extract(-2, 10)"#)?,
+            &[1, 2]
+        );
+        assert_eq!(
+            engine.eval_with_scope::<rhai::Blob>(&mut scope, r#"map.extract(16..=20)"#)?,
+            &[1, 2]
+        );
+
+        // Extreme bounds are clamped rather than overflowing.
+        scope.push_constant("MIN", rhai::INT::MIN + 1);
+        scope.push_constant("MAX", rhai::INT::MAX);
+        for script in [
+            "map.extract(5..MIN)",
+            "map.extract(5..=MIN)",
+            "map.extract(MIN, MIN)",
+            "map.extract(MAX..MAX)",
+            "map.extract(MAX..=MAX)",
+        ] {
+            assert!(engine
+                .eval_with_scope::<rhai::Blob>(&mut scope, script)?
+                .is_empty());
+        }
+        assert_eq!(
+            engine
+                .eval_with_scope::<rhai::Blob>(&mut scope, "map.extract(MIN..=MAX)")?
+                .len(),
+            18
+        );
+        assert_eq!(
+            engine
+                .eval_with_scope::<rhai::Blob>(&mut scope, "map.extract(MIN, MAX)")?
+                .len(),
+            18
+        );
+
+        // Searching for a pattern.
+        assert_eq!(
+            engine.eval_with_scope::<rhai::INT>(
+                &mut scope,
+                r#"let pattern = blob(); pattern.push(1); pattern.push(2); map.find(pattern)"#
+            )?,
+            7
+        );
+        assert_eq!(
+            engine.eval_with_scope::<rhai::INT>(&mut scope, r#"map.find(pattern, 8)"#)?,
+            16
+        );
+        assert_eq!(
+            engine.eval_with_scope::<rhai::INT>(
+                &mut scope,
+                r#"pattern.push(3); map.find(pattern)"#
+            )?,
+            -1
+        );
+
+        // Slices larger than the max array size throw.
+        assert!(engine
+            .set_max_array_size(4)
+            .eval_with_scope::<rhai::Blob>(&mut scope, r#"map.extract(0..6)"#)
+            .is_err());
+    }
+
+    Ok(())
+}
@@ -1,20 +1,20 @@
-use std::env::current_dir;
 use std::path::{Path, PathBuf};
 
 use rhai::{packages::Package, Engine, EvalAltResult, Scope};
-use rhai_fs::FilesystemPackage;
+use rhai_fs::{FilesystemConfig, FilesystemPackage};
 
 #[test]
 fn test_path() -> Result<(), Box<EvalAltResult>> {
     let mut engine = Engine::new();
 
-    // Register our filesystem package.
-    let package = FilesystemPackage::new();
+    // Register our filesystem package, working in the fixtures directory.
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let package = FilesystemPackage::with_config(FilesystemConfig {
+        cwd: Some(fixtures.clone()),
+        ..Default::default()
+    });
     package.register_into_engine(&mut engine);
 
-    std::env::set_current_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures"))
-        .unwrap();
-
     // Add two paths.
     let path_one = PathBuf::from("bar");
     let mut scope = Scope::new();
@@ -42,11 +42,7 @@ fn test_path() -> Result<(), Box<EvalAltResult>> {
             &mut scope,
             r#"PATH_ONE.canonicalize() + path("foo.txt")"#
         )?,
-        current_dir()
-            .unwrap()
-            .join("bar/foo.txt")
-            .canonicalize()
-            .unwrap()
+        fixtures.join("bar/foo.txt").canonicalize().unwrap()
     );
 
     // Append a path.
@@ -70,3 +66,177 @@ fn test_path() -> Result<(), Box<EvalAltResult>> {
 
     Ok(())
 }
+
+#[test]
+fn test_path_compare() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    // Register our filesystem package.
+    let package = FilesystemPackage::new();
+    package.register_into_engine(&mut engine);
+
+    // Paths compare component-wise, with each other and with strings.
+    assert!(engine.eval::<bool>(r#"path("bar/foo.txt") == path("bar//foo.txt")"#)?);
+    assert!(engine.eval::<bool>(r#"path("bar/foo.txt") == "bar/./foo.txt""#)?);
+    assert!(engine.eval::<bool>(r#"path("bar") != "baz""#)?);
+    assert!(engine.eval::<bool>(r#"path("bar") < path("bar/foo.txt")"#)?);
+    assert!(engine.eval::<bool>(r#"path("baz") >= "bar/foo.txt""#)?);
+
+    // Component-wise prefix and suffix checks.
+    assert!(engine.eval::<bool>(r#"path("bar/foo.txt").starts_with("bar")"#)?);
+    assert!(!engine.eval::<bool>(r#"path("barn/foo.txt").starts_with(path("bar"))"#)?);
+    assert!(engine.eval::<bool>(r#"path("bar/foo.txt").ends_with("foo.txt")"#)?);
+    assert!(!engine.eval::<bool>(r#"path("bar/foo.txt").ends_with("o.txt")"#)?);
+
+    // Arrays of paths can be sorted and searched.
+    assert_eq!(
+        engine
+            .eval::<rhai::Array>(
+                r#"let x = [path("c"), path("a/b"), path("a")]; x.sort(|a, b| a < b); x"#
+            )?
+            .into_iter()
+            .map(|p| p.cast::<PathBuf>())
+            .collect::<Vec<_>>(),
+        vec![PathBuf::from("a"), PathBuf::from("a/b"), PathBuf::from("c")]
+    );
+    assert!(engine.eval::<bool>(r#"[path("a"), path("b")].contains(path("b"))"#)?);
+
+    Ok(())
+}
+
+#[test]
+fn test_path_normalize() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    // Register our filesystem package.
+    let package = FilesystemPackage::new();
+    package.register_into_engine(&mut engine);
+
+    // Lexical normalization doesn't need the path to exist.
+    assert_eq!(
+        engine.eval::<PathBuf>(r#"path("a/./b/../../c/missing.txt").normalize()"#)?,
+        PathBuf::from("c/missing.txt")
+    );
+    assert_eq!(
+        engine.eval::<PathBuf>(r#"path("../a/..").normalize()"#)?,
+        PathBuf::from("..")
+    );
+    assert_eq!(
+        engine.eval::<PathBuf>(r#"path("a/..").normalize()"#)?,
+        PathBuf::from(".")
+    );
+
+    // Prefixes can be stripped.
+    assert_eq!(
+        engine.eval::<PathBuf>(r#"path("bar/foo.txt").strip_prefix("bar")"#)?,
+        PathBuf::from("foo.txt")
+    );
+    assert!(engine
+        .eval::<PathBuf>(r#"path("bar/foo.txt").strip_prefix("baz")"#)
+        .is_err());
+
+    // Relative paths between two paths.
+    assert_eq!(
+        engine.eval::<PathBuf>(r#"path("a/c/d").relative_to("a/b")"#)?,
+        PathBuf::from("../c/d")
+    );
+    assert_eq!(
+        engine.eval::<PathBuf>(r#"path("a/b").relative_to(path("a/b"))"#)?,
+        PathBuf::from(".")
+    );
+
+    // Absolute paths are joined onto the current working directory.
+    assert!(engine.eval::<bool>(
+        r#"let p = path("missing/../foo.txt").absolute(); p.is_absolute && p.ends_with("foo.txt") && !p.ends_with("missing/foo.txt")"#
+    )?);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(all(unix, not(feature = "no_index")))]
+fn test_path_non_utf8() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    // Register our filesystem package.
+    let package = FilesystemPackage::new();
+    package.register_into_engine(&mut engine);
+
+    // A Latin-1 encoded file name.
+    let mut scope = Scope::new();
+    scope.push_constant("BYTES", b"caf\xe9.txt".to_vec());
+
+    // Converting to a string is lossy, never empty.
+    assert_eq!(
+        engine.eval_with_scope::<String>(&mut scope, r#"path_from_bytes(BYTES).to_string()"#)?,
+        "caf\u{FFFD}.txt"
+    );
+    assert_eq!(
+        engine.eval_with_scope::<String>(&mut scope, r#"path_from_bytes(BYTES).to_debug()"#)?,
+        r#""caf\xE9.txt""#
+    );
+
+    // The raw bytes round trip.
+    assert_eq!(
+        engine.eval_with_scope::<rhai::Blob>(&mut scope, r#"path_from_bytes(BYTES).to_bytes()"#)?,
+        b"caf\xe9.txt"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_path_cwd() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    // Register our filesystem package.
+    let package = FilesystemPackage::new();
+    package.register_into_engine(&mut engine);
+
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).to_path_buf();
+
+    // Relative paths are resolved against the virtual working directory.
+    let mut scope = Scope::new();
+    scope.push_constant("ROOT", root.clone());
+    assert_eq!(
+        engine
+            .eval_with_scope::<PathBuf>(&mut scope, r#"set_cwd(ROOT); set_cwd("tests"); cwd()"#)?,
+        root.join("tests")
+    );
+    assert!(engine.eval::<bool>(r#"path("fixtures/oof.txt").is_file"#)?);
+    assert_eq!(engine.eval::<rhai::INT>(r#"open_dir("fixtures").len"#)?, 2);
+
+    // Scoped working directories are restored afterwards, even when an exception is thrown.
+    assert_eq!(
+        engine.eval::<PathBuf>(r#"with_cwd("fixtures/bar", || path("foo.txt").canonicalize())"#)?,
+        root.join("tests/fixtures/bar/foo.txt")
+            .canonicalize()
+            .unwrap()
+    );
+    assert!(engine
+        .eval::<()>(r#"with_cwd("fixtures", || throw "oops")"#)
+        .is_err());
+    assert_eq!(engine.eval::<PathBuf>(r#"cwd()"#)?, root.join("tests"));
+
+    // Only directories can become the working directory.
+    assert!(engine.eval::<()>(r#"set_cwd("fixtures/oof.txt")"#).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_path_type_name() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    // Register our filesystem package.
+    let package = FilesystemPackage::new();
+    package.register_into_engine(&mut engine);
+
+    // Paths use a friendly type name.
+    assert_eq!(
+        engine.eval::<String>(r#"type_of(path("foo.txt"))"#)?,
+        "Path"
+    );
+
+    Ok(())
+}
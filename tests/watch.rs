@@ -0,0 +1,140 @@
+#![cfg(feature = "watch")]
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rhai::{EvalAltResult, Scope};
+
+mod common;
+use common::engine_in;
+
+/// Watches the directory with the options, changes a few files and returns the events as `kind:name` strings.
+fn changes(options: &str) -> Result<(String, Vec<String>), Box<EvalAltResult>> {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("config.toml"), "a = 1").unwrap();
+    std::fs::write(dir.path().join("old.txt"), "").unwrap();
+    std::fs::create_dir(dir.path().join("sub")).unwrap();
+    let engine = engine_in(&dir, &[]);
+
+    let mut scope = Scope::new();
+    engine.run_with_scope(&mut scope, &format!("let w = watch(cwd(), {options});"))?;
+    let backend = engine.eval_with_scope::<String>(&mut scope, "w.backend")?;
+
+    std::fs::write(dir.path().join("config.toml"), "a = 2").unwrap();
+    std::fs::write(dir.path().join("new.txt"), "").unwrap();
+    std::fs::remove_file(dir.path().join("old.txt")).unwrap();
+    std::fs::write(dir.path().join("sub").join("nested.txt"), "").unwrap();
+
+    let mut events = engine
+        .eval_with_scope::<rhai::Array>(&mut scope, "w.poll(5000)")?
+        .into_iter()
+        .map(|event| {
+            let event = event.cast::<rhai::Map>();
+            let path = event["path"].clone().cast::<std::path::PathBuf>();
+            format!(
+                "{}:{}",
+                event["kind"],
+                path.file_name().unwrap().to_string_lossy()
+            )
+        })
+        .collect::<Vec<_>>();
+    events.sort();
+    events.dedup();
+    Ok((backend, events))
+}
+
+#[test]
+fn test_watch_native() -> Result<(), Box<EvalAltResult>> {
+    let (backend, events) = changes(r#"#{ recursive: true, debounce: 200 }"#)?;
+
+    assert!(events.contains(&"modified:config.toml".to_string()));
+    assert!(events.contains(&"created:new.txt".to_string()));
+    assert!(events.contains(&"removed:old.txt".to_string()));
+    if backend == "native" {
+        assert!(events.contains(&"created:nested.txt".to_string()));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_watch_poll() -> Result<(), Box<EvalAltResult>> {
+    let (backend, events) = changes(r#"#{ poll: true, poll_interval: 50, debounce: 300 }"#)?;
+
+    assert_eq!(backend, "poll");
+    assert!(events.contains(&"created:new.txt".to_string()));
+    assert!(events.contains(&"removed:old.txt".to_string()));
+    // Not recursive, so nothing inside `sub` is reported.
+    assert!(!events.iter().any(|e| e.ends_with("nested.txt")));
+
+    Ok(())
+}
+
+#[test]
+fn test_watch_timeout() -> Result<(), Box<EvalAltResult>> {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = engine_in(&dir, &[]);
+
+    let mut scope = Scope::new();
+    engine.run_with_scope(&mut scope, "let w = watch(cwd(), #{ debounce: 1000 });")?;
+
+    // New changes that keep arriving don't hold the poll past its timeout.
+    let stop = Arc::new(AtomicBool::new(false));
+    let flag = stop.clone();
+    let path = dir.path().to_path_buf();
+    let handle = std::thread::spawn(move || {
+        let mut count = 0;
+        // Give up after a few seconds, so a poll that waits for things to quiet down fails rather than hangs.
+        while !flag.load(Ordering::Relaxed) && count < 60 {
+            count += 1;
+            std::fs::write(path.join(format!("{count}.txt")), "").unwrap();
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    });
+
+    let started = Instant::now();
+    let result = engine.eval_with_scope::<rhai::Array>(&mut scope, "w.poll(500)");
+    let elapsed = started.elapsed();
+    stop.store(true, Ordering::Relaxed);
+    handle.join().unwrap();
+
+    assert!(!result?.is_empty());
+    assert!(elapsed < Duration::from_millis(1500), "{elapsed:?}");
+
+    Ok(())
+}
+
+#[test]
+fn test_watch_errors() -> Result<(), Box<EvalAltResult>> {
+    let dir = tempfile::tempdir().unwrap();
+    let mut engine = engine_in(&dir, &[]);
+
+    assert!(engine.run(r#"watch("missing")"#).is_err());
+    assert!(engine.run(r#"watch(cwd(), #{ recursve: true })"#).is_err());
+
+    // Nothing changed, so the timeout elapses with no events.
+    assert!(engine
+        .eval::<rhai::Array>(r#"watch(cwd()).poll(50)"#)?
+        .is_empty());
+
+    // A long wait can be interrupted by the progress callback.
+    let stop = Arc::new(AtomicBool::new(false));
+    let flag = stop.clone();
+    engine.on_progress(move |_| flag.load(Ordering::Relaxed).then(|| "stopped".into()));
+    let handle = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(200));
+        stop.store(true, Ordering::Relaxed);
+    });
+
+    let started = Instant::now();
+    let result = engine.eval::<rhai::Array>(r#"watch(cwd()).poll(60000)"#);
+    handle.join().unwrap();
+    assert!(matches!(
+        *result.unwrap_err(),
+        EvalAltResult::ErrorTerminated(..)
+    ));
+    assert!(started.elapsed() < Duration::from_secs(10));
+
+    Ok(())
+}